@@ -12,6 +12,8 @@ fn main() {
     let relation_dir: PathBuf = appdata_dir.join("Sequel").join("Database").join("Relations");
     let index_dir: PathBuf = appdata_dir.join("Sequel").join("Database").join("Indexes");
     let export_dir: PathBuf = appdata_dir.join("Sequel").join("Database").join("Export");
+    let temp_dir: PathBuf = appdata_dir.join("Sequel").join("Database").join("Temp");
+    let history_dir: PathBuf = appdata_dir.join("Sequel").join("Database").join("History");
 
     // Create the directories (and any necessary parent directories)
     if let Err(e) = fs::create_dir_all(&relation_dir) {
@@ -23,6 +25,12 @@ fn main() {
     if let Err(e) = fs::create_dir_all(&export_dir) {
         panic!("Failed to create export directory: {:?}", e);
     }
+    if let Err(e) = fs::create_dir_all(&temp_dir) {
+        panic!("Failed to create temp directory: {:?}", e);
+    }
+    if let Err(e) = fs::create_dir_all(&history_dir) {
+        panic!("Failed to create history directory: {:?}", e);
+    }
 
     // Ensure build.rs is re-run if it changes
     println!("cargo:rerun-if-changed=build.rs");
@@ -31,6 +39,8 @@ fn main() {
     let relation_path = env::var("RELATION_PATH").unwrap_or_else(|_| relation_dir.to_string_lossy().to_string());
     let index_path = env::var("INDEX_PATH").unwrap_or_else(|_| index_dir.to_string_lossy().to_string());
     let export_path = env::var("EXPORT_PATH").unwrap_or_else(|_| export_dir.to_string_lossy().to_string());
+    let temp_path = env::var("TEMP_PATH").unwrap_or_else(|_| temp_dir.to_string_lossy().to_string());
+    let history_path = env::var("HISTORY_PATH").unwrap_or_else(|_| history_dir.to_string_lossy().to_string());
 
     // Create a config file with the generated paths
     let mut file = File::create("src/config.rs").unwrap();
@@ -40,10 +50,14 @@ fn main() {
         pub const RELATION_PATH: &str = r"{}";
         pub const INDEX_PATH: &str = r"{}";
         pub const EXPORT_PATH: &str = r"{}";
+        pub const TEMP_PATH: &str = r"{}";
+        pub const HISTORY_PATH: &str = r"{}";
         "#,
         relation_path,
         index_path,
-        export_path
+        export_path,
+        temp_path,
+        history_path
     )
     .unwrap();
 }
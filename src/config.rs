@@ -2,4 +2,6 @@
         pub const RELATION_PATH: &str = r"C:\Users\benem\AppData\Local\Sequel\Database\Relations";
         pub const INDEX_PATH: &str = r"C:\Users\benem\AppData\Local\Sequel\Database\Indexes";
         pub const EXPORT_PATH: &str = r"C:\Users\benem\AppData\Local\Sequel\Database\Export";
+        pub const TEMP_PATH: &str = r"C:\Users\benem\AppData\Local\Sequel\Database\Temp";
+        pub const HISTORY_PATH: &str = r"C:\Users\benem\AppData\Local\Sequel\Database\History";
         
\ No newline at end of file
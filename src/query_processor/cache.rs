@@ -0,0 +1,266 @@
+use std::{collections::HashMap, time::Instant};
+
+use crate::{config, structures::{db_err::DBError, relation::{io::{relation_file_path, saved_table_state}, system::is_system_table}}};
+
+use super::{parser::parse_statement, query::{execute_query, Query, QueryResult}, tokenizer::tokenize};
+
+
+/// a table name along with the generation of its file (see `Table::generation()`), used to tell if a cached result is stale.
+/// The generation changes every time the table is saved, however quickly saves follow each other
+type TableVersion = (String, Option<u64>);
+
+
+struct CachedResult {
+    table_versions: Vec<TableVersion>,
+    result: QueryResult,
+    /// when the entry was last used, counted in lookups, so the least recently used entry is evicted first
+    last_used: u64,
+}
+
+
+/// caches the results of read-only queries (SELECT, FILTER, SORT, JOIN), so running the same query
+/// again returns instantly as long as none of the tables it reads from have been saved since.
+///
+/// queries on tables with a TTL are never cached, since their rows expire without the table being saved.
+pub struct QueryCache {
+    entries: HashMap<String, CachedResult>,
+    max_entries: usize,
+    lookups: u64,
+    hits: u64,
+}
+
+
+impl QueryCache {
+
+    pub fn new(max_entries: usize) -> Self {
+        QueryCache { entries: HashMap::new(), max_entries, lookups: 0, hits: 0 }
+    }
+
+
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    pub fn clear(&mut self) { self.entries.clear(); }
+
+    /// how many results have been returned from the cache instead of running the query
+    pub fn hits(&self) -> u64 { self.hits }
+
+
+    /// parses and executes a query, returning the cached result if the same query was run before
+    /// and none of its tables have changed since.
+    pub fn execute(&mut self, command: String) -> Result<QueryResult, DBError> {
+        let start = Instant::now();
+
+        let query = parse_statement(&command)?;
+        let normalized_command = normalize_query(&command)?;
+
+        let tables = match read_only_tables(&query) {
+            Some(t) => t,
+            // anything that modifies data is never cached
+            None => return execute_query(query),
+        };
+        let current_versions = match table_versions(&tables) {
+            Some(versions) => versions,
+            None => return execute_query(query),
+        };
+
+        self.lookups += 1;
+        if let Some(cached) = self.entries.get_mut(&normalized_command) {
+            if cached.table_versions == current_versions {
+                cached.last_used = self.lookups;
+                self.hits += 1;
+                let mut result = cached.result.clone();
+                result.elapsed = start.elapsed();
+                return Ok(result);
+            }
+        }
+
+        let result = execute_query(query)?;
+
+        if result.has_rows() && self.max_entries > 0 {
+            if !self.entries.contains_key(&normalized_command) && self.entries.len() >= self.max_entries {
+                self.evict_least_recently_used();
+            }
+            self.entries.insert(
+                normalized_command,
+                CachedResult { table_versions: current_versions, result: result.clone(), last_used: self.lookups }
+            );
+        }
+
+        Ok(result)
+    }
+
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self.entries
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(command, _)| command.clone());
+        if let Some(command) = oldest {
+            self.entries.remove(&command);
+        }
+    }
+}
+
+
+/// rebuilds the query from its tokens, so that queries which only differ by spacing share a cache entry.
+/// Text and quoted names are kept exactly as written, including any spaces inside them
+fn normalize_query(command: &str) -> Result<String, DBError> {
+    Ok(tokenize(command)?
+        .iter()
+        .map(|token| token.source_text())
+        .collect::<Vec<String>>()
+        .join(" "))
+}
+
+
+/// gives the tables a query reads from, or `None` if the result can't be cached because the query modifies data or reads a system table
+fn read_only_tables(query: &Query) -> Option<Vec<String>> {
+    let tables = match query {
+        Query::SELECT(_, table)        => Some(vec![table.clone()]),
+        Query::ALIASEDSELECT(select)   => Some(
+            std::iter::once(&select.from).chain(select.join.as_ref().map(|j| &j.table)).map(|t| t.table.clone()).collect()
+        ),
+        Query::FILTER(table, _)        => Some(vec![table.clone()]),
+        Query::SORT(table, _, _)       => Some(vec![table.clone()]),
+        Query::JOIN(left, right, _)    => Some(vec![left.clone(), right.clone()]),
+        Query::CROSSJOIN(left, right, _) => Some(vec![left.clone(), right.clone()]),
+        Query::DIFF(old, new, _)       => Some(vec![old.clone(), new.clone()]),
+        _ => None
+    }?;
+
+    // system tables have no file to check for changes, so they are never cached
+    if tables.iter().any(|t| is_system_table(t)) { return None }
+    Some(tables)
+}
+
+
+/// the version of each table, or `None` if any of them has a TTL, so its result can't be cached
+fn table_versions(tables: &[String]) -> Option<Vec<TableVersion>> {
+    let mut versions: Vec<TableVersion> = Vec::new();
+    for table in tables {
+        let state = saved_table_state(&relation_file_path(config::RELATION_PATH, table));
+        if state.is_some_and(|state| state.has_ttl) { return None }
+        versions.push( (table.clone(), state.map(|state| state.generation)) );
+    }
+    Some(versions)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use chrono::Duration;
+
+    use crate::{config, structures::{column::{Column, DataType, FieldValue}, relation::{io::{load_database, relation_file_path, relation_lock_file_name}, table::Table}}};
+
+    use super::{normalize_query, QueryCache};
+
+    /// saves a new table with one row per name, and returns the path of its file
+    fn save_table(table_name: &str, names: &[&str]) -> String {
+        let columns = vec![
+            Column::new("name".to_owned(), DataType::String, false),
+            Column::new("added".to_owned(), DataType::Date, false),
+        ];
+        let mut table = Table::new(table_name.to_owned(), columns, true);
+        for name in names {
+            add_row(&mut table, name);
+        }
+        table.save(config::RELATION_PATH.to_owned()).unwrap();
+        relation_file_path(config::RELATION_PATH, &table.to_file_name())
+    }
+
+    fn remove_table(table_name: &str) {
+        let _ = fs::remove_file(relation_file_path(config::RELATION_PATH, table_name));
+        let _ = fs::remove_file(format!("{}/{}", config::RELATION_PATH, relation_lock_file_name(table_name)));
+    }
+
+    fn add_row(table: &mut Table, name: &str) {
+        let row = HashMap::from([
+            ("name".to_owned(), FieldValue::String(name.to_owned())),
+            ("added".to_owned(), FieldValue::Date(chrono::Utc::now())),
+        ]);
+        table.insert_row(&row).unwrap();
+    }
+
+    #[test]
+    fn spacing_outside_of_literals_is_ignored() {
+        assert_eq!(
+            normalize_query("SELECT  *   FROM orders\nWHERE note = 'a  b'").unwrap(),
+            normalize_query("SELECT * FROM orders WHERE note = 'a  b'").unwrap()
+        );
+        assert_ne!(
+            normalize_query("SELECT * FROM orders WHERE note = 'a  b'").unwrap(),
+            normalize_query("SELECT * FROM orders WHERE note = 'a b'").unwrap()
+        );
+    }
+
+    #[test]
+    fn repeated_queries_are_served_until_the_table_is_saved() {
+        let file_path = save_table("cache test hits", &["ann", "bob"]);
+        let mut cache = QueryCache::new(8);
+
+        let first = cache.execute("SELECT * FROM \"cache test hits\"".to_owned()).unwrap();
+        assert_eq!(first.rows.len(), 2);
+        assert_eq!(cache.hits(), 0);
+
+        let second = cache.execute("SELECT   *  FROM \"cache test hits\"".to_owned()).unwrap();
+        assert_eq!(second.rows.len(), 2);
+        assert_eq!(cache.hits(), 1);
+
+        // a different query misses
+        cache.execute("SELECT name FROM \"cache test hits\"".to_owned()).unwrap();
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.len(), 2);
+
+        // saving the table invalidates what was cached for it
+        let mut table = load_database(&file_path).unwrap();
+        add_row(&mut table, "cy");
+        table.save(config::RELATION_PATH.to_owned()).unwrap();
+
+        let third = cache.execute("SELECT * FROM \"cache test hits\"".to_owned()).unwrap();
+        assert_eq!(third.rows.len(), 3);
+        assert_eq!(cache.hits(), 1);
+
+        remove_table("cache test hits");
+    }
+
+    #[test]
+    fn a_full_cache_evicts_the_least_recently_used_entry() {
+        save_table("cache test eviction", &["ann"]);
+        let mut cache = QueryCache::new(2);
+
+        cache.execute("SELECT * FROM \"cache test eviction\"".to_owned()).unwrap();
+        cache.execute("SELECT name FROM \"cache test eviction\"".to_owned()).unwrap();
+        cache.execute("SELECT * FROM \"cache test eviction\"".to_owned()).unwrap();
+        assert_eq!(cache.hits(), 1);
+
+        // the entry for `SELECT name` was used least recently, so it makes room
+        cache.execute("SELECT added FROM \"cache test eviction\"".to_owned()).unwrap();
+        assert_eq!(cache.len(), 2);
+        cache.execute("SELECT * FROM \"cache test eviction\"".to_owned()).unwrap();
+        assert_eq!(cache.hits(), 2);
+        cache.execute("SELECT name FROM \"cache test eviction\"".to_owned()).unwrap();
+        assert_eq!(cache.hits(), 2);
+
+        remove_table("cache test eviction");
+    }
+
+    #[test]
+    fn tables_with_a_ttl_are_never_cached() {
+        let file_path = save_table("cache test ttl", &["ann"]);
+        let mut table = load_database(&file_path).unwrap();
+        table.set_ttl("added", Duration::days(1)).unwrap();
+        table.save(config::RELATION_PATH.to_owned()).unwrap();
+
+        let mut cache = QueryCache::new(8);
+        cache.execute("SELECT * FROM \"cache test ttl\"".to_owned()).unwrap();
+        cache.execute("SELECT * FROM \"cache test ttl\"".to_owned()).unwrap();
+        assert_eq!(cache.hits(), 0);
+        assert!(cache.is_empty());
+
+        remove_table("cache test ttl");
+    }
+}
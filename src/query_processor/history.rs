@@ -0,0 +1,174 @@
+use std::{fmt, fs, time::{Duration, Instant}};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, structures::db_err::DBError};
+
+use super::{parser::parse_statement, query::{execute_query, QueryResult}, session::Session};
+
+
+/// the name of the file the history is kept in, inside `config::HISTORY_PATH`
+pub const HISTORY_FILE_NAME: &str = "query_history.bin";
+
+
+/// a single command which was run through `QueryHistory::execute()`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub command: String,
+    pub executed_at: DateTime<Utc>,
+    pub duration: Duration,
+    pub succeeded: bool,
+    /// the user of the session the command was run in. `None` if it wasn't run in a session
+    pub user: Option<String>,
+}
+
+
+/// an entry saved before entries were attributed to a user
+#[derive(Deserialize)]
+struct LegacyHistoryEntry {
+    command: String,
+    executed_at: DateTime<Utc>,
+    duration: Duration,
+    succeeded: bool,
+}
+
+
+impl From<LegacyHistoryEntry> for HistoryEntry {
+    fn from(entry: LegacyHistoryEntry) -> Self {
+        HistoryEntry { command: entry.command, executed_at: entry.executed_at, duration: entry.duration, succeeded: entry.succeeded, user: None }
+    }
+}
+
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "[{}] ({}ms{}) {}{}",
+            self.executed_at.format("%Y-%m-%d %H:%M:%S"),
+            self.duration.as_millis(),
+            if self.succeeded { "" } else { ", failed" },
+            self.user.as_ref().map(|user| format!("{}: ", user)).unwrap_or_default(),
+            self.command
+        )
+    }
+}
+
+
+/// every query that has been run, saved to disk after each one so it survives between sessions.
+///
+/// entries are numbered from 1, and running `!n` re-runs the n-th entry.
+pub struct QueryHistory {
+    file_path: String,
+    entries: Vec<HistoryEntry>,
+}
+
+
+impl QueryHistory {
+
+    /// opens the history in `config::HISTORY_PATH`, starting an empty one if it doesn't exist yet
+    pub fn open_default() -> Result<Self, DBError> {
+        QueryHistory::open(&format!("{}/{}", config::HISTORY_PATH, HISTORY_FILE_NAME))
+    }
+
+
+    /// opens the history saved at `file_path`, starting an empty one if the file doesn't exist yet
+    pub fn open(file_path: &str) -> Result<Self, DBError> {
+        let entries = match fs::read(file_path) {
+            Ok(data) => match bincode::deserialize(&data) {
+                Ok(entries) => entries,
+                Err(_) => bincode::deserialize::<Vec<LegacyHistoryEntry>>(&data)
+                    .map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?
+                    .into_iter()
+                    .map(HistoryEntry::from)
+                    .collect(),
+            },
+            Err(_) => Vec::new(),
+        };
+        Ok(QueryHistory { file_path: file_path.to_owned(), entries })
+    }
+
+
+    pub fn entries(&self) -> &Vec<HistoryEntry> { &self.entries }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+
+    /// gets the entry with the given number, counting from 1
+    pub fn get(&self, number: usize) -> Option<&HistoryEntry> {
+        if number == 0 { return None }
+        self.entries.get(number - 1)
+    }
+
+
+    /// lists every entry with its number, oldest first
+    pub fn list(&self) -> String {
+        let mut history_list = String::from("\n");
+        for (idx, entry) in self.entries.iter().enumerate() {
+            history_list += &format!("{}) {entry}\n", idx + 1);
+        }
+        history_list
+    }
+
+
+    /// parses and executes a command, and records it in the history. Failed commands are recorded too.
+    ///
+    /// a command of the form `!n` re-runs the n-th entry of the history instead, and the command
+    /// it runs is what gets recorded.
+    pub fn execute(&mut self, command: String) -> Result<QueryResult, DBError> {
+        self.record(command, None, |command| parse_statement(command).and_then(execute_query))
+    }
+
+
+    /// same as `QueryHistory::execute()`, but the command is run by `session` and recorded as run by its user
+    pub fn execute_in(&mut self, session: &Session, command: String) -> Result<QueryResult, DBError> {
+        self.record(command, Some(session.user().to_string()), |command| session.execute(command))
+    }
+
+
+    fn record(
+        &mut self,
+        command: String,
+        user: Option<String>,
+        run: impl FnOnce(&str) -> Result<QueryResult, DBError>
+    ) -> Result<QueryResult, DBError> {
+
+        let command = match command.trim().strip_prefix('!') {
+            Some(number) => {
+                let number: usize = number.trim().parse().map_err(|_| DBError::InvalidQuery(command.clone()))?;
+                self.get(number).ok_or(DBError::InvalidQuery(command.clone()))?.command.clone()
+            },
+            None => command,
+        };
+
+        let executed_at = Utc::now();
+        let start = Instant::now();
+        let result = run(&command);
+
+        self.entries.push(HistoryEntry { command, executed_at, duration: start.elapsed(), succeeded: result.is_ok(), user });
+        self.save()?;
+
+        result
+    }
+
+
+    /// re-runs the entry with the given number, the same as executing `!n`
+    pub fn replay(&mut self, number: usize) -> Result<QueryResult, DBError> {
+        self.execute(format!("!{number}"))
+    }
+
+
+    /// removes every entry, including the saved copy
+    pub fn clear(&mut self) -> Result<(), DBError> {
+        self.entries.clear();
+        self.save()
+    }
+
+
+    fn save(&self) -> Result<(), DBError> {
+        let encoded_data = bincode::serialize(&self.entries).map_err(|_| DBError::DataBaseFileFailure(self.file_path.clone()))?;
+        fs::write(&self.file_path, encoded_data).map_err(|_| DBError::DataBaseFileFailure(self.file_path.clone()))
+    }
+}
@@ -1 +1,8 @@
-pub mod query;
\ No newline at end of file
+pub mod query;
+pub mod cache;
+pub mod history;
+pub mod saved;
+pub mod tokenizer;
+pub mod parser;
+pub mod undo;
+pub mod session;
@@ -0,0 +1,870 @@
+use std::collections::HashMap;
+
+use crate::structures::{
+    column::{parse_as_data_type, parse_into_field_value, DataType, FieldValue},
+    conflict::ConflictPolicy,
+    db_err::{DBError, SyntaxError},
+    filter::{FilterCondition, FilterConditionValue, FilterExpr},
+    relation::table::Table,
+};
+
+use super::{query::{parse_command, row_from_values, AliasedSelect, JoinClause, Query, TableReference}, tokenizer::{tokenize, Token, TokenKind}};
+
+
+/// statements which are read by the SQL parser. Every other command is handed to `parse_command()`
+const SQL_STATEMENTS: [&str; 6] = ["select", "insert", "update", "delete", "create", "find"];
+
+/// commands which only have the older keyword-matching syntax, see `parse_command()`
+const OTHER_COMMANDS: [&str; 14] = ["replace", "remove", "sort", "filter", "index", "join", "merge", "diff", "generate", "undo", "lineage", "cleanup", "usage", "migrate"];
+
+/// words which can't be used as names without quoting them, since the query would be ambiguous
+const RESERVED_WORDS: [&str; 21] = [
+    "select", "from", "where", "insert", "into", "values", "update", "set", "delete",
+    "create", "table", "primary", "and", "or", "not", "null", "is", "between", "on", "join", "as",
+];
+
+
+/// parses a users command into a query, returning an error which points at the problem if it can't be parsed.
+///
+/// ## SQL Statements
+///
+/// SELECT `(* | col1, col2, ..., coln)` FROM `(table)` [WHERE `(expression)`] <br>
+/// SELECT `(* | col1, ...)` FROM `(table)` [AS] `(alias)` [JOIN `(table)` [[AS] `(alias)`] ON `(column1)` = `(column2)`] [WHERE `(expression)`] <br>
+/// INSERT INTO `(table)` [`(col1, col2, ..., coln)`] VALUES `(val1, val2, ..., valn)` [ON CONFLICT DO `(NOTHING | REPLACE | UPDATE (col1, ...))`] <br>
+/// UPDATE `(table)` SET `(column)` = `(value)` WHERE `(expression)` <br>
+/// DELETE FROM `(table)` WHERE `(expression)` <br>
+/// CREATE TABLE `(table)` (`(col1)` `(datatype)` [PRIMARY KEY], ..., [PRIMARY KEY (col1, ...)]) <br>
+/// FIND `(value)`
+///
+/// where an expression is one or more `(column)` `(condition)` pairs joined with AND, OR, NOT and brackets.
+/// A condition is one of `= != <> < <= > >=` followed by a value, `IS [NOT] NULL`, `IS [NOT] TRUE | FALSE`,
+/// `BETWEEN (low) AND (high)` with two numbers or two dates, `SIMILAR TO (text) (threshold)` with a similarity from 0 to 1,
+/// or `MATCHES (pattern)` with a regular expression (with the `regex` feature).
+///
+/// in a SELECT with aliases, columns can be qualified with the alias of their table (i.e. `o.user_id`, or `"o.unit price"`).
+///
+/// names can be put in double quotes (`"unit price"`), and text in single quotes (`'hello'`). NULL (without quotes) is an empty value.
+/// The older forms of SELECT, INSERT and CREATE are still accepted, and any other command is parsed by `parse_command()`.
+pub fn parse_statement(command: &str) -> Result<Query, DBError> {
+    let mut parser = Parser { query: command, tokens: tokenize(command)?, position: 0 };
+
+    let statement = match parser.peek() {
+        Some(token) if token.kind == TokenKind::Word => token.text.to_lowercase(),
+        _ => return Err(parser.error("a statement, i.e. SELECT, INSERT, UPDATE, DELETE or CREATE")),
+    };
+
+    if !SQL_STATEMENTS.contains(&statement.as_str()) {
+        if !OTHER_COMMANDS.contains(&statement.as_str()) {
+            return Err(parser.error("a statement, i.e. SELECT, INSERT, UPDATE, DELETE or CREATE"));
+        }
+        return parse_command(command.to_string()).ok_or(DBError::InvalidQuery(command.to_string()));
+    }
+
+    let query = match statement.as_str() {
+        "select" => parser.select()?,
+        "insert" => parser.insert()?,
+        "update" => parser.update()?,
+        "delete" => parser.delete()?,
+        "find"   => parser.find()?,
+        _ => parser.create()?,
+    };
+    parser.end()?;
+
+    Ok(query)
+}
+
+
+/// parses a WHERE expression on its own, i.e. `ts > 2024-01-01 AND NOT status = 'test'`
+pub fn parse_expression(expression: &str) -> Result<FilterExpr, DBError> {
+    let mut parser = Parser { query: expression, tokens: tokenize(expression)?, position: 0 };
+    let expr = parser.expression()?;
+    parser.end()?;
+    Ok(expr)
+}
+
+
+/// parses a line of comma separated values into a row of `table`, with a value for each of the table's writable
+/// columns in order (see `Table::writable_columns()`). The values can be wrapped in brackets.
+///
+/// values are read the same way as in an INSERT, so text containing commas can be put in single quotes,
+/// i.e. `1, 'Ottawa, ON', NULL`. Fails if there are too many or too few values, or if a value doesn't fit its column.
+pub fn parse_row(table: &Table, line: &str) -> Result<HashMap<String, FieldValue>, DBError> {
+    let mut parser = Parser { query: line, tokens: tokenize(line)?, position: 0 };
+
+    let bracketed = parser.accept_symbol("(");
+    let values = parser.values()?;
+    if bracketed { parser.expect_symbol(")")?; }
+    parser.end()?;
+
+    let column_names: Vec<String> = table.writable_columns().iter().map(|c| c.get_name().to_string()).collect();
+    if values.len() != column_names.len() {
+        let expected = format!("{} value(s), one for each column ({})", column_names.len(), column_names.join(", "));
+        return Err(parser.error_at(values.get(column_names.len()).map(|(_, token)| token), &expected));
+    }
+
+    let values: Vec<Option<String>> = values.into_iter().map(|(value, _)| value).collect();
+    row_from_values(table, &column_names, &values)
+}
+
+
+/// a recursive descent parser over the tokens of a query
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<Token>,
+    /// the index of the next token to read
+    position: usize,
+}
+
+
+impl Parser<'_> {
+
+    // ---------------
+    //   STATEMENTS
+    // ---------------
+
+    /// SELECT (* | col1, ...) FROM (table) [WHERE (expression)], or with aliases
+    /// SELECT (* | col1, ...) FROM (table) [alias] [JOIN (table) [alias] ON (column1) = (column2)] [WHERE (expression)]
+    fn select(&mut self) -> Result<Query, DBError> {
+        self.expect_keyword("select")?;
+
+        let columns = if self.accept_symbol("*") {
+            vec!["*".to_string()]
+        } else if self.peek().is_some_and(|t| t.is_symbol("(")) {
+            self.name_list("a column name")?
+        } else {
+            self.names("a column name")?
+        };
+
+        self.expect_keyword("from")?;
+        let table = self.name("a table name")?;
+        let alias = self.alias()?;
+
+        let join = if self.accept_keyword("join") {
+            let join_table = self.name("a table name")?;
+            let alias_token = self.peek().cloned();
+            let join_alias = self.alias()?;
+            if join_alias.as_ref().unwrap_or(&join_table) == alias.as_ref().unwrap_or(&table) {
+                return Err(self.error_at(alias_token.as_ref(), "a different alias for each table"));
+            }
+            self.expect_keyword("on")?;
+            let left_column = self.name("a column name")?;
+            self.expect_symbol("=")?;
+            let right_column = self.name("a column name")?;
+            Some(JoinClause { table: TableReference { alias: join_alias.unwrap_or(join_table.clone()), table: join_table }, left_column, right_column })
+        } else {
+            None
+        };
+
+        if alias.is_some() || join.is_some() {
+            let filter = if self.accept_keyword("where") { Some(self.expression()?) } else { None };
+            let from = TableReference { alias: alias.unwrap_or(table.clone()), table };
+            return Ok(Query::ALIASEDSELECT(AliasedSelect { columns, from, join, filter }));
+        }
+
+        if !self.peek().is_some_and(|t| t.is_keyword("where")) {
+            return Ok(Query::SELECT(columns, table));
+        }
+
+        // filtering always returns whole rows
+        if columns != ["*"] {
+            return Err(self.error("the end of the query, since WHERE can only be used with SELECT *"));
+        }
+        self.expect_keyword("where")?;
+        let expr = self.expression()?;
+        Ok(Query::FILTER(table, expr))
+    }
+
+
+    /// INSERT INTO (table) [(col1, ...)] VALUES (val1, ...) [ON CONFLICT DO (action)], or the older
+    /// INSERT (val1, ...) INTO (table) (col1, ...) [ON CONFLICT DO (action)]
+    fn insert(&mut self) -> Result<Query, DBError> {
+        self.expect_keyword("insert")?;
+
+        let (table, columns, values_token, values) = if self.peek().is_some_and(|t| t.is_symbol("(")) {
+            let values_token = self.peek().cloned();
+            let values = self.value_list()?;
+            self.expect_keyword("into")?;
+            let table = self.name("a table name")?;
+            let columns = self.name_list("a column name")?;
+            (table, columns, values_token, values)
+        } else {
+            self.expect_keyword("into")?;
+            let table = self.name("a table name")?;
+            // without a list of columns, the values are checked against the table when the query runs
+            let columns = if self.peek().is_some_and(|t| t.is_keyword("values")) { Vec::new() } else { self.name_list("a column name")? };
+            self.expect_keyword("values")?;
+            let values_token = self.peek().cloned();
+            let values = self.value_list()?;
+            (table, columns, values_token, values)
+        };
+
+        if !columns.is_empty() && values.len() != columns.len() {
+            return Err(self.error_at(values_token.as_ref(), &format!("{} value(s), one for each column", columns.len())));
+        }
+
+        let conflict_policy = if self.accept_keyword("on") {
+            self.expect_keyword("conflict")?;
+            self.expect_keyword("do")?;
+            self.conflict_policy()?
+        } else {
+            ConflictPolicy::Error
+        };
+
+        Ok(Query::INSERT(values, table, columns, conflict_policy))
+    }
+
+
+    /// UPDATE (table) SET (column) = (value) WHERE (expression)
+    fn update(&mut self) -> Result<Query, DBError> {
+        self.expect_keyword("update")?;
+        let table = self.name("a table name")?;
+        self.expect_keyword("set")?;
+        let column = self.name("a column name")?;
+        self.expect_symbol("=")?;
+        let value = self.field_value()?;
+
+        if self.peek().is_some_and(|t| t.is_symbol(",")) {
+            return Err(self.error("WHERE, since only one column can be updated at a time"));
+        }
+
+        self.expect_keyword("where")?;
+        let expr = self.expression()?;
+        Ok(Query::REPLACE(table, column, value, expr))
+    }
+
+
+    /// DELETE FROM (table) WHERE (expression)
+    fn delete(&mut self) -> Result<Query, DBError> {
+        self.expect_keyword("delete")?;
+        self.expect_keyword("from")?;
+        let table = self.name("a table name")?;
+        self.expect_keyword("where")?;
+        let expr = self.expression()?;
+        Ok(Query::DELETE(table, expr))
+    }
+
+
+    /// FIND (value), which searches every table for the value
+    fn find(&mut self) -> Result<Query, DBError> {
+        self.expect_keyword("find")?;
+        let token = self.peek().cloned();
+        match self.value()? {
+            Some(value) => Ok(Query::FIND(value)),
+            None => Err(self.error_at(token.as_ref(), "a value other than NULL")),
+        }
+    }
+
+
+    /// CREATE TABLE (table) ((col) (datatype) [PRIMARY KEY], ..., [PRIMARY KEY (col1, ...)]), or the older
+    /// CREATE (table) COLUMNS (col1:datatype1, ...) [KEYS (col1, ...)]
+    fn create(&mut self) -> Result<Query, DBError> {
+        self.expect_keyword("create")?;
+
+        let mut column_names: Vec<String> = Vec::new();
+        let mut data_types: Vec<DataType> = Vec::new();
+        let mut keys: Vec<(String, Option<Token>)> = Vec::new();
+
+        let table = if self.accept_keyword("table") {
+            let table = self.name("a table name")?;
+            self.expect_symbol("(")?;
+            loop {
+                if self.accept_keyword("primary") {
+                    self.expect_keyword("key")?;
+                    self.expect_symbol("(")?;
+                    loop {
+                        let key_token = self.peek().cloned();
+                        keys.push( (self.name("a column name")?, key_token) );
+                        if !self.accept_symbol(",") { break }
+                    }
+                    self.expect_symbol(")")?;
+                } else {
+                    let name_token = self.peek().cloned();
+                    let name = self.name("a column name")?;
+                    if column_names.contains(&name) {
+                        return Err(self.error_at(name_token.as_ref(), "a column name which isn't already in the table"));
+                    }
+                    data_types.push( self.data_type()? );
+                    column_names.push( name.clone() );
+
+                    if self.accept_keyword("primary") {
+                        self.expect_keyword("key")?;
+                        keys.push( (name, None) );
+                    }
+                }
+
+                if !self.accept_symbol(",") { break }
+            }
+            self.expect_symbol(")")?;
+            table
+        } else {
+            let table = self.name("a table name")?;
+            self.expect_keyword("columns")?;
+            self.expect_symbol("(")?;
+            loop {
+                let name_token = self.peek().cloned();
+                let (name, data_type) = self.column_and_type()?;
+                if column_names.contains(&name) {
+                    return Err(self.error_at(name_token.as_ref(), "a column name which isn't already in the table"));
+                }
+                column_names.push(name);
+                data_types.push(data_type);
+                if !self.accept_symbol(",") { break }
+            }
+            self.expect_symbol(")")?;
+
+            if self.accept_keyword("keys") {
+                self.expect_symbol("(")?;
+                loop {
+                    let key_token = self.peek().cloned();
+                    keys.push( (self.name("a column name")?, key_token) );
+                    if !self.accept_symbol(",") { break }
+                }
+                self.expect_symbol(")")?;
+            }
+            table
+        };
+
+        for (key, key_token) in &keys {
+            if !column_names.contains(key) {
+                return Err(self.error_at(key_token.as_ref(), "one of the table's columns as a primary key"));
+            }
+        }
+        let keys = keys.into_iter().map(|(key, _)| key).collect();
+
+        Ok(Query::CREATE(table, column_names, data_types, keys))
+    }
+
+
+    // ---------------
+    //      PARTS
+    // ---------------
+
+    /// ON CONFLICT DO (NOTHING | REPLACE | UPDATE [(col1, ...)])
+    fn conflict_policy(&mut self) -> Result<ConflictPolicy, DBError> {
+        let action_token = self.peek().cloned();
+        let action = match &action_token {
+            Some(token) if token.kind == TokenKind::Word => token.text.clone(),
+            _ => return Err(self.error("NOTHING, REPLACE or UPDATE")),
+        };
+        self.position += 1;
+
+        let update_columns = if self.peek().is_some_and(|t| t.is_symbol("(")) { self.name_list("a column name")? } else { Vec::new() };
+
+        ConflictPolicy::parse_str(&action, update_columns).ok_or(self.error_at(action_token.as_ref(), "NOTHING, REPLACE or UPDATE"))
+    }
+
+
+    /// a column of the older CREATE syntax, i.e. `price:number` or `"unit price": number`
+    fn column_and_type(&mut self) -> Result<(String, DataType), DBError> {
+        let name_token = self.peek().cloned();
+
+        let (name, mut data_type_text) = match &name_token {
+            Some(token) if token.kind == TokenKind::QuotedName => (token.text.clone(), None),
+            Some(token) if token.kind == TokenKind::Word && token.text.contains(':') => {
+                let (name, data_type) = token.text.split_once(':').unwrap();
+                (name.to_string(), Some(data_type.to_string()))
+            },
+            _ => return Err(self.error("a column and its datatype, i.e. price:number")),
+        };
+        if name.is_empty() {
+            return Err(self.error("a column name before the ':'"));
+        }
+        self.position += 1;
+
+        // the ':' can be on its own, or at the start of the datatype
+        if data_type_text.is_none() {
+            match self.peek() {
+                Some(token) if token.kind == TokenKind::Word && token.text.starts_with(':') => {
+                    data_type_text = Some(token.text[1..].to_string());
+                    self.position += 1;
+                },
+                _ => return Err(self.error("':' followed by the column's datatype")),
+            }
+        }
+
+        let data_type = match data_type_text {
+            Some(text) if !text.is_empty() => {
+                // the datatype was part of the previous token
+                let token = Token { kind: TokenKind::Word, text, position: self.tokens[self.position - 1].position };
+                self.data_type_of(&token)?
+            },
+            _ => self.data_type()?,
+        };
+
+        Ok((name, data_type))
+    }
+
+
+    fn data_type(&mut self) -> Result<DataType, DBError> {
+        let token = self.peek().cloned().ok_or(self.error("a datatype"))?;
+        let data_type = self.data_type_of(&token)?;
+        self.position += 1;
+        Ok(data_type)
+    }
+
+
+    fn data_type_of(&self, token: &Token) -> Result<DataType, DBError> {
+        if token.kind != TokenKind::Word {
+            return Err(self.error_at(Some(token), "a datatype (NUMBER, STRING, BOOLEAN, DATE or URL)"));
+        }
+        match token.text.to_lowercase().as_str() {
+            "number" | "int" | "integer" | "float" => Ok(DataType::Number),
+            "string" | "str" | "text" | "varchar"  => Ok(DataType::String),
+            "boolean" | "bool"                     => Ok(DataType::Boolean),
+            "date" | "timestamp"                   => Ok(DataType::Date),
+            "url"                                  => Ok(DataType::Url),
+            _ => Err(self.error_at(Some(token), "a datatype (NUMBER, STRING, BOOLEAN, DATE or URL)")),
+        }
+    }
+
+
+    /// conditions joined with AND, OR and NOT, i.e. `age > 30 AND (city = 'Ottawa' OR NOT active = true)`.
+    /// AND is applied before OR, and NOT before both
+    fn expression(&mut self) -> Result<FilterExpr, DBError> {
+        let mut expr = self.and_expression()?;
+        while self.accept_keyword("or") {
+            expr = expr.or( self.and_expression()? );
+        }
+        Ok(expr)
+    }
+
+
+    fn and_expression(&mut self) -> Result<FilterExpr, DBError> {
+        let mut expr = self.not_expression()?;
+        while self.accept_keyword("and") {
+            expr = expr.and( self.not_expression()? );
+        }
+        Ok(expr)
+    }
+
+
+    /// NOT (expression), a bracketed expression, or a single condition on a column
+    fn not_expression(&mut self) -> Result<FilterExpr, DBError> {
+        if self.accept_keyword("not") {
+            return Ok( self.not_expression()?.negate() );
+        }
+
+        if self.accept_symbol("(") {
+            let expr = self.expression()?;
+            self.expect_symbol(")")?;
+            return Ok(expr);
+        }
+
+        let column = self.name("a column name")?;
+        let condition = self.condition()?;
+        Ok(FilterExpr::Leaf(column, condition))
+    }
+
+
+    /// a comparison, IS [NOT] (NULL | TRUE | FALSE), BETWEEN (low) AND (high), SIMILAR TO (text) (threshold), or MATCHES (pattern)
+    fn condition(&mut self) -> Result<FilterCondition, DBError> {
+        const EXPECTED: &str = "a condition, i.e. = 5, IS NULL, or BETWEEN 1 AND 10";
+        let token = self.peek().cloned().ok_or(self.error(EXPECTED))?;
+
+        if token.is_keyword("is") {
+            self.position += 1;
+            let negated = self.accept_keyword("not");
+            let condition = match self.peek() {
+                Some(t) if t.is_keyword("null") => if negated { FilterCondition::NotNull } else { FilterCondition::Null },
+                Some(t) if t.is_keyword("true") => if negated { FilterCondition::False } else { FilterCondition::True },
+                Some(t) if t.is_keyword("false") => if negated { FilterCondition::True } else { FilterCondition::False },
+                _ => return Err(self.error("NULL, TRUE or FALSE")),
+            };
+            self.position += 1;
+            return Ok(condition);
+        }
+
+        if token.is_keyword("between") {
+            self.position += 1;
+            let lower_token = self.peek().cloned();
+            let lower = self.condition_value()?;
+            self.expect_keyword("and")?;
+            let upper = self.condition_value()?;
+
+            return match (lower, upper) {
+                (FilterConditionValue::Number(l), FilterConditionValue::Number(u))
+                    => Ok(FilterCondition::NumberBetween(FilterConditionValue::NumberRange(l, u))),
+                (FilterConditionValue::Date(l), FilterConditionValue::Date(u))
+                    => Ok(FilterCondition::DateBetween(FilterConditionValue::DateRange(l, u))),
+                _ => Err(self.error_at(lower_token.as_ref(), "two numbers or two dates to be between")),
+            };
+        }
+
+        if token.is_keyword("similar") || token.is_keyword("similar_to") {
+            self.position += 1;
+            if token.is_keyword("similar") { self.expect_keyword("to")?; }
+            let text = self.text("the text to be similar to")?;
+
+            let threshold_token = self.peek().cloned();
+            return match threshold_token.as_ref().filter(|t| t.kind == TokenKind::Number).and_then(|t| t.text.parse::<f64>().ok()) {
+                Some(threshold) if (0.0..=1.0).contains(&threshold) => {
+                    self.position += 1;
+                    Ok(FilterCondition::SimilarTo(text, threshold))
+                },
+                _ => Err(self.error_at(threshold_token.as_ref(), "a similarity between 0 and 1")),
+            };
+        }
+
+        if token.is_keyword("matches") {
+            self.position += 1;
+            let pattern_token = self.peek().cloned();
+            let pattern = self.text("a pattern in quotes")?;
+            return self.regex_condition(&pattern, pattern_token.as_ref());
+        }
+
+        if token.kind != TokenKind::Symbol {
+            return Err(self.error(EXPECTED));
+        }
+        self.position += 1;
+
+        // comparing against null or a boolean is the same as IS
+        let next_word = self.peek().filter(|t| t.kind == TokenKind::Word).map(|t| t.text.to_lowercase());
+        let shorthand = match (token.text.as_str(), next_word.as_deref()) {
+            ("=", Some("null")) => Some(FilterCondition::Null),
+            ("!=" | "<>", Some("null")) => Some(FilterCondition::NotNull),
+            ("=", Some("true")) | ("!=" | "<>", Some("false")) => Some(FilterCondition::True),
+            ("=", Some("false")) | ("!=" | "<>", Some("true")) => Some(FilterCondition::False),
+            _ => None,
+        };
+        if let Some(condition) = shorthand {
+            self.position += 1;
+            return Ok(condition);
+        }
+
+        let value = self.condition_value()?;
+        match token.text.as_str() {
+            "="         => Ok(FilterCondition::Equal(value)),
+            "!=" | "<>" => Ok(FilterCondition::NotEqual(value)),
+            "<"         => Ok(FilterCondition::LessThan(value)),
+            "<="        => Ok(FilterCondition::LessThanOrEqualTo(value)),
+            ">"         => Ok(FilterCondition::GreaterThan(value)),
+            ">="        => Ok(FilterCondition::GreaterThanOrEqualTo(value)),
+            _ => Err(self.error_at(Some(&token), EXPECTED)),
+        }
+    }
+
+
+    /// compiles the pattern of a MATCHES condition, pointing at `token` if it isn't a valid regular expression
+    #[cfg(feature = "regex")]
+    fn regex_condition(&self, pattern: &str, token: Option<&Token>) -> Result<FilterCondition, DBError> {
+        FilterCondition::matches(pattern).map_err(|_| self.error_at(token, "a valid regular expression"))
+    }
+
+
+    #[cfg(not(feature = "regex"))]
+    fn regex_condition(&self, _pattern: &str, token: Option<&Token>) -> Result<FilterCondition, DBError> {
+        Err(self.error_at(token, "a condition other than MATCHES, which needs the regex feature"))
+    }
+
+
+    /// text in single or double quotes, i.e. the text of SIMILAR TO or the pattern of MATCHES
+    fn text(&mut self, expected: &str) -> Result<String, DBError> {
+        match self.peek() {
+            Some(token) if matches!(token.kind, TokenKind::Text | TokenKind::QuotedName) => {
+                let text = token.text.clone();
+                self.position += 1;
+                Ok(text)
+            },
+            _ => Err(self.error(expected)),
+        }
+    }
+
+
+    /// a number, a date, or text that a cell is compared against
+    fn condition_value(&mut self) -> Result<FilterConditionValue, DBError> {
+        let token = self.peek().cloned().ok_or(self.error("a value"))?;
+
+        let value = match token.kind {
+            TokenKind::Number => FilterConditionValue::Number(token.text.parse().unwrap()),
+            TokenKind::Text | TokenKind::Word if !self.is_reserved(&token) => {
+                match parse_as_data_type(&token.text, &DataType::Date) {
+                    Ok(FieldValue::Date(date)) => FilterConditionValue::Date(date),
+                    _ => FilterConditionValue::String(token.text.clone()),
+                }
+            },
+            _ => return Err(self.error("a value")),
+        };
+        self.position += 1;
+        Ok(value)
+    }
+
+
+    /// a value being inserted, kept as text since it is parsed as its column's datatype when the query runs
+    /// `None` is the NULL keyword, which is different from the text 'null'
+    fn value(&mut self) -> Result<Option<String>, DBError> {
+        let token = self.peek().cloned().ok_or(self.error("a value"))?;
+
+        let value = match token.kind {
+            TokenKind::Word if token.is_keyword("null") => None,
+            TokenKind::Number | TokenKind::Text | TokenKind::QuotedName => Some(token.text),
+            TokenKind::Word if !self.is_reserved(&token) => Some(token.text),
+            _ => return Err(self.error("a value")),
+        };
+        self.position += 1;
+        Ok(value)
+    }
+
+
+    /// the new value of an UPDATE. Quoted text is never read as a number or boolean, but can still be a date or url
+    fn field_value(&mut self) -> Result<FieldValue, DBError> {
+        let is_text = self.peek().is_some_and(|t| t.kind == TokenKind::Text);
+        let value = match self.value()? {
+            Some(value) => value,
+            None => return Ok(FieldValue::Null),
+        };
+
+        Ok(match parse_into_field_value(&value) {
+            FieldValue::Number(_) | FieldValue::Boolean(_) | FieldValue::Null if is_text => FieldValue::String(value),
+            parsed_value => parsed_value,
+        })
+    }
+
+
+    /// (val1, val2, ..., valn)
+    fn value_list(&mut self) -> Result<Vec<Option<String>>, DBError> {
+        self.expect_symbol("(")?;
+        let values = self.values()?.into_iter().map(|(value, _)| value).collect();
+        self.expect_symbol(")")?;
+        Ok(values)
+    }
+
+
+    /// val1, val2, ..., valn, along with the token each value was read from
+    fn values(&mut self) -> Result<Vec<(Option<String>, Token)>, DBError> {
+        let mut values: Vec<(Option<String>, Token)> = Vec::new();
+        loop {
+            let token = self.peek().cloned().ok_or(self.error("a value"))?;
+            values.push( (self.value()?, token) );
+            if !self.accept_symbol(",") { break }
+        }
+        Ok(values)
+    }
+
+
+    /// (name1, name2, ..., namen)
+    fn name_list(&mut self, expected: &str) -> Result<Vec<String>, DBError> {
+        self.expect_symbol("(")?;
+        let names = self.names(expected)?;
+        self.expect_symbol(")")?;
+        Ok(names)
+    }
+
+
+    /// name1, name2, ..., namen
+    fn names(&mut self, expected: &str) -> Result<Vec<String>, DBError> {
+        let mut names = vec![ self.name(expected)? ];
+        while self.accept_symbol(",") {
+            names.push( self.name(expected)? );
+        }
+        Ok(names)
+    }
+
+
+    /// [AS] (alias) after a table name. `None` if the table wasn't given an alias
+    fn alias(&mut self) -> Result<Option<String>, DBError> {
+        if self.accept_keyword("as") {
+            return Ok(Some( self.name("an alias for the table")? ));
+        }
+
+        let has_alias = self.peek().is_some_and(|t| t.kind == TokenKind::QuotedName || (t.kind == TokenKind::Word && !self.is_reserved(t)));
+        if !has_alias { return Ok(None) }
+        Ok(Some( self.name("an alias for the table")? ))
+    }
+
+
+    /// the name of a table or column, which can be in double quotes
+    fn name(&mut self, expected: &str) -> Result<String, DBError> {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::QuotedName
+                || (token.kind == TokenKind::Word && !self.is_reserved(token)) => {
+                let name = token.text.clone();
+                self.position += 1;
+                Ok(name)
+            },
+            _ => Err(self.error(expected)),
+        }
+    }
+
+
+    // ---------------
+    //     TOKENS
+    // ---------------
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+
+    fn is_reserved(&self, token: &Token) -> bool {
+        token.kind == TokenKind::Word && RESERVED_WORDS.contains(&token.text.to_lowercase().as_str())
+    }
+
+
+    /// moves past the next token if it's the given keyword
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek().is_some_and(|t| t.is_keyword(keyword));
+        if found { self.position += 1; }
+        found
+    }
+
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), DBError> {
+        if self.accept_keyword(keyword) { return Ok(()) }
+        Err(self.error(&keyword.to_uppercase()))
+    }
+
+
+    /// moves past the next token if it's the given symbol
+    fn accept_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.peek().is_some_and(|t| t.is_symbol(symbol));
+        if found { self.position += 1; }
+        found
+    }
+
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), DBError> {
+        if self.accept_symbol(symbol) { return Ok(()) }
+        Err(self.error(&format!("'{}'", symbol)))
+    }
+
+
+    /// makes sure there's nothing left in the query, other than a trailing ';'
+    fn end(&mut self) -> Result<(), DBError> {
+        self.accept_symbol(";");
+        if self.peek().is_some() {
+            return Err(self.error("the end of the query"));
+        }
+        Ok(())
+    }
+
+
+    /// an error pointing at the next token
+    fn error(&self, expected: &str) -> DBError {
+        self.error_at(self.peek(), expected)
+    }
+
+
+    /// an error pointing at `token`, or at the end of the query if there is no token
+    fn error_at(&self, token: Option<&Token>, expected: &str) -> DBError {
+        DBError::InvalidSyntax(SyntaxError {
+            query: self.query.to_string(),
+            position: token.map(|t| t.position).unwrap_or(self.query.chars().count()),
+            token: token.map(|t| t.source_text()),
+            expected: expected.to_string(),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{query_processor::query::Query, structures::{db_err::DBError, filter::{FilterCondition, FilterConditionValue, FilterExpr}}};
+
+    use super::{parse_expression, parse_statement};
+
+    fn expression(text: &str) -> String {
+        parse_expression(text).unwrap().to_string()
+    }
+
+    /// the position and token of the syntax error in `query`
+    fn error_position(query: &str) -> (usize, Option<String>) {
+        match parse_statement(query) {
+            Err(DBError::InvalidSyntax(e)) => (e.position, e.token),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn and_is_applied_before_or_and_not_before_both() {
+        assert_eq!(expression("a = 1 OR b = 2 AND c = 3"), "(a = 1 OR (b = 2 AND c = 3))");
+        assert_eq!(expression("a = 1 AND b = 2 OR c = 3"), "((a = 1 AND b = 2) OR c = 3)");
+        assert_eq!(expression("NOT a = 1 AND b = 2"), "(NOT a = 1 AND b = 2)");
+        assert_eq!(expression("NOT (a = 1 AND b = 2)"), "NOT (a = 1 AND b = 2)");
+        assert_eq!(expression("a = 1 AND (b = 2 OR c = 3)"), "(a = 1 AND (b = 2 OR c = 3))");
+    }
+
+    #[test]
+    fn every_condition_is_parsed() {
+        let cases = [
+            ("n = 5", "n = 5"),
+            ("n != 5", "n != 5"),
+            ("n <> 5", "n != 5"),
+            ("n < 5", "n < 5"),
+            ("n <= 5", "n <= 5"),
+            ("n > 5", "n > 5"),
+            ("n >= 5", "n >= 5"),
+            ("n IS NULL", "n is Null"),
+            ("n IS NOT NULL", "n is Not Null"),
+            ("n = NULL", "n is Null"),
+            ("n <> NULL", "n is Not Null"),
+            ("b IS TRUE", "b Is True"),
+            ("b IS NOT TRUE", "b Is False"),
+            ("b IS FALSE", "b Is False"),
+            ("b = false", "b Is False"),
+            ("b != false", "b Is True"),
+            ("n BETWEEN 1 AND 10", "n In the inclusive range [1, 10]"),
+            ("d BETWEEN 2024-01-01 AND '2024-02-01'", "d In the inclusive range [2024-01-01 00:00:00 UTC, 2024-02-01 00:00:00 UTC]"),
+            ("s SIMILAR TO 'jon' 0.8", "s Similar to 'jon' (at least 0.8)"),
+            ("s similar_to \"jon smith\" 1", "s Similar to 'jon smith' (at least 1)"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(expression(text), expected, "parsing `{text}`");
+        }
+    }
+
+    #[test]
+    fn dates_are_compared_as_dates() {
+        let expr = parse_expression("ts > 2024-01-31").unwrap();
+        assert!(matches!(expr, FilterExpr::Leaf(_, FilterCondition::GreaterThan(FilterConditionValue::Date(_)))));
+        let expr = parse_expression("ts > 'soon'").unwrap();
+        assert!(matches!(expr, FilterExpr::Leaf(_, FilterCondition::GreaterThan(FilterConditionValue::String(_)))));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn matches_compiles_its_pattern() {
+        let expr = parse_expression("email MATCHES '^[a-z]+@example\\.com$'").unwrap();
+        match expr {
+            FilterExpr::Leaf(_, FilterCondition::Matches(pattern)) => assert!(pattern.is_match("ann@example.com")),
+            other => panic!("expected MATCHES, got {other}"),
+        }
+        assert_eq!(error_position("SELECT * FROM t WHERE e MATCHES '('"), (32, Some("'('".to_owned())));
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn matches_needs_the_regex_feature() {
+        assert_eq!(error_position("SELECT * FROM t WHERE e MATCHES 'a+'"), (32, Some("'a+'".to_owned())));
+    }
+
+    #[test]
+    fn quoted_names_and_text_can_hold_keywords_and_commas() {
+        match parse_statement("SELECT * FROM \"order items\" WHERE \"select\" = 'it''s, from'").unwrap() {
+            Query::FILTER(table, FilterExpr::Leaf(column, FilterCondition::Equal(FilterConditionValue::String(value)))) => {
+                assert_eq!(table, "order items");
+                assert_eq!(column, "select");
+                assert_eq!(value, "it's, from");
+            },
+            other => panic!("expected a filter, got {other:?}"),
+        }
+
+        match parse_statement("INSERT INTO places VALUES (1, 'Ottawa, ON', NULL, 'null')").unwrap() {
+            Query::INSERT(values, table, _, _) => {
+                assert_eq!(table, "places");
+                assert_eq!(values, vec![Some("1".to_owned()), Some("Ottawa, ON".to_owned()), None, Some("null".to_owned())]);
+            },
+            other => panic!("expected an insert, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        assert_eq!(error_position("SELECT * FORM orders"), (9, Some("FORM".to_owned())));
+        assert_eq!(error_position("SELECT * FROM orders WHERE total >"), (34, None));
+        assert_eq!(error_position("SELECT * FROM orders WHERE total BETWEEN 1 AND 2024-01-01"), (41, Some("1".to_owned())));
+        assert_eq!(error_position("SELECT * FROM orders WHERE name SIMILAR TO 'jon' 2"), (49, Some("2".to_owned())));
+        assert_eq!(error_position("SELECT * FROM orders WHERE name SIMILAR 'jon' 0.5"), (40, Some("'jon'".to_owned())));
+        assert_eq!(error_position("SELECT * FROM orders WHERE (a = 1"), (33, None));
+        assert_eq!(error_position("SELECT * FROM orders WHERE a = 1 b"), (33, Some("b".to_owned())));
+    }
+}
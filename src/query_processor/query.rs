@@ -1,47 +1,169 @@
 use core::fmt;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::{Duration, Instant}};
 use crate::{config, structures::{
+    cancel::{default_filter, StatementContext, StatementLimits},
     column::{
-        parse_into_field_value, parse_str, 
-        Column, DataType, FieldValue
+        parse_field_value, parse_into_field_value, validate_column_name, 
+        Column, DataType, FieldValue, ParseMode
     }, 
+    conflict::{ConflictPolicy, InsertOutcome, MatchedAction, NotMatchedAction},
     db_err::DBError, 
-    filter::FilterCondition, 
-    relation::{io::load_database, table::Table}, 
+    filter::{FilterCondition, FilterExpr}, 
+    join::CartesianGuard,
+    relation::{find::find_value_in_all_tables, io::{load_database, relation_file_path, validate_table_name}, lineage::lineage_of, masking::ReadAccess, migrate::migrate_table_file, system::{is_system_table, system_table}, table::Table, temporary::{remove_stale_temp_tables, DEFAULT_TEMP_TABLE_MAX_AGE}, usage::disk_usage}, 
     sort::SortCondition
 }};
 
+use super::{parser::parse_statement, undo::{save_with_undo, undo_last_statement}};
+
+
+/// a table in the FROM clause of a SELECT, and the alias its columns are qualified with (i.e. `orders o` or `orders AS o`)
+#[derive(Debug, Clone)]
+pub struct TableReference {
+    pub table: String,
+    /// the table's own name if it wasn't given an alias
+    pub alias: String,
+}
+
+
+/// JOIN (table) [alias] ON (column1) = (column2)
+#[derive(Debug, Clone)]
+pub struct JoinClause {
+    pub table: TableReference,
+    pub left_column: String,
+    pub right_column: String,
+}
+
+
+/// a SELECT which gives its table an alias, or joins a second table, i.e.
+/// `SELECT o.id, u.name FROM orders o JOIN users u ON o.user_id = u.id WHERE o.total > 100`.
+///
+/// columns can be qualified with the alias of their table, and must be if both tables have a column with that name.
+#[derive(Debug, Clone)]
+pub struct AliasedSelect {
+    pub columns: Vec<String>,
+    pub from: TableReference,
+    pub join: Option<JoinClause>,
+    pub filter: Option<FilterExpr>,
+}
+
 
 #[derive(Debug)]
 pub enum Query {
-    /// SELECT (col1, col2, ..., coln) FROM (table)
+    /// SELECT (* | col1, col2, ..., coln) FROM (table)
     SELECT(Vec<String>, String),
 
-    /// INSERT (val1, val2, ..., valn) INTO (table) (col1, col2, ..., coln)
-    INSERT(Vec<String>, String, Vec<String>),
+    /// SELECT (* | col1, ...) FROM (table) [alias] [JOIN (table) [alias] ON (column1) = (column2)] [WHERE (expression)]
+    ALIASEDSELECT(AliasedSelect),
 
-    /// REPLACE (table) (column) TO (val) WHERE (condition_column) (condition)
-    REPLACE(String, String, FieldValue, String, FilterCondition),
+    /// INSERT INTO (table) [(col1, col2, ..., coln)] VALUES (val1, val2, ..., valn) [ON CONFLICT DO (NOTHING | REPLACE | UPDATE (col1, ...))]
+    /// 
+    /// a `None` value is an explicit NULL. Without any columns, there is a value for each of the table's writable columns.
+    INSERT(Vec<Option<String>>, String, Vec<String>, ConflictPolicy),
 
-    /// DELETE FROM (table) WHERE (column) (condition)
-    DELETE(String, String, FilterCondition),
+    /// UPDATE (table) SET (column) = (val) WHERE (expression)
+    REPLACE(String, String, FieldValue, FilterExpr),
+
+    /// DELETE FROM (table) WHERE (expression)
+    DELETE(String, FilterExpr),
 
     /// SORT (table) ON (sort_condition) COLUMN (column)
     SORT(String, SortCondition, String),
 
-    /// FILTER FROM (table) WHERE (column) (condition)
-    FILTER(String, String, FilterCondition),
+    /// SELECT * FROM (table) WHERE (expression)
+    FILTER(String, FilterExpr),
 
     /// INDEX (table) (column)
     INDEX(String, String),
 
-    // CREATE (table_name) COLUMNS (col_name1:data_type1, etc) KEYS (col_name_1, etc)
+    /// CREATE TABLE (table_name) (col_name1 data_type1 [PRIMARY KEY], etc, [PRIMARY KEY (col_name_1, etc)])
     CREATE(String, Vec<String>, Vec<DataType>, Vec<String>),
 
+    /// JOIN (table1) WITH (table2) ON (column)
+    JOIN(String, String, String),
+
+    /// JOIN (table1) WITH (table2) [ALLOW CARTESIAN]
+    CROSSJOIN(String, String, CartesianGuard),
+
+    /// MERGE (source) INTO (target) ON (column) [WHEN MATCHED (UPDATE | NOTHING)] [WHEN NOT MATCHED (INSERT | NOTHING)]
+    MERGE(String, String, String, MatchedAction, NotMatchedAction),
+
+    /// DIFF (old_table) WITH (new_table) ON (col1, col2, ..., coln)
+    DIFF(String, String, Vec<String>),
+
+    /// GENERATE (n) ROWS INTO (table) [SEED (seed)]
+    GENERATE(usize, String, u64),
+
+    /// FIND (value)
+    /// 
+    /// lists every cell in every table equal to the value
+    FIND(String),
+
+    /// UNDO
+    /// 
+    /// puts back the table changed by the last INSERT, UPDATE, DELETE, MERGE or GENERATE
+    UNDO,
+
+    /// LINEAGE (table)
+    /// 
+    /// shows the tables and files the table was derived from, and how
+    LINEAGE(String),
+
+    /// CLEANUP [(hours)]
+    /// 
+    /// deletes saved temporary tables (i.e. the results of filters and joins) made more than (hours) ago
+    CLEANUP(u32),
+
+    /// USAGE
+    /// 
+    /// shows the disk space used by each table, and by everything else in the database directories
+    USAGE,
+
+    /// MIGRATE (file)
+    /// 
+    /// converts a table file saved by an older version of sequel into the current format, and rebuilds its indexes
+    MIGRATE(String),
+
     // TODO: add import, export, (join ?)
 }
 
 
+impl Query {
+    /// the tables the query reads rows from, which are filtered by a session's default filters (see `Session::set_filter()`).
+    /// The table a query writes to isn't read this way, so it isn't listed
+    pub fn tables_read(&self) -> Vec<String> {
+        match self {
+            Query::SELECT(_, table)
+            | Query::FILTER(table, _)
+            | Query::SORT(table, _, _)            => vec![table.clone()],
+            Query::ALIASEDSELECT(select)          => std::iter::once(&select.from)
+                .chain(select.join.as_ref().map(|j| &j.table))
+                .map(|t| t.table.clone())
+                .collect(),
+            Query::JOIN(left, right, _)
+            | Query::CROSSJOIN(left, right, _)
+            | Query::DIFF(left, right, _)         => vec![left.clone(), right.clone()],
+            Query::MERGE(source, _, _, _, _)      => vec![source.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+
+    /// whether the query changes anything on disk, so it can be refused to a read only session (see `Session::set_read_only()`).
+    /// Saving the result of a read as a temporary table doesn't count
+    pub fn writes(&self) -> bool {
+        match self {
+            Query::INSERT(..) | Query::REPLACE(..) | Query::DELETE(..) | Query::INDEX(..)
+            | Query::CREATE(..) | Query::MERGE(..) | Query::GENERATE(..) | Query::UNDO
+            | Query::CLEANUP(..) | Query::MIGRATE(..) => true,
+            Query::SELECT(..) | Query::ALIASEDSELECT(..) | Query::SORT(..) | Query::FILTER(..)
+            | Query::JOIN(..) | Query::CROSSJOIN(..) | Query::DIFF(..) | Query::FIND(..)
+            | Query::LINEAGE(..) | Query::USAGE => false,
+        }
+    }
+}
+
+
 pub fn list_queries() -> String {
 
     let mut query_list = String::from("\n");
@@ -55,21 +177,38 @@ pub fn list_queries() -> String {
 fn all_queries() -> Vec<Query> {
     let s = String::new();
     let cs = vec![String::new()];
+    let cs2 = cs.clone();
     let dts = vec![DataType::Number];
     let sc = SortCondition::AlphaAscending;
-    let fc = FilterCondition::Null;
-    let fc2 = FilterCondition::Null;
+    let fe = FilterExpr::leaf("", FilterCondition::Null);
     let fv = FieldValue::Null;
 
     vec![
         Query::SELECT(cs.clone(), s.clone()),
-        Query::INSERT(cs.clone(), s.clone(), cs.clone()),
-        Query::REPLACE(s.clone(), s.clone(), fv,s.clone() , fc.clone()),
-        Query::DELETE(s.clone(), s.clone(), fc),
+        Query::ALIASEDSELECT(AliasedSelect {
+            columns: cs.clone(),
+            from: TableReference { table: s.clone(), alias: s.clone() },
+            join: None,
+            filter: None,
+        }),
+        Query::INSERT(vec![None], s.clone(), cs.clone(), ConflictPolicy::Error),
+        Query::REPLACE(s.clone(), s.clone(), fv, fe.clone()),
+        Query::DELETE(s.clone(), fe.clone()),
         Query::SORT(s.clone(), sc, s.clone()),
-        Query::FILTER(s.clone(), s.clone(), fc2),
+        Query::FILTER(s.clone(), fe),
         Query::INDEX(s.clone(), s.clone()),
-        Query::CREATE(s, cs.clone(), dts, cs)
+        Query::CREATE(s.clone(), cs.clone(), dts, cs),
+        Query::JOIN(s.clone(), s.clone(), s.clone()),
+        Query::CROSSJOIN(s.clone(), s.clone(), CartesianGuard::default()),
+        Query::MERGE(s.clone(), s.clone(), s.clone(), MatchedAction::Update, NotMatchedAction::Insert),
+        Query::DIFF(s.clone(), s.clone(), cs2),
+        Query::GENERATE(0, s.clone(), 0),
+        Query::FIND(s),
+        Query::UNDO,
+        Query::LINEAGE(String::new()),
+        Query::CLEANUP(0),
+        Query::USAGE,
+        Query::MIGRATE(String::new()),
     ]
 }
 
@@ -77,68 +216,106 @@ impl fmt::Display for Query {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Query::SELECT(_, _) 
-            => write!(f, "SELECT (col1, col2, ...) FROM {{table_name}}"),
-            Query::INSERT(_, _, _) 
-            => write!(f, "INSERT (val1, val2, ...) INTO {{table}} (col1, col2, ..."),
-            Query::REPLACE(_, _, _, _, _) 
-            => write!(f, "REPLACE {{table}} {{column}} TO {{val}} WHERE {{column}} {{condition}}"),
-            Query::DELETE(_, _, _) 
-            => write!(f, "DELETE FROM {{table}} WHERE {{column}} {{condition}}"),
+            => write!(f, "SELECT (* | col1, col2, ...) FROM {{table_name}}"),
+            Query::ALIASEDSELECT(_)
+            => write!(f, "SELECT (* | alias.col1, ...) FROM {{table}} [alias] [JOIN {{table}} [alias] ON {{column1}} = {{column2}}] [WHERE ...]"),
+            Query::INSERT(_, _, _, _) 
+            => write!(f, "INSERT INTO {{table}} [(col1, col2, ...)] VALUES (val1, val2, ...) [ON CONFLICT DO (NOTHING | REPLACE | UPDATE (col1, ...))]"),
+            Query::REPLACE(_, _, _, _) 
+            => write!(f, "UPDATE {{table}} SET {{column}} = {{val}} WHERE {{column}} {{condition}} [(AND | OR) ...]"),
+            Query::DELETE(_, _) 
+            => write!(f, "DELETE FROM {{table}} WHERE {{column}} {{condition}} [(AND | OR) ...]"),
             Query::SORT(_, _, _)
              => write!(f, "SORT {{table}} ON {{sort_condition}} COLUMN {{column}}"),
-            Query::FILTER(_, _, _)
-             => write!(f, "FILTER FROM {{table}} WHERE {{column}} {{condition}}"),
+            Query::FILTER(_, _)
+             => write!(f, "SELECT * FROM {{table}} WHERE {{column}} {{condition}} [(AND | OR) ...]"),
             Query::INDEX(_, _)
              => write!(f, "INDEX {{table}} {{column}}"),
             Query::CREATE(_, _, _, _)
-             => write!(f, "CREATE {{table_name}} COLUMNS (col_name1:data_type1, ...) KEYS (col_name_1, ...)"),
+             => write!(f, "CREATE TABLE {{table_name}} (col_name1 data_type1 [PRIMARY KEY], ...)"),
+            Query::JOIN(_, _, _)
+             => write!(f, "JOIN {{table1}} WITH {{table2}} ON {{column}}"),
+            Query::CROSSJOIN(_, _, _)
+             => write!(f, "JOIN {{table1}} WITH {{table2}} [ALLOW CARTESIAN]"),
+            Query::MERGE(_, _, _, _, _)
+             => write!(f, "MERGE {{source}} INTO {{target}} ON {{column}} [WHEN MATCHED (UPDATE | NOTHING)] [WHEN NOT MATCHED (INSERT | NOTHING)]"),
+            Query::DIFF(_, _, _)
+             => write!(f, "DIFF {{old_table}} WITH {{new_table}} ON (col1, col2, ...)"),
+            Query::GENERATE(_, _, _)
+             => write!(f, "GENERATE {{n}} ROWS INTO {{table}} [SEED {{seed}}]"),
+            Query::FIND(_)
+             => write!(f, "FIND {{value}}"),
+            Query::UNDO
+             => write!(f, "UNDO"),
+            Query::LINEAGE(_)
+             => write!(f, "LINEAGE {{table}}"),
+            Query::CLEANUP(_)
+             => write!(f, "CLEANUP [{{hours}}]"),
+            Query::USAGE
+             => write!(f, "USAGE"),
+            Query::MIGRATE(_)
+             => write!(f, "MIGRATE {{file}}"),
         }
     }
 }
 
 
 /// given a users command, converts it into a valid database query if possible.
+/// returns None if there is an error during parsing, use `parse_statement()` to find out what the error was.
+/// 
+/// see `parse_statement()` for the SQL statements which can be parsed, and `parse_command()` for everything else.
+pub fn parse_query(command: String) -> Option<Query> {
+    parse_statement(&command).ok()
+}
+
+
+/// parses the commands which aren't SQL statements, by matching their keywords.
 /// returns None if there is an error during parsing.
 /// 
 /// ## Valid Query Templates
 /// 
-/// SELECT `(col1, col2, ..., coln)` FROM `(table)` <br>
-/// INSERT `(val1, val2, ..., valn)` INTO `(table)` `(col1, col2, ..., coln)` <br>
-/// EDIT `(val1, val2, ..., valn)` INTO `(table)` `(col1, col2, ..., coln)` <br>
+/// REPLACE `(table)` `(column)` TO `(val)` WHERE `(condition_column)` `(condition)` <br>
 /// REMOVE FROM `(table)` WHERE `(condition)` <br>
 /// SORT `(table)` ON `(sort_condition)` COLUMN (column) <br>
-/// FILTER `(table)` ON `(filter_condition)` <br>
-/// INDEX `(table)` `(column)`
-pub fn parse_query(command: String) -> Option<Query> {
+/// FILTER FROM `(table)` WHERE `(column)` `(condition)` <br>
+/// INDEX `(table)` `(column)` <br>
+/// JOIN `(table1)` WITH `(table2)` ON `(column)` <br>
+/// JOIN `(table1)` WITH `(table2)` [ALLOW CARTESIAN] <br>
+/// MERGE `(source)` INTO `(target)` ON `(column)` [WHEN MATCHED `(UPDATE | NOTHING)`] [WHEN NOT MATCHED `(INSERT | NOTHING)`] <br>
+/// DIFF `(old_table)` WITH `(new_table)` ON `(col1, col2, ..., coln)` <br>
+/// GENERATE `(n)` ROWS INTO `(table)` [SEED `(seed)`] <br>
+/// UNDO <br>
+/// MIGRATE `(file)`
+pub(super) fn parse_command(command: String) -> Option<Query> {
     
     // Helper function to parse a comma-separated list within parentheses
     fn parse_list(input: &str) -> Vec<String> {
         input
             .trim_matches(|c| c == '(' || c == ')')
             .split(',')
-            .map(|s| s.trim().to_string())
+            .map(|s| s.trim().trim_matches('"').to_string())
             .collect()
     }
 
-    /// helper function to split the command into its parts, while keeping lists intact 
+    /// helper function to split the command into its parts, while keeping lists and quoted names intact 
     fn split_outside_parentheses(s: &str) -> Vec<&str> {
         let mut result = Vec::new();
         let mut start = 0;
         let mut inside_parentheses = false;
+        let mut inside_quotes = false;
 
         let bytes = s.as_bytes(); // Work with bytes to track positions
 
         for (i, &c) in bytes.iter().enumerate() {
             match c {
+                b'"' => inside_quotes = !inside_quotes,
                 b'(' => inside_parentheses = true,
                 b')' => inside_parentheses = false,
-                b' ' => {
-                    if !inside_parentheses {
-                        if start != i { // Check if we have a non-empty word
-                            result.push(&s[start..i]);
-                        }
-                        start = i + 1; // Update start to be after the space
+                b' ' if !inside_parentheses && !inside_quotes => {
+                    if start != i { // Check if we have a non-empty word
+                        result.push(&s[start..i]);
                     }
+                    start = i + 1; // Update start to be after the space
                 }
                 _ => {} // Do nothing for other characters
             }
@@ -158,30 +335,14 @@ pub fn parse_query(command: String) -> Option<Query> {
     let main_query_command = parts[0].to_lowercase();
 
     // Match various command templates
-    if main_query_command.starts_with("select") {
-        // SELECT (col1, col2, ..., coln) FROM (table)
-        if let Some(from_index) = parts.iter().position(|&s| s.to_lowercase() == "from") {
-            let columns = parse_list(parts[1]);
-            println!("parsed list: {:?} | parts: {:?}", &columns, &parts);
-            let table = parts[from_index + 1].trim_matches(|c| c == '(' || c == ')').to_string();
-            return Some(Query::SELECT(columns, table));
-        }
-    } else if main_query_command.starts_with("insert") {
-        // INSERT (val1, val2, ..., valn) INTO (table) (col1, col2, ..., coln)
-        if let Some(into_index) = parts.iter().position(|&s| s.to_lowercase() == "into") {
-            let values = parse_list(parts[1]);
-            let table = parts[into_index + 1].trim_matches(|c| c == '(' || c == ')').to_string();
-            let columns = parse_list(parts[into_index + 2]);
-            return Some(Query::INSERT(values, table, columns));
-        }
-    } else if main_query_command.starts_with("replace") {   
+    if main_query_command.starts_with("replace") {   
         // REPLACE (table) (column) TO (val) WHERE (condition_column) (condition)
         println!("replacing!");
         println!("parts = {:?}", &parts);
-        let table_name = parts[1].to_owned();
-        let modified_column_name = parts[2].to_owned();
+        let table_name = parts[1].trim_matches('"').to_owned();
+        let modified_column_name = parts[2].trim_matches('"').to_owned();
         let val_to_replace_with = parse_into_field_value( &parts[4].to_string() );
-        let condition_column = parts[6].to_owned();
+        let condition_column = parts[6].trim_matches('"').to_owned();
         let condition_str: String = parts[7..].iter().map(|s| format!("{} ", s)).collect();
         let replacement_condition = FilterCondition::parse_str( &condition_str )?;
         println!("replacement condition is {:?}", &replacement_condition);
@@ -189,8 +350,7 @@ pub fn parse_query(command: String) -> Option<Query> {
             table_name, 
             modified_column_name, 
             val_to_replace_with, 
-            condition_column, 
-            replacement_condition
+            FilterExpr::Leaf(condition_column, replacement_condition)
         );
         println!("returning query: {:?}", q);
         return Some(q);
@@ -202,8 +362,8 @@ pub fn parse_query(command: String) -> Option<Query> {
             parts.iter().position(|&s| s.to_lowercase() == "from"), 
             parts.iter().position(|&s| s.to_lowercase() == "where")
         ) {
-            let table = parts[from_index + 1].trim_matches(|c| c == '(' || c == ')').to_string();
-            let column = parts[where_index + 1].trim_matches(|c| c == '(' || c == ')').to_string();
+            let table = parts[from_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let column = parts[where_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
 
             // Parse FilterCondition (e.g., LessThan, GreaterThan, etc.)
             let condition_str: String = parts[where_index + 2..].iter().map(|s| format!("{} ", s)).collect();
@@ -211,19 +371,19 @@ pub fn parse_query(command: String) -> Option<Query> {
             
             if let Some(cond) = condition {
                 // Return a valid DELETE query if all parts were successfully parsed
-                return Some(Query::DELETE(table, column, cond));
+                return Some(Query::DELETE(table, FilterExpr::Leaf(column, cond)));
             }
         }
     } else if main_query_command.starts_with("sort") {
         // SORT (table) ON (sort_condition)
         if let Some(on_index) = parts.iter().position(|&s| s.to_lowercase() == "on") {
-            let table = parts[1].trim_matches(|c| c == '(' || c == ')').to_string();
+            let table = parts[1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
             let sort_condition = SortCondition::parse_str( parts[on_index + 1] );
             
             if sort_condition.is_none() { return None }
 
             if let Some(column_index) = parts.iter().position(|&s| s.to_lowercase() == "column") {
-                let column = parts[column_index + 1].trim_matches(|c| c == '(' || c == ')').to_string();
+                let column = parts[column_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
                 
                 return Some(Query::SORT(table, sort_condition.unwrap(), column));
             } else { return None }   
@@ -234,52 +394,131 @@ pub fn parse_query(command: String) -> Option<Query> {
             parts.iter().position(|&s| s.to_lowercase() == "from"), 
             parts.iter().position(|&s| s.to_lowercase() == "where")
         ) {
-            let table = parts[from_index + 1].trim_matches(|c| c == '(' || c == ')').to_string();
-            let column = parts[where_index + 1].trim_matches(|c| c == '(' || c == ')').to_string();
+            let table = parts[from_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let column = parts[where_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
 
             // Parse FilterCondition (e.g., LessThan, GreaterThan, etc.)
             let condition_str: String = parts[where_index + 2..].iter().map(|s| format!("{} ", s)).collect();
             let condition = FilterCondition::parse_str(&condition_str);
 
             if let Some(cond) = condition {
-                return Some(Query::FILTER(table, column, cond));
+                return Some(Query::FILTER(table, FilterExpr::Leaf(column, cond)));
             }
         }
     } else if main_query_command.starts_with("index") {
         // INDEX (table) (column)
-        let table = parts[1].trim_matches(|c| c == '(' || c == ')').to_string();
-        let column = parts[2].trim_matches(|c| c == '(' || c == ')').to_string();
+        let table = parts[1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+        let column = parts[2].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
         return Some(Query::INDEX(table, column));
-    } else if main_query_command.starts_with("create") {
-        // CREATE (table_name) COLUMNS (col_name1:data_type1, etc) KEYS (col_name_1, etc)
-        if let Some(columns_index) = parts.iter().position(|&s| s.to_lowercase() == "columns") {
-            let table_name = parts[1].trim_matches(|c| c == '(' || c == ')').to_string();
-            
-            let columns_str = parts[columns_index + 1];
-            let columns_and_values: Vec<String> = columns_str.split(',').map(|s| s.trim().to_string()).collect();
-
-            let mut column_names = Vec::new();
-            let mut data_types = Vec::new();
-
-            for pair in columns_and_values {
-                let pair = pair.replace("(", "");
-                let pair = pair.replace(")", "");
-                let mut split = pair.split(':');
-                let column_name = split.next().unwrap().to_string();
-                let data_type_str = split.next().unwrap().to_string();
-                column_names.push(column_name);
-
-                // Parse Datatype
-                data_types.push( parse_str(&data_type_str) );
-            }
+    } else if main_query_command.starts_with("join") {
+        // JOIN (table1) WITH (table2) ON (column)
+        if let (Some(with_index), Some(on_index)) = ( 
+            parts.iter().position(|&s| s.to_lowercase() == "with"), 
+            parts.iter().position(|&s| s.to_lowercase() == "on")
+        ) {
+            if with_index + 1 >= parts.len() || on_index + 1 >= parts.len() { return None }
 
-            if let Some(keys_index) = parts.iter().position(|&s| s.to_lowercase() == "keys") {
-                let keys_str = parts[keys_index + 1];
-                let keys: Vec<String> = parse_list(keys_str);
+            let left_table = parts[1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let right_table = parts[with_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let column = parts[on_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            return Some(Query::JOIN(left_table, right_table, column));
+        }
+
+        // JOIN (table1) WITH (table2) [ALLOW CARTESIAN]
+        if let Some(with_index) = parts.iter().position(|&s| s.to_lowercase() == "with") {
+            if with_index + 1 >= parts.len() { return None }
+
+            let left_table = parts[1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let right_table = parts[with_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
 
-                return Some(Query::CREATE(table_name, column_names, data_types, keys));
+            // large cartesian joins are refused unless they are explicitly allowed
+            let allowed = parts
+                .windows(2)
+                .any(|w| w[0].to_lowercase() == "allow" && w[1].to_lowercase() == "cartesian");
+            let guard = if allowed { CartesianGuard::Allow } else { CartesianGuard::default() };
+
+            return Some(Query::CROSSJOIN(left_table, right_table, guard));
+        }
+    } else if main_query_command.starts_with("merge") {
+        // MERGE (source) INTO (target) ON (column) [WHEN MATCHED (action)] [WHEN NOT MATCHED (action)]
+        if let (Some(into_index), Some(on_index)) = ( 
+            parts.iter().position(|&s| s.to_lowercase() == "into"), 
+            parts.iter().position(|&s| s.to_lowercase() == "on")
+        ) {
+            if into_index + 1 >= parts.len() || on_index + 1 >= parts.len() { return None }
+
+            let source_table = parts[1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let target_table = parts[into_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let column = parts[on_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+
+            // by default, matched rows are updated and new rows are inserted
+            let mut when_matched = MatchedAction::Update;
+            let mut when_not_matched = NotMatchedAction::Insert;
+
+            for (i, part) in parts.iter().enumerate() {
+                if part.to_lowercase() != "when" { continue }
+
+                match parts.get(i + 1)?.to_lowercase().as_str() {
+                    "matched" => when_matched = MatchedAction::parse_str(parts.get(i + 2)?)?,
+                    "not" => {
+                        if parts.get(i + 2)?.to_lowercase() != "matched" { return None }
+                        when_not_matched = NotMatchedAction::parse_str(parts.get(i + 3)?)?;
+                    },
+                    _ => return None
+                }
             }
+            return Some(Query::MERGE(source_table, target_table, column, when_matched, when_not_matched));
+        }
+    } else if main_query_command.starts_with("diff") {
+        // DIFF (old_table) WITH (new_table) ON (col1, col2, ...)
+        if let (Some(with_index), Some(on_index)) = ( 
+            parts.iter().position(|&s| s.to_lowercase() == "with"), 
+            parts.iter().position(|&s| s.to_lowercase() == "on")
+        ) {
+            if with_index + 1 >= parts.len() || on_index + 1 >= parts.len() { return None }
+
+            let old_table = parts[1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let new_table = parts[with_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+            let key_columns = parse_list(parts[on_index + 1]);
+            return Some(Query::DIFF(old_table, new_table, key_columns));
         }
+    } else if main_query_command.starts_with("generate") {
+        // GENERATE (n) ROWS INTO (table) [SEED (seed)]
+        if let Some(into_index) = parts.iter().position(|&s| s.to_lowercase() == "into") {
+            if into_index + 1 >= parts.len() { return None }
+
+            let n_rows: usize = parts.get(1)?.trim_matches(|c| c == '(' || c == ')').parse().ok()?;
+            let table = parts[into_index + 1].trim_matches(|c| c == '(' || c == ')' || c == '"').to_string();
+
+            // without a seed, every run makes different rows
+            let seed: u64 = match parts.iter().position(|&s| s.to_lowercase() == "seed") {
+                Some(seed_index) => parts.get(seed_index + 1)?.trim_matches(|c| c == '(' || c == ')').parse().ok()?,
+                None => rand::random(),
+            };
+            return Some(Query::GENERATE(n_rows, table, seed));
+        }
+    } else if main_query_command.trim_end_matches(';') == "undo" && parts.len() == 1 {
+        // UNDO
+        return Some(Query::UNDO);
+    } else if main_query_command.starts_with("lineage") && parts.len() > 1 {
+        // LINEAGE (table)
+        let table = parts[1..].join(" ").trim_matches(|c| c == '(' || c == ')' || c == '"' || c == ';').to_string();
+        return Some(Query::LINEAGE(table));
+    } else if main_query_command.trim_end_matches(';') == "cleanup" {
+        // CLEANUP [(hours)]
+        let hours: u32 = match parts.get(1) {
+            Some(hours) => hours.trim_matches(|c| c == '(' || c == ')' || c == ';').parse().ok()?,
+            None => DEFAULT_TEMP_TABLE_MAX_AGE.num_hours() as u32,
+        };
+        if parts.len() > 2 { return None }
+        return Some(Query::CLEANUP(hours));
+    } else if main_query_command.trim_end_matches(';') == "usage" && parts.len() == 1 {
+        // USAGE
+        return Some(Query::USAGE);
+    } else if main_query_command == "migrate" && parts.len() > 1 {
+        // MIGRATE (file)
+        let file_path = parts[1..].join(" ").trim_matches(|c| c == '(' || c == ')' || c == '"' || c == ';').to_string();
+        return Some(Query::MIGRATE(file_path));
     }
 
     // If no valid command is found, return None
@@ -287,97 +526,429 @@ pub fn parse_query(command: String) -> Option<Query> {
 }
 
 
+/// same as `execute_query()`, but the query is stopped with an error if it runs longer than
+/// `limits.timeout` or produces more than `limits.max_rows` rows.
+pub fn execute_query_with_limits(query: Query, limits: StatementLimits) -> Result<QueryResult, DBError> {
+    execute_query_with_context(query, ReadAccess::default(), &StatementContext::new(limits))
+}
+
+
 /// # NOTE 
 /// local path must be where **ALL** files will be stored. Both relations **AND** indexes
-pub fn execute_query(query: Query) -> Result<Either<Table, String>, DBError>{
+/// 
+/// sensitive columns are masked in the result, use `execute_query_as()` to read them as an admin.
+pub fn execute_query(query: Query) -> Result<QueryResult, DBError>{
+    execute_query_as(query, ReadAccess::default())
+}
+
+
+/// same as `execute_query()`, but rows are shown as `access` is allowed to see them. See `Table::for_reader()`
+pub fn execute_query_as(query: Query, access: ReadAccess) -> Result<QueryResult, DBError> {
+    execute_query_with_context(query, access, &StatementContext::default())
+}
+
+
+/// runs the query under `context`, so it is stopped by the context's timeout, row limit or cancel handle.
+/// Each statement should get its own context, see `StatementContext`.
+pub fn execute_query_with_context(query: Query, access: ReadAccess, context: &StatementContext) -> Result<QueryResult, DBError> {
+    context.run(|| {
+        let start = Instant::now();
+        let mut result = run_query(query, access)?;
+        // operators check the limit as they go, this catches anything which built its rows in one step
+        context.check_row_limit(result.rows.len())?;
+        result.elapsed = start.elapsed();
+        Ok(result)
+    })
+}
+
+
+/// loads a table to read from. System tables (i.e. `sys.tables`) are built from the database's metadata instead.
+///
+/// only the rows matching the statement's default filter on the table are kept, see `Session::set_filter()`
+fn load_table(table: &str) -> Result<Table, DBError> {
+    let mut loaded = if is_system_table(table) {
+        system_table(table)?
+    } else {
+        load_database(&relation_file_path(config::RELATION_PATH, table))?
+    };
+    match default_filter(table) {
+        Some(filter) => loaded.select_rows_where(&filter),
+        None => Ok(loaded),
+    }
+}
+
+
+/// runs a SELECT with table aliases. The tables are loaded once each (a table joined with itself is only read once),
+/// and every column of the result is named `alias.column`, so columns with the same name in both tables are kept apart.
+fn run_aliased_select(select: AliasedSelect) -> Result<Table, DBError> {
+    let from_table = load_table(&select.from.table)?;
+    let mut table = from_table.qualified(&select.from.alias);
+
+    if let Some(join) = select.join {
+        let other_table = if join.table.table == select.from.table {
+            from_table.qualified(&join.table.alias)
+        } else {
+            load_table(&join.table.table)?.qualified(&join.table.alias)
+        };
+
+        // the ON condition can name the tables in either order
+        let left_column = resolve_column(&join.left_column, &table.all_column_names());
+        let right_column = resolve_column(&join.right_column, &other_table.all_column_names());
+        let (left_column, right_column) = match (left_column, right_column) {
+            (Ok(left), Ok(right)) => (left, right),
+            (left, right) => {
+                let swapped_left = resolve_column(&join.right_column, &table.all_column_names());
+                let swapped_right = resolve_column(&join.left_column, &other_table.all_column_names());
+                match (swapped_left, swapped_right) {
+                    (Ok(swapped_left), Ok(swapped_right)) => (swapped_left, swapped_right),
+                    _ => return Err( left.and(right).unwrap_err() ),
+                }
+            },
+        };
+
+        table = table.inner_join_on(&other_table, &left_column, &right_column)?;
+    }
+
+    let column_names = table.all_column_names();
+
+    if let Some(filter) = select.filter {
+        let filter = filter.try_map_columns(&|name| resolve_column(name, &column_names))?;
+        table = table.select_rows_where(&filter)?;
+    }
+
+    let columns: Vec<String> = if select.columns == ["*"] {
+        column_names
+    } else {
+        select.columns.iter().map(|name| resolve_column(name, &column_names)).collect::<Result<_, _>>()?
+    };
+    table.select_columns(&columns)
+}
+
+
+/// finds the column of an aliased select which `name` refers to. Names can be qualified with an alias (`o.id`),
+/// or left unqualified (`id`) as long as only one of the tables has a column with that name
+fn resolve_column(name: &str, column_names: &[String]) -> Result<String, DBError> {
+    if column_names.iter().any(|c| c == name) {
+        return Ok(name.to_string());
+    }
+
+    let candidates: Vec<String> = column_names
+        .iter()
+        .filter(|c| c.split_once('.').is_some_and(|(_, column)| column == name))
+        .cloned()
+        .collect();
+
+    match candidates.len() {
+        0 => Err(DBError::InvalidColumn(name.to_string())),
+        1 => Ok(candidates[0].clone()),
+        _ => Err(DBError::AmbiguousColumn(name.to_string(), candidates)),
+    }
+}
+
+
+/// builds a row from the values being inserted into `table`, checking there is exactly one value for each column
+/// and that every value fits its column's datatype. `None` is an explicit NULL.
+///
+/// writable columns which aren't given are left empty, so every row has a value for every column.
+pub fn row_from_values(table: &Table, column_names: &[String], values: &[Option<String>]) -> Result<HashMap<String, FieldValue>, DBError> {
+    if column_names.len() != values.len() {
+        return Err(DBError::ValueCountMismatch(column_names.len(), values.len()));
+    }
+
+    let mut row: HashMap<String, FieldValue> = HashMap::new();
+    for (col_name, new_val) in column_names.iter().zip(values) {
+        let column = table.column(col_name.to_string()).ok_or(DBError::InvalidColumn(col_name.to_string()))?;
+
+        let value = match new_val {
+            None => FieldValue::Null,
+            // text is kept exactly as it was written, so 'null' and '' are still text
+            Some(text) if column.get_data_type().eq(&DataType::String) => FieldValue::String(text.clone()),
+            // parse the value as the column's datatype, so "true" can still be inserted into a string column
+            Some(text) => parse_field_value(text, ParseMode::Strict(column.get_data_type().clone()))?,
+        };
+        row.insert(col_name.to_owned(), value);
+    }
+
+    for column in table.writable_columns() {
+        row.entry(column.get_name().to_string()).or_insert(FieldValue::Null);
+    }
+
+    Ok(row)
+}
+
+
+/// system tables are built on the fly, so they can't be written to
+fn reject_system_table(table: &str) -> Result<(), DBError> {
+    if is_system_table(table) {
+        return Err(DBError::ReadOnlyTable(table.to_string()));
+    }
+    Ok(())
+}
+
+
+fn run_query(query: Query, access: ReadAccess) -> Result<QueryResult, DBError> {
 
     let relation_directory = config::RELATION_PATH.to_owned();
     let _index_directory = config::INDEX_PATH.to_owned();
 
     match query {
         Query::SELECT(col_names, table) => {
-            let file_path = format!("{}/db_{table}.bin", &relation_directory);
-            let db = load_database(&file_path)?;
+            let db = load_table(&table)?;
+            let col_names = if col_names == ["*"] { db.all_column_names() } else { col_names };
 
             let r = db.select_columns(&col_names)?;
 
-            return Ok(Either::This(r))
+            QueryResult::from_table_as(&r, access)
+        },
+        Query::ALIASEDSELECT(select) => {
+            let r = run_aliased_select(select)?;
+            QueryResult::from_table_as(&r, access)
         },
-        Query::INSERT(new_vals, table, col_names) => {
-            let file_path = format!("{}/db_{table}.bin", &relation_directory);
+        Query::INSERT(new_vals, table, col_names, conflict_policy) => {
+            reject_system_table(&table)?;
+            let file_path = relation_file_path(&relation_directory, &table);
             let mut db = load_database(&file_path)?;
+            db.track_changes();
             
-            let mut row: HashMap<String, FieldValue> = HashMap::new();
-
-            for (col_name, new_val) in col_names.iter().zip(new_vals) {
-                let datatype = parse_into_field_value(&new_val);
-                row.insert(col_name.to_owned(), datatype);
-            }
-
-            db.insert_row(&row)?;
-            db.save(relation_directory)?;
-
-            return Ok(Either::This(db))
+            let col_names = if col_names.is_empty() {
+                db.writable_columns().iter().map(|c| c.get_name().to_string()).collect()
+            } else {
+                col_names
+            };
+            let row = row_from_values(&db, &col_names, &new_vals)?;
+
+            let outcome = db.insert_row_on_conflict(&row, &conflict_policy)?;
+            save_with_undo(&mut db, relation_directory)?;
+
+            let mut result = QueryResult::from_table_as(&db, access)?;
+            result.affected = Some( if outcome == InsertOutcome::Ignored { 0 } else { 1 } );
+            Ok(result)
         },
-            Query::REPLACE(table, modified_column, new_value, condition_column, condition) => {
+        Query::REPLACE(table, modified_column, new_value, expr) => {
             
-            let file_path = format!("{}/db_{table}.bin", &relation_directory);
+            reject_system_table(&table)?;
+            let file_path = relation_file_path(&relation_directory, &table);
             let mut db = load_database(&file_path)?;
+            db.track_changes();
             
-            let total_changes: u32 = db.edit_rows( condition_column, modified_column, condition, new_value )?;
+            let total_changes: u32 = db.edit_rows_where( &modified_column, &expr, new_value )?;
             
-            db.save(relation_directory)?;
-            return Ok(Either::That(format!("{} cells affected.", total_changes)))
+            save_with_undo(&mut db, relation_directory)?;
+            Ok(QueryResult::from_message(format!("{} cells affected.", total_changes), Some(total_changes)))
         },
         Query::SORT(table, condition, column) => {
-            let file_path = format!("{}/db_{table}.bin", &relation_directory);
-            let mut db = load_database(&file_path)?;
+            let mut db = load_table(&table)?;
             
             db.sort_rows(condition, column)?;
 
-            return Ok(Either::This(db))
+            QueryResult::from_table_as(&db, access)
         },
         Query::INDEX(table, column) => {
-            let file_path = format!("{}/db_{table}.bin", &relation_directory);
+            reject_system_table(&table)?;
+            let file_path = relation_file_path(&relation_directory, &table);
             let db = load_database(&file_path)?;
             db.index_column(column.clone())?;
             
             // save index
-            // return a message saying the index on {column} was created
-            return Err(DBError::ActionNotImplemented("indexing a table".to_owned()))
+            // a message saying the index on {column} was created
+            Err(DBError::ActionNotImplemented("indexing a table".to_owned()))
         },
         Query::CREATE(table, col_names, datatypes, keys) => {
+            reject_system_table(&table)?;
+            validate_table_name(&table)?;
+            for col in &col_names {
+                validate_column_name(col)?;
+            }
+
             let mut columns: Vec<Column> = Vec::new();
             for (col, datatype) in col_names.iter().zip(datatypes.iter()) {
                 let column_is_key = keys.contains(col);
                 columns.push(Column::new(col.clone(), datatype.clone(), column_is_key));
             }
-            let db = Table::new(table.clone(), columns, true);
-            let _ = db.save(relation_directory);
-            return Ok(Either::That(format!("Created table '{table}'")))
+            // a table created without keys has none, rather than a 'Tuple ID' key
+            let mut db = Table::new(table.clone(), columns, keys.is_empty());
+            db.save(relation_directory)?;
+            Ok(QueryResult::from_message(format!("Created table '{table}'"), None))
         },
-        Query::DELETE(table , column, filter_condition) => {
-            let file_path = format!("{}/db_{table}.bin", &relation_directory);
+        Query::DELETE(table, expr) => {
+            reject_system_table(&table)?;
+            let file_path = relation_file_path(&relation_directory, &table);
             let mut db = load_database(&file_path)?;
-            let number_of_rows_deleted = db.delete_rows(column, filter_condition)?;
-            let _ = db.save(relation_directory)?;
-            return Ok(Either::That(format!("deleted {} row(s)", number_of_rows_deleted)));
+            db.track_changes();
+            let number_of_rows_deleted = db.delete_rows_where(&expr)?;
+            save_with_undo(&mut db, relation_directory)?;
+            Ok(QueryResult::from_message(format!("deleted {} row(s)", number_of_rows_deleted), Some(number_of_rows_deleted)))
+        },
+        Query::FILTER(table, expr) => {
+            let mut db = load_table(&table)?;
+
+            let filtered_table = db.select_rows_where(&expr)?; 
+            QueryResult::from_table_as(&filtered_table, access)
         },
-        Query::FILTER(table , column, filter_condition) => {
-            let file_path = format!("{}/db_{table}.bin", &relation_directory);
+        Query::JOIN(left_table, right_table, column) => {
+            let left_db = load_table(&left_table)?;
+            let right_db = load_table(&right_table)?;
+
+            let joined_table = left_db.inner_join(&right_db, column)?;
+            QueryResult::from_table_as(&joined_table, access)
+        },
+        Query::CROSSJOIN(left_table, right_table, guard) => {
+            let left_db = load_table(&left_table)?;
+            let right_db = load_table(&right_table)?;
+
+            let joined_table = left_db.cartesian_join_guarded(&right_db, guard)?;
+            QueryResult::from_table_as(&joined_table, access)
+        },
+        Query::MERGE(source_table, target_table, column, when_matched, when_not_matched) => {
+            reject_system_table(&target_table)?;
+            let target_file_path = relation_file_path(&relation_directory, &target_table);
+            let source_db = load_table(&source_table)?;
+            let mut target_db = load_database(&target_file_path)?;
+            target_db.track_changes();
+
+            let summary = target_db.merge_from(&source_db, &column, when_matched, when_not_matched)?;
+            save_with_undo(&mut target_db, relation_directory)?;
+
+            Ok(QueryResult::from_message(
+                format!(
+                    "{} row(s) updated, {} row(s) inserted, {} row(s) skipped", 
+                    summary.updated, summary.inserted, summary.skipped
+                ),
+                Some(summary.updated + summary.inserted)
+            ))
+        },
+        Query::DIFF(old_table, new_table, key_columns) => {
+            let old_db = load_table(&old_table)?;
+            let new_db = load_table(&new_table)?;
+
+            let diff = old_db.diff(&new_db, &key_columns)?;
+            QueryResult::from_table_as(&diff.to_table()?, access)
+        },
+        Query::GENERATE(n_rows, table, seed) => {
+            reject_system_table(&table)?;
+            let file_path = relation_file_path(&relation_directory, &table);
             let mut db = load_database(&file_path)?;
+            db.track_changes();
+
+            let number_of_rows_added = db.insert_generated_rows(n_rows, seed)?;
+            save_with_undo(&mut db, relation_directory)?;
 
-            let filtered_table = db.select_rows(&column, filter_condition)?; 
-            return Ok(Either::This(filtered_table))
+            Ok(QueryResult::from_message(
+                format!("generated {} row(s) with seed {}", number_of_rows_added, seed), 
+                Some(number_of_rows_added)
+            ))
+        },
+        Query::FIND(value) => {
+            let matches = find_value_in_all_tables(&value, access)?;
+            let rows = matches
+                .into_iter()
+                .map(|m| HashMap::from([
+                    ("Table".to_string(), FieldValue::String(m.table)),
+                    ("Column".to_string(), FieldValue::String(m.column)),
+                    ("Row".to_string(), FieldValue::Number(m.row as f64)),
+                    ("Key".to_string(), FieldValue::String(m.key)),
+                ]))
+                .collect();
+
+            Ok(QueryResult {
+                columns: vec![
+                    ("Table".to_string(), DataType::String),
+                    ("Column".to_string(), DataType::String),
+                    ("Row".to_string(), DataType::Number),
+                    ("Key".to_string(), DataType::String),
+                ],
+                rows,
+                affected: None,
+                message: None,
+                elapsed: Duration::ZERO,
+            })
+        },
+        Query::UNDO => {
+            let table = undo_last_statement()?;
+            Ok(QueryResult::from_message(format!("undid the last change to '{table}'"), None))
+        },
+        Query::LINEAGE(table) => {
+            Ok(QueryResult::from_message(lineage_of(&table)?, None))
+        },
+        Query::USAGE => {
+            Ok(QueryResult::from_message(disk_usage()?.to_string(), None))
+        },
+        Query::MIGRATE(file_path) => {
+            let report = migrate_table_file(&file_path)?;
+            Ok(QueryResult::from_message(report.to_string(), Some(report.rows as u32)))
+        },
+        Query::CLEANUP(hours) => {
+            let removed_tables = remove_stale_temp_tables(chrono::Duration::hours(hours as i64))?;
+            Ok(QueryResult::from_message(
+                format!("removed {} temporary table(s) older than {} hour(s)", removed_tables.len(), hours),
+                Some(removed_tables.len() as u32)
+            ))
         },
     }
 }
 
 
-/// used exclusively for query execution, so that I can return a 
-/// "number of rows affected" statement or the table
-#[derive(Debug)]
-pub enum Either<X, Y> {
-    This(X),
-    That(Y),
-}
\ No newline at end of file
+/// what a query gives back: the rows it produced along with their schema, or a message saying what it did.
+#[derive(Debug, Clone)]
+pub struct QueryResult {
+    /// the name and datatype of every column in `rows`, in order
+    pub columns: Vec<(String, DataType)>,
+    pub rows: Vec<HashMap<String, FieldValue>>,
+    /// the number of rows (or cells) changed, for queries which modify data
+    pub affected: Option<u32>,
+    /// describes what the query did, for queries which don't return rows
+    pub message: Option<String>,
+    /// how long the query took to run
+    pub elapsed: Duration,
+}
+
+
+impl QueryResult {
+
+    pub fn from_table(table: &Table) -> Self {
+        QueryResult {
+            columns: table.columns().iter().map(|c| (c.get_name().to_string(), c.get_data_type().clone())).collect(),
+            rows: table.rows().clone(),
+            affected: None,
+            message: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+
+    /// the rows of `table` as `access` is allowed to see them, see `Table::for_reader()`
+    pub fn from_table_as(table: &Table, access: ReadAccess) -> Result<Self, DBError> {
+        let readable = table.for_reader(access)?;
+        Ok(QueryResult::from_table(&readable))
+    }
+
+
+    pub fn from_message(message: String, affected: Option<u32>) -> Self {
+        QueryResult { columns: Vec::new(), rows: Vec::new(), affected, message: Some(message), elapsed: Duration::ZERO }
+    }
+
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+
+    /// true if the query returned rows, rather than just a message
+    pub fn has_rows(&self) -> bool { !self.columns.is_empty() }
+
+
+    /// keeps only the given columns of the result, in the order given
+    pub fn select_columns(mut self, column_names: &[String]) -> Result<QueryResult, DBError> {
+        let mut columns: Vec<(String, DataType)> = Vec::new();
+        for column_name in column_names {
+            let column = self.columns.iter().find(|(name, _)| name == column_name).ok_or(DBError::InvalidColumn(column_name.clone()))?;
+            columns.push(column.clone());
+        }
+
+        for row in self.rows.iter_mut() {
+            row.retain(|name, _| column_names.contains(name));
+        }
+        self.columns = columns;
+        Ok(self)
+    }
+}
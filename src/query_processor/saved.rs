@@ -0,0 +1,277 @@
+use std::{collections::BTreeMap, fmt, fs};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, structures::db_err::DBError};
+
+use super::{parser::parse_statement, query::{execute_query, QueryResult}, tokenizer::{tokenize, TokenKind}};
+
+
+/// the name of the file saved queries are kept in, inside `config::RELATION_PATH`
+pub const SAVED_QUERIES_FILE_NAME: &str = "saved_queries.bin";
+
+/// the placeholder in a saved query which is filled in by an argument when it is run
+pub const PARAMETER_PLACEHOLDER: char = '?';
+
+
+/// a value filled in for a placeholder when a saved query is run.
+///
+/// arguments are written into the query as literals of their type, so an argument can never change
+/// the rest of the query (i.e. text holding a quote, or `1 OR x = x`, is only ever compared as text)
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryArgument {
+    Text(String),
+    Number(f64),
+    Date(DateTime<Utc>),
+    Null,
+}
+
+
+impl QueryArgument {
+
+    /// the argument as a literal the parser reads back as the same value
+    fn to_literal(&self) -> Result<String, DBError> {
+        match self {
+            QueryArgument::Text(text) => Ok(format!("'{}'", text.replace('\'', "''"))),
+            QueryArgument::Number(n) if n.is_finite() => Ok(n.to_string()),
+            QueryArgument::Number(n) => Err(DBError::InvalidQuery(format!("{n} can't be used as an argument, it isn't a finite number"))),
+            QueryArgument::Date(date) => Ok(format!("'{}'", date)),
+            QueryArgument::Null => Ok("NULL".to_string()),
+        }
+    }
+}
+
+
+impl From<&str> for QueryArgument {
+    fn from(text: &str) -> Self { QueryArgument::Text(text.to_string()) }
+}
+
+impl From<String> for QueryArgument {
+    fn from(text: String) -> Self { QueryArgument::Text(text) }
+}
+
+impl From<f64> for QueryArgument {
+    fn from(n: f64) -> Self { QueryArgument::Number(n) }
+}
+
+impl From<DateTime<Utc>> for QueryArgument {
+    fn from(date: DateTime<Utc>) -> Self { QueryArgument::Date(date) }
+}
+
+
+/// a query saved under a name, which may have `?` placeholders to fill in when it is run
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedQuery {
+    pub name: String,
+    pub template: String,
+    pub saved_at: DateTime<Utc>,
+}
+
+
+impl SavedQuery {
+
+    /// the number of arguments needed to run the query. Fails if the template can't be tokenized
+    pub fn parameter_count(&self) -> Result<usize, DBError> {
+        Ok(placeholder_positions(&self.template)?.len())
+    }
+
+
+    /// fills in each placeholder with the arguments, in order. See `QueryArgument`
+    pub fn expand(&self, arguments: &[QueryArgument]) -> Result<String, DBError> {
+        let positions = placeholder_positions(&self.template)?;
+        if positions.len() != arguments.len() {
+            return Err(DBError::InvalidQuery(format!(
+                "'{}' takes {} argument(s), but {} were given", self.name, positions.len(), arguments.len()
+            )));
+        }
+
+        let mut command = String::new();
+        let mut last_position = 0;
+        for (position, argument) in positions.iter().zip(arguments) {
+            command += &self.template[last_position..*position];
+            command += &argument.to_literal()?;
+            last_position = position + PARAMETER_PLACEHOLDER.len_utf8();
+        }
+        command += &self.template[last_position..];
+        Ok(command)
+    }
+}
+
+
+impl fmt::Display for SavedQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.parameter_count() {
+            Ok(count) => write!(f, "{} ({} argument(s)): {}", self.name, count, self.template),
+            Err(_) => write!(f, "{} (invalid): {}", self.name, self.template),
+        }
+    }
+}
+
+
+/// the byte offset of every placeholder in the template. The template is split up by the same tokenizer as
+/// a query, so a `?` inside quotes (i.e. `'why?'`) or part of a longer word is never a placeholder
+fn placeholder_positions(template: &str) -> Result<Vec<usize>, DBError> {
+    let byte_offsets: Vec<usize> = template.char_indices().map(|(idx, _)| idx).collect();
+
+    Ok(tokenize(template)?
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Word && token.text == PARAMETER_PLACEHOLDER.to_string())
+        .map(|token| byte_offsets[token.position])
+        .collect())
+}
+
+
+/// named queries stored alongside the tables, so they can be run again later with different arguments
+pub struct SavedQueries {
+    file_path: String,
+    queries: BTreeMap<String, SavedQuery>,
+}
+
+
+impl SavedQueries {
+
+    /// opens the saved queries in `config::RELATION_PATH`
+    pub fn open_default() -> Result<Self, DBError> {
+        SavedQueries::open(&format!("{}/{}", config::RELATION_PATH, SAVED_QUERIES_FILE_NAME))
+    }
+
+
+    /// opens the saved queries at `file_path`, starting with none if the file doesn't exist yet
+    pub fn open(file_path: &str) -> Result<Self, DBError> {
+        let queries = match fs::read(file_path) {
+            Ok(data) => bincode::deserialize(&data).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?,
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(SavedQueries { file_path: file_path.to_owned(), queries })
+    }
+
+
+    pub fn get(&self, name: &str) -> Option<&SavedQuery> { self.queries.get(name) }
+
+    /// every saved query, sorted by name
+    pub fn queries(&self) -> impl Iterator<Item = &SavedQuery> { self.queries.values() }
+
+
+    /// saves `template` under `name`, replacing any query already saved with that name.
+    ///
+    /// the template is only checked when it is run, since it can't be parsed until its placeholders are filled in.
+    pub fn save(&mut self, name: &str, template: &str) -> Result<(), DBError> {
+        if name.trim().is_empty() || name.contains(char::is_whitespace) {
+            return Err(DBError::InvalidQuery(format!("'{name}' is not a valid name for a saved query")));
+        }
+        self.queries.insert(
+            name.to_owned(), 
+            SavedQuery { name: name.to_owned(), template: template.to_owned(), saved_at: Utc::now() }
+        );
+        self.write()
+    }
+
+
+    /// removes a saved query, returning it if it existed
+    pub fn remove(&mut self, name: &str) -> Result<Option<SavedQuery>, DBError> {
+        let removed = self.queries.remove(name);
+        if removed.is_some() { self.write()?; }
+        Ok(removed)
+    }
+
+
+    /// fills in the saved query's placeholders with the arguments, giving the command to run
+    pub fn expand(&self, name: &str, arguments: &[QueryArgument]) -> Result<String, DBError> {
+        self.get(name)
+            .ok_or(DBError::InvalidQuery(format!("no query is saved as '{name}'")))?
+            .expand(arguments)
+    }
+
+
+    /// fills in the saved query's placeholders with the arguments, then parses and executes it
+    pub fn run(&self, name: &str, arguments: &[QueryArgument]) -> Result<QueryResult, DBError> {
+        let command = self.expand(name, arguments)?;
+        let query = parse_statement(&command)?;
+        execute_query(query)
+    }
+
+
+    fn write(&self) -> Result<(), DBError> {
+        let encoded_data = bincode::serialize(&self.queries).map_err(|_| DBError::DataBaseFileFailure(self.file_path.clone()))?;
+        fs::write(&self.file_path, encoded_data).map_err(|_| DBError::DataBaseFileFailure(self.file_path.clone()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{query_processor::{parser::parse_statement, query::Query, tokenizer::{tokenize, TokenKind}}, structures::{db_err::DBError, filter::{FilterCondition, FilterConditionValue, FilterExpr}}};
+
+    use super::{QueryArgument, SavedQuery};
+
+    fn saved(template: &str) -> SavedQuery {
+        SavedQuery { name: "test".to_owned(), template: template.to_owned(), saved_at: Utc::now() }
+    }
+
+    #[test]
+    fn placeholders_in_quotes_are_not_counted() {
+        let query = saved("SELECT * FROM orders WHERE note = 'why?' AND id = ?");
+        assert_eq!(query.parameter_count().unwrap(), 1);
+        assert_eq!(
+            query.expand(&[QueryArgument::Number(4.0)]).unwrap(),
+            "SELECT * FROM orders WHERE note = 'why?' AND id = 4"
+        );
+
+        let quoted_name = saved("SELECT \"what?\" FROM orders WHERE id = ?");
+        assert_eq!(quoted_name.parameter_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn the_number_of_arguments_must_match() {
+        let query = saved("SELECT * FROM orders WHERE id = ? AND total > ?");
+        assert!(matches!(query.expand(&[QueryArgument::Number(1.0)]), Err(DBError::InvalidQuery(_))));
+        assert!(matches!(
+            query.expand(&[QueryArgument::Number(1.0), QueryArgument::Number(2.0), QueryArgument::Number(3.0)]),
+            Err(DBError::InvalidQuery(_))
+        ));
+        assert!(query.expand(&[QueryArgument::Number(1.0), QueryArgument::Number(2.0)]).is_ok());
+    }
+
+    #[test]
+    fn arguments_can_not_change_the_query() {
+        let query = saved("SELECT * FROM users WHERE name = ?");
+
+        for attempt in ["x' OR 'a' = 'a", "1 OR x = x", "a'; DELETE FROM users WHERE 'a' = 'a"] {
+            let command = query.expand(&[QueryArgument::from(attempt)]).unwrap();
+
+            // the argument is a single text token at the end of the query
+            let tokens = tokenize(&command).unwrap();
+            assert_eq!(tokens.len(), 8);
+            assert_eq!(tokens[7].kind, TokenKind::Text);
+            assert_eq!(tokens[7].text, attempt);
+
+            match parse_statement(&command).unwrap() {
+                Query::FILTER(_, FilterExpr::Leaf(column, FilterCondition::Equal(FilterConditionValue::String(value)))) => {
+                    assert_eq!(column, "name");
+                    assert_eq!(value, attempt);
+                },
+                other => panic!("the argument changed the query to {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn numbers_and_dates_are_written_as_literals() {
+        let query = saved("SELECT * FROM orders WHERE placed BETWEEN ? AND ?");
+        let from = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2024, 1, 31, 12, 30, 0).unwrap();
+
+        let command = query.expand(&[QueryArgument::from(from), QueryArgument::from(to)]).unwrap();
+        match parse_statement(&command).unwrap() {
+            Query::FILTER(_, FilterExpr::Leaf(_, FilterCondition::DateBetween(FilterConditionValue::DateRange(l, u)))) => {
+                assert_eq!((l, u), (from, to));
+            },
+            other => panic!("expected a date range, got {other:?}"),
+        }
+
+        assert!(saved("SELECT * FROM orders WHERE id = ?").expand(&[QueryArgument::Number(f64::NAN)]).is_err());
+    }
+}
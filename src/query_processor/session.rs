@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+
+use crate::structures::{cancel::StatementContext, db_err::DBError, filter::FilterExpr, relation::{index_stats::flush_index_stats, masking::ReadAccess, temporary::{remove_stale_temp_tables, DEFAULT_TEMP_TABLE_MAX_AGE}}};
+
+use super::{parser::{parse_expression, parse_statement}, query::{execute_query_with_context, QueryResult}};
+
+
+/// the user of a session which wasn't made for anyone in particular
+pub const ANONYMOUS_USER: &str = "anonymous";
+
+
+/// the user running statements, and settings which only last as long as the session, for exploring data without
+/// repeating the same conditions.
+///
+/// the user is who the session's statements are attributed to, i.e. in the query history (see `QueryHistory::execute_in()`).
+/// A read only session refuses every statement which writes, see `Query::writes()`.
+///
+/// a default filter is applied every time its table is read until it is cleared: by a SELECT (as if it was part of
+/// the WHERE clause), a sort, a join, a diff, or as the source of a MERGE. Statements which write to a table
+/// (i.e. UPDATE or DELETE) and FIND see every row.
+/// When the session ends, saved temporary tables older than its `temp_table_max_age` are deleted, and index lookups
+/// counted in memory are saved (see `flush_index_stats()`).
+///
+/// sensitive columns are masked in every result, unless the session is given admin access with `Session::set_access()`.
+pub struct Session {
+    user: String,
+    read_only: bool,
+    default_filters: HashMap<String, FilterExpr>,
+    temp_table_max_age: Duration,
+    access: ReadAccess,
+}
+
+
+impl Default for Session {
+    fn default() -> Self { Session::new() }
+}
+
+
+impl Session {
+
+    /// a session for `ANONYMOUS_USER`
+    pub fn new() -> Self {
+        Session::for_user(ANONYMOUS_USER)
+    }
+
+
+    pub fn for_user(user: &str) -> Self {
+        Session {
+            user: user.to_string(),
+            read_only: false,
+            default_filters: HashMap::new(),
+            temp_table_max_age: DEFAULT_TEMP_TABLE_MAX_AGE,
+            access: ReadAccess::default(),
+        }
+    }
+
+
+    pub fn user(&self) -> &str { &self.user }
+
+
+    /// whether the session refuses statements which write (i.e. INSERT, UPDATE or CREATE) with `DBError::PermissionDenied`
+    pub fn set_read_only(&mut self, read_only: bool) { self.read_only = read_only; }
+
+
+    pub fn is_read_only(&self) -> bool { self.read_only }
+
+
+    /// whether the session sees sensitive columns in plaintext (`ReadAccess::Admin`) or masked
+    pub fn set_access(&mut self, access: ReadAccess) { self.access = access; }
+
+
+    pub fn access(&self) -> ReadAccess { self.access }
+
+
+    /// how old a temporary table has to be to be deleted when the session ends
+    pub fn set_temp_table_max_age(&mut self, max_age: Duration) { self.temp_table_max_age = max_age; }
+
+
+    /// filters every read of `table` by `expression` (i.e. `ts > 2024-01-01`), replacing its previous default filter
+    pub fn set_filter(&mut self, table: &str, expression: &str) -> Result<(), DBError> {
+        let expr = parse_expression(expression)?;
+        self.default_filters.insert(table.to_string(), expr);
+        Ok(())
+    }
+
+
+    /// stops filtering reads of `table`. Returns false if it had no default filter
+    pub fn clear_filter(&mut self, table: &str) -> bool {
+        self.default_filters.remove(table).is_some()
+    }
+
+
+    pub fn clear_all_filters(&mut self) { self.default_filters.clear(); }
+
+
+    /// the default filter on `table`, if there is one
+    pub fn filter(&self, table: &str) -> Option<&FilterExpr> {
+        self.default_filters.get(table)
+    }
+
+
+    /// lists every default filter, sorted by table
+    pub fn list_filters(&self) -> String {
+        let mut tables: Vec<&String> = self.default_filters.keys().collect();
+        tables.sort();
+
+        let mut list = String::new();
+        for table in tables {
+            list += &format!("{}: {}\n", table, self.default_filters[table]);
+        }
+        list
+    }
+
+
+    /// parses and executes a query, keeping only the rows which match the default filter of every table it reads.
+    /// The result's message says which filters were applied, so filtered results aren't mistaken for the whole table.
+    pub fn execute(&self, command: &str) -> Result<QueryResult, DBError> {
+        let query = parse_statement(command)?;
+        if self.read_only && query.writes() {
+            return Err(DBError::PermissionDenied(self.user.clone(), command.trim().to_string()));
+        }
+
+        let mut applied_filters: Vec<String> = Vec::new();
+        for table in query.tables_read() {
+            if let Some(filter) = self.default_filters.get(&table) {
+                let applied = format!("{}: {}", table, filter);
+                if !applied_filters.contains(&applied) { applied_filters.push(applied); }
+            }
+        }
+
+        let context = StatementContext::default().with_default_filters(self.default_filters.clone());
+        let mut result = execute_query_with_context(query, self.access, &context)?;
+        if !applied_filters.is_empty() {
+            let note = format!("default filter applied to {}", applied_filters.join(", "));
+            result.message = Some(match result.message {
+                Some(message) => format!("{}\n{}", message, note),
+                None => note,
+            });
+        }
+        Ok(result)
+    }
+}
+
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // cleaning up is best effort, a table which can't be removed now will be tried again next time
+        let _ = remove_stale_temp_tables(self.temp_table_max_age);
+        let _ = flush_index_stats();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, path::Path};
+
+    use crate::{config, query_processor::history::QueryHistory, structures::{column::{Column, DataType, FieldValue}, db_err::DBError, relation::{io::{relation_file_path, relation_lock_file_name}, table::Table}}};
+
+    use super::Session;
+
+    /// saves a table with an `id` and a `total` for each pair
+    fn save_table(table_name: &str, rows: &[(f64, f64)]) {
+        let columns = vec![
+            Column::new("id".to_owned(), DataType::Number, false),
+            Column::new("total".to_owned(), DataType::Number, false),
+        ];
+        let mut table = Table::new(table_name.to_owned(), columns, true);
+        for (id, total) in rows {
+            table.insert_row(&HashMap::from([
+                ("id".to_owned(), FieldValue::Number(*id)),
+                ("total".to_owned(), FieldValue::Number(*total)),
+            ])).unwrap();
+        }
+        table.save(config::RELATION_PATH.to_owned()).unwrap();
+    }
+
+    fn remove_table(table_name: &str) {
+        let _ = fs::remove_file(relation_file_path(config::RELATION_PATH, table_name));
+        let _ = fs::remove_file(format!("{}/{}", config::RELATION_PATH, relation_lock_file_name(table_name)));
+    }
+
+    #[test]
+    fn every_read_is_filtered() {
+        save_table("session_test_orders", &[(1.0, 5.0), (2.0, 50.0), (3.0, 500.0)]);
+        save_table("session_test_users", &[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]);
+
+        let mut session = Session::new();
+        session.set_filter("session_test_orders", "total > 10").unwrap();
+
+        let result = session.execute("SELECT * FROM session_test_orders").unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert!(result.message.unwrap().contains("session_test_orders: total > 10"));
+
+        let result = session.execute("SELECT * FROM session_test_orders WHERE total < 100").unwrap();
+        assert_eq!(result.rows.len(), 1);
+
+        let result = session.execute("SORT session_test_orders ON NUMERIC_DESCENDING COLUMN total").unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0]["total"], FieldValue::Number(500.0));
+
+        let result = session.execute("SELECT o.total FROM session_test_orders o JOIN session_test_users u ON o.id = u.id").unwrap();
+        assert_eq!(result.rows.len(), 2);
+
+        let result = session.execute("JOIN session_test_users WITH session_test_orders ON id").unwrap();
+        assert_eq!(result.rows.len(), 2);
+
+        // the other table isn't filtered
+        let result = session.execute("SELECT * FROM session_test_users").unwrap();
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(result.message, None);
+
+        remove_table("session_test_orders");
+        remove_table("session_test_users");
+    }
+
+    #[test]
+    fn cleared_filters_are_no_longer_applied() {
+        save_table("session_test_cleared", &[(1.0, 5.0), (2.0, 50.0)]);
+
+        let mut session = Session::new();
+        session.set_filter("session_test_cleared", "total > 10").unwrap();
+        assert_eq!(session.execute("SELECT * FROM session_test_cleared").unwrap().rows.len(), 1);
+        assert_eq!(session.list_filters(), "session_test_cleared: total > 10\n");
+
+        assert!(session.clear_filter("session_test_cleared"));
+        assert!(!session.clear_filter("session_test_cleared"));
+        let result = session.execute("SELECT * FROM session_test_cleared").unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.message, None);
+
+        remove_table("session_test_cleared");
+    }
+
+    #[test]
+    fn a_read_only_session_refuses_writes() {
+        save_table("session_test_read_only", &[(1.0, 5.0)]);
+
+        let mut session = Session::for_user("auditor");
+        session.set_read_only(true);
+        assert_eq!(session.execute("SELECT * FROM session_test_read_only").unwrap().rows.len(), 1);
+        assert!(matches!(
+            session.execute("DELETE FROM session_test_read_only WHERE total > 0"),
+            Err(DBError::PermissionDenied(user, _)) if user == "auditor"
+        ));
+        assert!(matches!(session.execute("CREATE TABLE session_test_refused (id NUMBER)"), Err(DBError::PermissionDenied(..))));
+        assert!(!Path::new(&relation_file_path(config::RELATION_PATH, "session_test_refused")).exists());
+
+        remove_table("session_test_read_only");
+    }
+
+    #[test]
+    fn history_entries_are_attributed_to_the_session_user() {
+        save_table("session_test_history", &[(1.0, 5.0)]);
+        let history_path = format!("{}/session_test_history.bin", config::HISTORY_PATH);
+
+        let session = Session::for_user("alice");
+        let mut history = QueryHistory::open(&history_path).unwrap();
+        history.execute_in(&session, "SELECT * FROM session_test_history".to_owned()).unwrap();
+        history.execute("SELECT * FROM session_test_history".to_owned()).unwrap();
+
+        let history = QueryHistory::open(&history_path).unwrap();
+        assert_eq!(history.get(1).unwrap().user.as_deref(), Some("alice"));
+        assert_eq!(history.get(2).unwrap().user, None);
+        assert!(history.list().contains("alice: SELECT * FROM session_test_history"));
+
+        let _ = fs::remove_file(history_path);
+        remove_table("session_test_history");
+    }
+
+    #[test]
+    fn an_invalid_filter_is_refused() {
+        let mut session = Session::new();
+        assert!(session.set_filter("orders", "total >").is_err());
+        assert!(session.filter("orders").is_none());
+    }
+}
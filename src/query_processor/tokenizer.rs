@@ -0,0 +1,231 @@
+use crate::structures::db_err::{DBError, SyntaxError};
+
+
+/// the kinds of token a query is split into
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// a keyword, an unquoted name, or an unquoted value, i.e. `SELECT`, `price`, or `2024-01-31`
+    Word,
+    /// a name in double quotes, which can hold spaces or keywords, i.e. `"unit price"`
+    QuotedName,
+    /// text in single quotes, i.e. `'hello world'`
+    Text,
+    /// i.e. `12`, `-4.5`
+    Number,
+    /// one of `( ) , ; * = != <> < <= > >=`
+    Symbol,
+}
+
+
+/// a piece of a query, along with where it was found so errors can point at it
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// the text of the token, without any quotes around it
+    pub text: String,
+    /// the offset (in characters) of the token in the query
+    pub position: usize,
+}
+
+
+impl Token {
+    /// true if the token is the given keyword, ignoring case. Quoted names are never keywords
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    pub fn is_symbol(&self, symbol: &str) -> bool {
+        self.kind == TokenKind::Symbol && self.text == symbol
+    }
+
+    /// the token as it was written in the query, for error messages
+    pub fn source_text(&self) -> String {
+        match self.kind {
+            TokenKind::QuotedName => format!("\"{}\"", self.text.replace('"', "\"\"")),
+            TokenKind::Text => format!("'{}'", self.text.replace('\'', "''")),
+            _ => self.text.clone(),
+        }
+    }
+}
+
+
+/// symbols which are two characters long. These are checked before single character symbols
+const TWO_CHARACTER_SYMBOLS: [&str; 4] = ["!=", "<>", "<=", ">="];
+
+/// characters which end a word, on top of whitespace and quotes
+const SYMBOL_CHARACTERS: [char; 9] = ['(', ')', ',', ';', '*', '=', '!', '<', '>'];
+
+
+/// splits a query into tokens.
+///
+/// a doubled quote inside quotes is read as a single quote, i.e. `'it''s'` is the text `it's`.
+/// Fails if a quote is never closed, or if there is a character which can't start a token.
+pub fn tokenize(query: &str) -> Result<Vec<Token>, DBError> {
+    let characters: Vec<char> = query.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+
+    while i < characters.len() {
+        let c = characters[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // quoted names and text
+        if c == '"' || c == '\'' {
+            let (text, end) = read_quoted(&characters, i).ok_or(DBError::InvalidSyntax(SyntaxError {
+                query: query.to_string(),
+                position: i,
+                token: Some(c.to_string()),
+                expected: format!("a closing {}", c),
+            }))?;
+            let kind = if c == '"' { TokenKind::QuotedName } else { TokenKind::Text };
+            tokens.push(Token { kind, text, position: i });
+            i = end;
+            continue;
+        }
+
+        if i + 1 < characters.len() {
+            let pair: String = characters[i..i + 2].iter().collect();
+            if TWO_CHARACTER_SYMBOLS.contains(&pair.as_str()) {
+                tokens.push(Token { kind: TokenKind::Symbol, text: pair, position: i });
+                i += 2;
+                continue;
+            }
+        }
+
+        if SYMBOL_CHARACTERS.contains(&c) {
+            // '!' is only valid as part of "!="
+            if c == '!' {
+                return Err(DBError::InvalidSyntax(SyntaxError {
+                    query: query.to_string(),
+                    position: i,
+                    token: Some(c.to_string()),
+                    expected: "'!='".to_string(),
+                }));
+            }
+            tokens.push(Token { kind: TokenKind::Symbol, text: c.to_string(), position: i });
+            i += 1;
+            continue;
+        }
+
+        // everything else runs until the next space, quote or symbol
+        let start = i;
+        while i < characters.len()
+            && !characters[i].is_whitespace()
+            && !SYMBOL_CHARACTERS.contains(&characters[i])
+            && characters[i] != '"'
+            && characters[i] != '\''
+        {
+            i += 1;
+        }
+        let text: String = characters[start..i].iter().collect();
+        let kind = if text.parse::<f64>().is_ok_and(|n| n.is_finite()) { TokenKind::Number } else { TokenKind::Word };
+        tokens.push(Token { kind, text, position: start });
+    }
+
+    Ok(tokens)
+}
+
+
+/// reads the quoted token starting at `start`, returning its text and the position after the closing quote.
+/// returns `None` if the quote is never closed
+fn read_quoted(characters: &[char], start: usize) -> Option<(String, usize)> {
+    let quote = characters[start];
+    let mut text = String::new();
+    let mut i = start + 1;
+
+    while i < characters.len() {
+        if characters[i] == quote {
+            // two quotes in a row are an escaped quote
+            if characters.get(i + 1) == Some(&quote) {
+                text.push(quote);
+                i += 2;
+                continue;
+            }
+            return Some((text, i + 1));
+        }
+        text.push(characters[i]);
+        i += 1;
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::db_err::DBError;
+
+    use super::{tokenize, TokenKind};
+
+    fn kinds_and_text(query: &str) -> Vec<(TokenKind, String)> {
+        tokenize(query).unwrap().into_iter().map(|t| (t.kind, t.text)).collect()
+    }
+
+    /// the position and token of a syntax error
+    fn error_position(query: &str) -> (usize, Option<String>) {
+        match tokenize(query) {
+            Err(DBError::InvalidSyntax(e)) => (e.position, e.token),
+            other => panic!("expected a syntax error, got {:?}", other.map(|tokens| tokens.len())),
+        }
+    }
+
+    #[test]
+    fn every_kind_of_token_is_read() {
+        assert_eq!(kinds_and_text("SELECT \"unit price\" FROM t WHERE n >= -4.5 AND s = 'a b'"), vec![
+            (TokenKind::Word, "SELECT".to_owned()),
+            (TokenKind::QuotedName, "unit price".to_owned()),
+            (TokenKind::Word, "FROM".to_owned()),
+            (TokenKind::Word, "t".to_owned()),
+            (TokenKind::Word, "WHERE".to_owned()),
+            (TokenKind::Word, "n".to_owned()),
+            (TokenKind::Symbol, ">=".to_owned()),
+            (TokenKind::Number, "-4.5".to_owned()),
+            (TokenKind::Word, "AND".to_owned()),
+            (TokenKind::Word, "s".to_owned()),
+            (TokenKind::Symbol, "=".to_owned()),
+            (TokenKind::Text, "a b".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn symbols_end_words_without_spaces() {
+        assert_eq!(kinds_and_text("a<>b,c<=(d)"), vec![
+            (TokenKind::Word, "a".to_owned()),
+            (TokenKind::Symbol, "<>".to_owned()),
+            (TokenKind::Word, "b".to_owned()),
+            (TokenKind::Symbol, ",".to_owned()),
+            (TokenKind::Word, "c".to_owned()),
+            (TokenKind::Symbol, "<=".to_owned()),
+            (TokenKind::Symbol, "(".to_owned()),
+            (TokenKind::Word, "d".to_owned()),
+            (TokenKind::Symbol, ")".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn doubled_quotes_are_escaped_quotes() {
+        let tokens = tokenize("'it''s' \"say \"\"hi\"\"\"").unwrap();
+        assert_eq!(tokens[0].text, "it's");
+        assert_eq!(tokens[0].source_text(), "'it''s'");
+        assert_eq!(tokens[1].text, "say \"hi\"");
+        assert_eq!(tokens[1].source_text(), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn positions_are_counted_in_characters() {
+        let tokens = tokenize("name = 'café' AND x = 1").unwrap();
+        let positions: Vec<usize> = tokens.iter().map(|t| t.position).collect();
+        assert_eq!(positions, vec![0, 5, 7, 14, 18, 20, 22]);
+    }
+
+    #[test]
+    fn unclosed_quotes_and_lone_exclamation_marks_are_errors() {
+        assert_eq!(error_position("name = 'abc"), (7, Some("'".to_owned())));
+        assert_eq!(error_position("\"unit price = 4"), (0, Some("\"".to_owned())));
+        assert_eq!(error_position("a ! b"), (2, Some("!".to_owned())));
+    }
+}
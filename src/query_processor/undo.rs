@@ -0,0 +1,165 @@
+use std::fs;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, structures::{db_err::DBError, relation::{changes::RowChanges, io::{load_database, relation_file_path}, table::Table}}};
+
+
+/// the name of the file the last change is kept in, inside `config::HISTORY_PATH`
+pub const UNDO_FILE_NAME: &str = "undo.bin";
+
+
+/// the rows a statement changed, as they were just before it ran
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UndoRecord {
+    pub table_name: String,
+    pub changes: RowChanges,
+    /// the generation of the table once the statement saved it, used to tell if it was changed again since
+    pub changed_generation: u64,
+    pub captured_at: DateTime<Utc>,
+}
+
+
+fn undo_file_path() -> String {
+    format!("{}/{}", config::HISTORY_PATH, UNDO_FILE_NAME)
+}
+
+
+/// saves `table` once a statement has changed it, and keeps the rows it changed (see `Table::track_changes()`)
+/// so the statement can be undone. Returns false without saving if the statement didn't change any rows.
+///
+/// only the last statement can be undone, so this replaces the rows kept for the statement before it.
+pub fn save_with_undo(table: &mut Table, local_path: String) -> Result<bool, DBError> {
+    let changes = table.take_changes();
+    if changes.is_empty() { return Ok(false) }
+
+    table.save(local_path)?;
+    let record = UndoRecord { table_name: table.name(), changes, changed_generation: table.generation(), captured_at: Utc::now() };
+
+    let file_path = undo_file_path();
+    let encoded_data = bincode::serialize(&record).map_err(|_| DBError::DataBaseFileFailure(file_path.clone()))?;
+    fs::write(&file_path, encoded_data).map_err(|e| DBError::IOFailure(file_path, e.to_string()))?;
+    Ok(true)
+}
+
+
+/// the rows changed by the last statement, or `None` if there is nothing to undo
+pub fn last_undo_record() -> Option<UndoRecord> {
+    fs::read(undo_file_path())
+        .ok()
+        .and_then(|data| bincode::deserialize(&data).ok())
+}
+
+
+/// puts the table changed by the last data-modifying statement (INSERT, UPDATE, DELETE, MERGE or GENERATE)
+/// back to how it was before the statement ran. Returns the name of the table.
+///
+/// a statement can only be undone once. Fails with `DBError::TableModified` if the table was changed again
+/// since, instead of throwing away those changes too.
+pub fn undo_last_statement() -> Result<String, DBError> {
+    let record = last_undo_record().ok_or(DBError::NothingToUndo)?;
+    let table_name = record.table_name;
+
+    let mut table = load_database(&relation_file_path(config::RELATION_PATH, &table_name))?;
+    if table.generation() != record.changed_generation {
+        return Err(DBError::TableModified(table_name));
+    }
+
+    table.revert_changes(record.changes)?;
+    table.restore(config::RELATION_PATH.to_owned())?;
+
+    let file_path = undo_file_path();
+    fs::remove_file(&file_path).map_err(|e| DBError::IOFailure(file_path, e.to_string()))?;
+    Ok(table_name)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, sync::Mutex};
+
+    use crate::{config, query_processor::{parser::{parse_expression, parse_statement}, query::{execute_query, QueryResult}}, structures::{column::FieldValue, db_err::DBError, relation::io::{load_database, relation_file_path, relation_lock_file_name}}};
+
+    /// every statement shares the same undo file, so the tests take turns
+    static UNDO_FILE: Mutex<()> = Mutex::new(());
+
+    fn run(command: &str) -> Result<QueryResult, DBError> {
+        execute_query(parse_statement(command)?)
+    }
+
+    /// the values of `n` in the saved table, in row order
+    fn saved_numbers(table_name: &str) -> Vec<FieldValue> {
+        let table = load_database(&relation_file_path(config::RELATION_PATH, table_name)).unwrap();
+        table.rows().iter().map(|row| row["n"].clone()).collect()
+    }
+
+    fn numbers(values: &[f64]) -> Vec<FieldValue> {
+        values.iter().map(|n| FieldValue::Number(*n)).collect()
+    }
+
+    fn create_table(table_name: &str, values: &[f64]) {
+        run(&format!("CREATE TABLE {table_name} (n NUMBER)")).unwrap();
+        for n in values {
+            run(&format!("INSERT INTO {table_name} VALUES ({n})")).unwrap();
+        }
+    }
+
+    /// removes the table, and the undo file which may point at it
+    fn remove_table(table_name: &str) {
+        let _ = fs::remove_file(super::undo_file_path());
+        let _ = fs::remove_file(relation_file_path(config::RELATION_PATH, table_name));
+        let _ = fs::remove_file(format!("{}/{}", config::RELATION_PATH, relation_lock_file_name(table_name)));
+    }
+
+    #[test]
+    fn the_last_statement_can_be_undone_once() {
+        let _turn = UNDO_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        let table_name = "undo_test_once";
+        create_table(table_name, &[1.0, 2.0, 3.0]);
+
+        run(&format!("DELETE FROM {table_name} WHERE n = 2")).unwrap();
+        assert_eq!(saved_numbers(table_name), numbers(&[1.0, 3.0]));
+        run("UNDO").unwrap();
+        assert_eq!(saved_numbers(table_name), numbers(&[1.0, 2.0, 3.0]));
+        assert!(matches!(run("UNDO"), Err(DBError::NothingToUndo)));
+
+        run(&format!("UPDATE {table_name} SET n = 8 WHERE n = 3")).unwrap();
+        assert_eq!(saved_numbers(table_name), numbers(&[1.0, 2.0, 8.0]));
+        run("UNDO").unwrap();
+        assert_eq!(saved_numbers(table_name), numbers(&[1.0, 2.0, 3.0]));
+
+        remove_table(table_name);
+    }
+
+    #[test]
+    fn statements_which_change_nothing_are_not_undone() {
+        let _turn = UNDO_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        let table_name = "undo_test_no_change";
+        create_table(table_name, &[1.0, 2.0]);
+
+        // the delete matches nothing, so UNDO still takes back the last insert
+        run(&format!("DELETE FROM {table_name} WHERE n = 5")).unwrap();
+        run("UNDO").unwrap();
+        assert_eq!(saved_numbers(table_name), numbers(&[1.0]));
+
+        remove_table(table_name);
+    }
+
+    #[test]
+    fn a_table_saved_since_is_not_undone() {
+        let _turn = UNDO_FILE.lock().unwrap_or_else(|e| e.into_inner());
+        let table_name = "undo_test_modified";
+        create_table(table_name, &[1.0]);
+
+        // saved without going through a statement, so the change can't be undone
+        let mut table = load_database(&relation_file_path(config::RELATION_PATH, table_name)).unwrap();
+        table.edit_rows_where("n", &parse_expression("n = 1").unwrap(), FieldValue::Number(4.0)).unwrap();
+        table.save(config::RELATION_PATH.to_owned()).unwrap();
+
+        assert!(matches!(run("UNDO"), Err(DBError::TableModified(name)) if name == table_name));
+        assert_eq!(saved_numbers(table_name), numbers(&[4.0]));
+
+        remove_table(table_name);
+    }
+}
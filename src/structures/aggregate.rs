@@ -0,0 +1,150 @@
+use core::fmt;
+
+use super::column::{DataType, FieldValue};
+
+
+/// an aggregate function applied to each group of rows
+#[derive(Debug, Clone)]
+pub enum Aggregation {
+    /// number of rows in the group
+    Count,
+    /// sum of a number column, ignoring nulls
+    Sum(String),
+    /// average of a number column, ignoring nulls
+    Average(String),
+    /// smallest non-null value in a column
+    Min(String),
+    /// largest non-null value in a column
+    Max(String),
+}
+
+
+impl Aggregation {
+
+    /// the column being aggregated, if there is one
+    pub fn column(&self) -> Option<&String> {
+        match self {
+            Aggregation::Count => None,
+            Aggregation::Sum(c) | Aggregation::Average(c) | Aggregation::Min(c) | Aggregation::Max(c) => Some(c),
+        }
+    }
+
+
+    /// the datatype of the aggregated value, given the datatype of the column being aggregated
+    pub fn result_data_type(&self, column_data_type: Option<&DataType>) -> DataType {
+        match (self, column_data_type) {
+            (Aggregation::Min(_), Some(dt)) | (Aggregation::Max(_), Some(dt)) => dt.clone(),
+            _ => DataType::Number
+        }
+    }
+
+
+    /// parses strings like "count", "sum(price)" or "max(date)" into an aggregation
+    pub fn parse_str(str: &str) -> Option<Aggregation> {
+        let cleaned_string = str.trim().to_lowercase();
+        if cleaned_string == "count" || cleaned_string == "count()" { return Some(Aggregation::Count) }
+
+        let open_paren = str.find('(')?;
+        let close_paren = str.rfind(')')?;
+        if close_paren < open_paren { return None }
+
+        // keep the original casing of the column name
+        let column = str[open_paren + 1..close_paren].trim().to_string();
+        match cleaned_string[..open_paren].trim() {
+            "sum"               => Some(Aggregation::Sum(column)),
+            "avg" | "average"   => Some(Aggregation::Average(column)),
+            "min"               => Some(Aggregation::Min(column)),
+            "max"               => Some(Aggregation::Max(column)),
+            _ => None
+        }
+    }
+}
+
+
+/// the running value of an aggregation for one group
+#[derive(Debug, Clone)]
+pub enum AggregateState {
+    Count(u64),
+    Sum(f64),
+    Average(f64, u64),
+    Min(Option<FieldValue>),
+    Max(Option<FieldValue>),
+}
+
+
+impl AggregateState {
+
+    pub fn new(aggregation: &Aggregation) -> Self {
+        match aggregation {
+            Aggregation::Count      => AggregateState::Count(0),
+            Aggregation::Sum(_)     => AggregateState::Sum(0.0),
+            Aggregation::Average(_) => AggregateState::Average(0.0, 0),
+            Aggregation::Min(_)     => AggregateState::Min(None),
+            Aggregation::Max(_)     => AggregateState::Max(None),
+        }
+    }
+
+
+    /// adds one value to the running aggregate. `value` is `None` for `Count`, which doesn't look at any column.
+    pub fn update(&mut self, value: Option<&FieldValue>) {
+        // nulls are skipped by everything other than count
+        let value = match value {
+            Some(FieldValue::Null) => None,
+            v => v,
+        };
+
+        match (self, value) {
+            (AggregateState::Count(n), _) => *n += 1,
+            (AggregateState::Sum(total), Some(FieldValue::Number(v))) => *total += v,
+            (AggregateState::Average(total, n), Some(FieldValue::Number(v))) => { *total += v; *n += 1; },
+            (AggregateState::Min(current), Some(v)) => {
+                let replace = match current {
+                    Some(c) => v.is_less_than_value(c),
+                    None => true,
+                };
+                if replace { *current = Some(v.clone()); }
+            },
+            (AggregateState::Max(current), Some(v)) => {
+                let replace = match current {
+                    Some(c) => c.is_less_than_value(v),
+                    None => true,
+                };
+                if replace { *current = Some(v.clone()); }
+            },
+            _ => ()
+        }
+    }
+
+
+    /// the final value of the aggregate
+    pub fn result(&self) -> FieldValue {
+        match self {
+            AggregateState::Count(n)            => FieldValue::Number(*n as f64),
+            AggregateState::Sum(total)          => FieldValue::Number(*total),
+            AggregateState::Average(_, 0)       => FieldValue::Null,
+            AggregateState::Average(total, n)   => FieldValue::Number(total / (*n as f64)),
+            AggregateState::Min(v) | AggregateState::Max(v) => v.clone().unwrap_or(FieldValue::Null),
+        }
+    }
+}
+
+
+impl FieldValue {
+    /// compares two values of any datatype, treating values that can't be compared as not less than
+    fn is_less_than_value(&self, other: &FieldValue) -> bool {
+        self.compare_to(other).map(|o| o.is_lt()).unwrap_or(false)
+    }
+}
+
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Aggregation::Count      => write!(f, "count"),
+            Aggregation::Sum(c)     => write!(f, "sum({c})"),
+            Aggregation::Average(c) => write!(f, "avg({c})"),
+            Aggregation::Min(c)     => write!(f, "min({c})"),
+            Aggregation::Max(c)     => write!(f, "max({c})"),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{cell::RefCell, collections::HashMap, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::{Duration, Instant}};
+
+use super::{db_err::DBError, filter::FilterExpr};
+
+
+thread_local! {
+    /// the context of the statement running on this thread, see `StatementContext::run()`
+    static CURRENT_STATEMENT: RefCell<Option<StatementContext>> = const { RefCell::new(None) };
+}
+
+
+/// limits on how long a single statement may run, how many rows it may produce and how much it may hold in memory,
+/// so a runaway query (i.e. a huge cross join) is stopped instead of running forever.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatementLimits {
+    pub timeout: Option<Duration>,
+    pub max_rows: Option<usize>,
+    /// the most sort keys or groups a sort, join or group by keeps in memory before spilling to temporary files.
+    /// Each operator's default budget is used if this isn't set
+    pub memory_budget: Option<usize>,
+}
+
+
+/// stops the statement it was taken from, see `StatementContext::cancel_handle()`.
+///
+/// this only sets a flag, so it is safe to call from a Ctrl+C handler or another thread.
+#[derive(Debug, Clone)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+
+/// the limits of one statement, the default filters of the session running it, and whether it has been cancelled.
+/// Each statement gets its own context, so statements
+/// running at the same time (i.e. in two sessions on different threads) don't share a timeout or row limit,
+/// and cancelling one doesn't stop the others.
+///
+/// the timeout starts counting when the context is made.
+#[derive(Debug, Clone)]
+pub struct StatementContext {
+    limits: StatementLimits,
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+    /// filters every table the statement reads, by table name. See `Session::set_filter()`
+    default_filters: HashMap<String, FilterExpr>,
+}
+
+
+impl Default for StatementContext {
+    fn default() -> Self {
+        StatementContext::new(StatementLimits::default())
+    }
+}
+
+
+impl StatementContext {
+
+    pub fn new(limits: StatementLimits) -> Self {
+        StatementContext {
+            limits,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            default_filters: HashMap::new(),
+        }
+    }
+
+
+    /// keeps only the rows matching `default_filters[table]` whenever the statement reads `table`
+    pub fn with_default_filters(mut self, default_filters: HashMap<String, FilterExpr>) -> Self {
+        self.default_filters = default_filters;
+        self
+    }
+
+
+    pub fn limits(&self) -> StatementLimits { self.limits }
+
+
+    /// a handle which stops this statement as soon as it safely can, i.e. to hand to a Ctrl+C handler
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancelled.clone())
+    }
+
+
+    /// runs `statement` with this as the current context of the thread, so every loop it runs is checked against
+    /// this context's limits by `check_cancelled()` and `check_row_limit()`. The previous context is put back afterwards.
+    pub fn run<T>(&self, statement: impl FnOnce() -> T) -> T {
+        /// puts back the previous context even if the statement panics
+        struct RestoreOnDrop(Option<StatementContext>);
+        impl Drop for RestoreOnDrop {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                CURRENT_STATEMENT.with(|current| *current.borrow_mut() = previous);
+            }
+        }
+
+        let previous = CURRENT_STATEMENT.with(|current| current.borrow_mut().replace(self.clone()));
+        let _restore = RestoreOnDrop(previous);
+        statement()
+    }
+
+
+    /// returns `DBError::Cancelled` if the statement was cancelled, or `DBError::TimedOut` if it has run past its timeout
+    pub fn check_cancelled(&self) -> Result<(), DBError> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(DBError::Cancelled);
+        }
+
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout) {
+            if Instant::now() > deadline {
+                return Err(DBError::TimedOut(timeout));
+            }
+        }
+        Ok(())
+    }
+
+
+    /// returns `DBError::RowLimitExceeded` if the statement has produced more rows than it is allowed to
+    pub fn check_row_limit(&self, rows_produced: usize) -> Result<(), DBError> {
+        match self.limits.max_rows {
+            Some(max_rows) if rows_produced > max_rows => Err(DBError::RowLimitExceeded(max_rows)),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+/// checks the statement running on this thread with `StatementContext::check_cancelled()`.
+/// Outside of a statement (see `StatementContext::run()`), nothing is ever cancelled.
+///
+/// ## Usage
+/// call this inside of any loop that may run for a long time, i.e. `check_cancelled()?;`
+pub fn check_cancelled() -> Result<(), DBError> {
+    CURRENT_STATEMENT.with(|current| match &*current.borrow() {
+        Some(context) => context.check_cancelled(),
+        None => Ok(()),
+    })
+}
+
+
+/// checks the statement running on this thread with `StatementContext::check_row_limit()`.
+/// Outside of a statement (see `StatementContext::run()`), there is no row limit.
+///
+/// ## Usage
+/// call this as rows are added to a result, i.e. `check_row_limit(result.rows().len())?;`
+pub fn check_row_limit(rows_produced: usize) -> Result<(), DBError> {
+    CURRENT_STATEMENT.with(|current| match &*current.borrow() {
+        Some(context) => context.check_row_limit(rows_produced),
+        None => Ok(()),
+    })
+}
+
+
+/// the memory budget of the statement running on this thread, or `default` if it doesn't set one
+pub fn memory_budget_or(default: usize) -> usize {
+    CURRENT_STATEMENT.with(|current| {
+        current.borrow().as_ref().and_then(|context| context.limits.memory_budget).unwrap_or(default)
+    })
+}
+
+
+
+/// the default filter on `table` of the statement running on this thread, if it has one.
+/// Outside of a statement (see `StatementContext::run()`), nothing is filtered.
+pub fn default_filter(table: &str) -> Option<FilterExpr> {
+    CURRENT_STATEMENT.with(|current| {
+        current.borrow().as_ref().and_then(|context| context.default_filters.get(table).cloned())
+    })
+}
@@ -2,7 +2,9 @@ use core::fmt;
 use std::cmp::Ordering;
 
 use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use super::db_err::DBError;
 use url::Url;
 
@@ -11,20 +13,118 @@ use url::Url;
 pub struct Column {
     name: String,
     data_type: DataType,
-    is_primary_key: bool
+    is_primary_key: bool,
+    /// if set, the column holds sensitive data and is masked when the table is redacted
+    mask: Option<MaskingStrategy>,
+    /// if false, the column can't hold `FieldValue::Null`
+    allows_nulls: bool,
+    /// if true, no two rows can hold the same value in the column. Nulls aren't counted
+    is_unique: bool,
 }
 
 
 impl Column {
     pub fn new(name: String, data_type: DataType, is_primary_key: bool) -> Self {
-        Column {name:name, data_type:data_type, is_primary_key:is_primary_key }
+        Column { name, data_type, is_primary_key, mask: None, allows_nulls: true, is_unique: false }
     }
 
     pub fn get_name(&self)       -> &str      { &self.name }
     pub fn get_data_type(&self)  -> &DataType { &self.data_type }
     pub fn is_primary_key(&self) -> bool      { self.is_primary_key }
+    pub fn is_sensitive(&self)   -> bool      { self.mask.is_some() }
+    pub fn get_mask(&self)       -> Option<&MaskingStrategy> { self.mask.as_ref() }
+    pub fn allows_nulls(&self)   -> bool      { self.allows_nulls }
+    pub fn is_unique(&self)      -> bool      { self.is_unique }
     pub fn change_pk_state(&mut self, is_pk: bool)  { self.is_primary_key = is_pk; }
     pub fn new_name(&mut self, new_name: String) { self.name = new_name; }
+    pub fn set_mask(&mut self, mask: Option<MaskingStrategy>) { self.mask = mask; }
+    pub fn set_allows_nulls(&mut self, allows_nulls: bool) { self.allows_nulls = allows_nulls; }
+    pub fn set_unique(&mut self, is_unique: bool) { self.is_unique = is_unique; }
+}
+
+
+/// characters which can't be used in a column name, since they are used by the query parser and CSV files
+pub const INVALID_IDENTIFIER_CHARS: [char; 8] = [',', ':', '(', ')', '"', '\n', '\r', '\t'];
+
+
+/// makes sure a user given column name can be used in queries and exported files.
+/// 
+/// ## Rules
+/// 1. it can't be empty, or start / end with a space
+/// 2. it can't start with a digit
+/// 3. it can't contain any of `INVALID_IDENTIFIER_CHARS`
+/// 
+/// names with spaces are allowed, but must be written in double quotes in queries, i.e. `"First Name"`
+pub fn validate_column_name(name: &str) -> Result<(), DBError> {
+    let invalid = |reason: &str| Err(DBError::InvalidColumnName(name.to_string(), reason.to_string()));
+
+    if name.trim().is_empty() {
+        return invalid("column names can't be empty");
+    }
+    if name.trim() != name {
+        return invalid("column names can't start or end with a space");
+    }
+    if name.chars().next().unwrap().is_ascii_digit() {
+        return invalid("column names can't start with a digit");
+    }
+    if let Some(c) = name.chars().find(|c| INVALID_IDENTIFIER_CHARS.contains(c)) {
+        return invalid(&format!("column names can't contain '{}'", c.escape_default()));
+    }
+
+    Ok(())
+}
+
+
+/// wraps a column name in double quotes if it needs them to be used in a query
+pub fn quote_identifier(name: &str) -> String {
+    if name.contains(char::is_whitespace) { format!("\"{}\"", name) } else { name.to_string() }
+}
+
+
+/// how the values of a sensitive column are hidden from users who aren't allowed to see them
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum MaskingStrategy {
+    /// replaces the value with a hash of itself keyed by the database's masking key, so equal values can still
+    /// be matched up, but values can't be guessed by hashing likely inputs
+    Hash,
+    /// only shows the last `n` characters of the value, i.e. "*******4321"
+    Partial(usize),
+    /// replaces every value with the same string
+    Fixed(String)
+}
+
+
+impl MaskingStrategy {
+    /// masks the value using this strategy. Null values stay null.
+    /// `key` is the database's masking key (see `masking_key()`), which is only used by `MaskingStrategy::Hash`.
+    /// 
+    /// ## Note
+    /// the masked value is always a `FieldValue::String`, regardless of the original datatype
+    pub fn apply(&self, value: &FieldValue, key: &[u8]) -> FieldValue {
+        if value.eq(&FieldValue::Null) { return FieldValue::Null; }
+
+        let plaintext = value.to_string();
+        match self {
+            MaskingStrategy::Hash => {
+                // HMAC-SHA256 gives the same output on every build, so masked values can be compared across versions
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                mac.update(plaintext.as_bytes());
+                let digest = mac.finalize().into_bytes();
+                FieldValue::String( digest[..16].iter().map(|b| format!("{:02x}", b)).collect() )
+            },
+            MaskingStrategy::Partial(visible_chars) => {
+                let number_of_chars = plaintext.chars().count();
+                let hidden_chars = number_of_chars.saturating_sub(*visible_chars);
+                let masked: String = plaintext
+                    .chars()
+                    .enumerate()
+                    .map(|(idx, c)| if idx < hidden_chars { '*' } else { c })
+                    .collect();
+                FieldValue::String(masked)
+            },
+            MaskingStrategy::Fixed(replacement) => FieldValue::String(replacement.clone()),
+        }
+    }
 }
 
 
@@ -52,12 +152,12 @@ pub enum FieldValue {
 impl PartialOrd for FieldValue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
-            (FieldValue::String(s1), FieldValue::String(s2)) => Some(s1.cmp(s2)),
-            (FieldValue::Number(n1), FieldValue::Number(n2)) => Some(n1.total_cmp(n2)),
-            (FieldValue::Date(d1), FieldValue::Date(d2)) => Some(d1.cmp(d2)),
-            (FieldValue::Url(u1), FieldValue::Url(u2)) => Some(u1.cmp(u2)),
-            (FieldValue::Boolean(b1), FieldValue::Boolean(b2)) => Some(b1.cmp(b2)),
-            _ => None
+            // nulls come first, the same as `Ord`, so comparisons agree with the order of an index
+            (FieldValue::Null, FieldValue::Null) => Some(Ordering::Equal),
+            (FieldValue::Null, _) => Some(Ordering::Less),
+            (_, FieldValue::Null) => Some(Ordering::Greater),
+            // values of different types can't be compared
+            _ => self.compare_to( other ).ok()
         }
     }
 }
@@ -67,7 +167,13 @@ impl Eq for FieldValue {}
 
 impl Ord for FieldValue {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.compare_to( other ).unwrap()
+        // nulls come first, so a nullable column can still be indexed and sorted
+        match (self, other) {
+            (FieldValue::Null, FieldValue::Null) => Ordering::Equal,
+            (FieldValue::Null, _) => Ordering::Less,
+            (_, FieldValue::Null) => Ordering::Greater,
+            _ => self.compare_to( other ).unwrap(),
+        }
     }
     
     fn max(self, other: Self) -> Self
@@ -127,6 +233,51 @@ pub fn parse_str(str: &str) -> DataType {
 impl FieldValue {
     pub fn to_string(&self) -> String { format!("{}", self) }
 
+    /// a string which is unique to this value and its datatype, so values can be used as `HashMap` keys.
+    /// (FieldValue can't implement `Hash` since numbers are floats)
+    pub fn to_key_string(&self) -> String { format!("{}:{}", self.data_type(), self) }
+
+}
+
+
+/// how strictly a users input is converted into a `FieldValue`
+#[derive(Debug, Clone)]
+pub enum ParseMode {
+    /// guesses the datatype, falling back to `String` if nothing else fits
+    Lenient,
+    /// the value must be of the given datatype (or null), otherwise an error is returned
+    Strict(DataType),
+}
+
+
+/// converts a users input into a `FieldValue` using the given parse mode.
+/// 
+/// in both modes, an empty string or "null" (in any casing) is parsed as `FieldValue::Null`.
+/// 
+/// ## Usage
+/// use `ParseMode::Strict` whenever the datatype of the column is known, i.e. when inserting into a table.
+pub fn parse_field_value(value: &str, mode: ParseMode) -> Result<FieldValue, DBError> {
+    match mode {
+        ParseMode::Lenient => Ok(parse_into_field_value(&value.to_string())),
+        ParseMode::Strict(data_type) => parse_as_data_type(value, &data_type),
+    }
+}
+
+
+/// strictly parses a value as the given datatype. See `parse_field_value()`
+pub fn parse_as_data_type(value: &str, data_type: &DataType) -> Result<FieldValue, DBError> {
+    let trimmed_value = value.trim();
+    if is_null_literal(trimmed_value) { return Ok(FieldValue::Null) }
+
+    let parsed_value = match data_type {
+        DataType::String  => Some(FieldValue::String(value.to_string())),
+        DataType::Number  => parse_number(trimmed_value).map(FieldValue::Number),
+        DataType::Date    => parse_date(trimmed_value).map(FieldValue::Date),
+        DataType::Url     => Url::parse(trimmed_value).ok().map(|u| FieldValue::Url(Into::<String>::into(u))),
+        DataType::Boolean => parse_boolean(trimmed_value).map(FieldValue::Boolean),
+    };
+
+    parsed_value.ok_or(DBError::InvalidValue(value.to_string(), data_type.clone()))
 }
 
 
@@ -136,34 +287,74 @@ impl FieldValue {
 /// ## Usage
 /// used for parsing a users input into a datatype
 pub fn parse_into_field_value(value: &String) -> FieldValue {
-    // 1. Try parsing as Boolean
-    let lower_value = value.to_lowercase();
-    if lower_value == "true" {
-        return FieldValue::Boolean(true);
-    } else if lower_value == "false" {
-        return FieldValue::Boolean(false);
+    // 1. Check for null values
+    if is_null_literal(value.trim()) {
+        return FieldValue::Null;
     }
 
-    // 2. Try parsing as Number (f64)
-    if let Ok(num) = value.parse::<f64>() {
+    // 2. Try parsing as Boolean
+    if let Some(b) = parse_boolean(value.trim()) {
+        return FieldValue::Boolean(b);
+    }
+
+    // 3. Try parsing as Number (f64)
+    if let Some(num) = parse_number(value.trim()) {
         return FieldValue::Number(num);
     }
 
-    // 3. Try parsing as Date (YYYY-MM-DD)
-    if let Ok(naive_date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+    // 4. Try parsing as Date (YYYY-MM-DD)
+    if let Some(date) = parse_date(value.trim()) {
+        return FieldValue::Date(date);
+    }
+
+    // 5. Try parsing as URL. 
+    // only URLs with a host are accepted, otherwise strings like "note:something" would count as URLs
+    if let Ok(parsed_url) = Url::parse(value.trim()) {
+        if parsed_url.has_host() {
+            return FieldValue::Url(Into::<String>::into(parsed_url));
+        }
+    }
+
+    // 6. If none of the above, return as String
+    FieldValue::String(value.to_string())
+}
+
+
+fn is_null_literal(value: &str) -> bool {
+    value.is_empty() || value.eq_ignore_ascii_case("null")
+}
+
+
+fn parse_boolean(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None
+    }
+}
+
+
+/// parses a number, rejecting values like "NaN" or "inf" which rust accepts but aren't real numbers
+fn parse_number(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+
+/// parses dates in the forms "YYYY-MM-DD", "YYYY-MM-DD HH:MM:SS", and the way dates are displayed ("YYYY-MM-DD HH:MM:SS UTC")
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(naive_date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
         // Combine date with default time "00:00:00"
         let date_str = format!("{}-{}-{} 00:00:00", naive_date.year(), naive_date.month(), naive_date.day());
         let date = NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S").unwrap();
-        return FieldValue::Date(Utc.from_utc_datetime(&date));
+        return Some(Utc.from_utc_datetime(&date));
     }
 
-    // 4. Try parsing as URL
-    if let Ok(parsed_url) = Url::parse(&value) {
-        return FieldValue::Url(Into::<String>::into(parsed_url));
+    let without_timezone = value.strip_suffix(" UTC").unwrap_or(value);
+    if let Ok(date) = NaiveDateTime::parse_from_str(without_timezone, "%Y-%m-%d %H:%M:%S%.f") {
+        return Some(Utc.from_utc_datetime(&date));
     }
 
-    // 5. If none of the above, return as String
-    FieldValue::String(value.to_string())
+    None
 }
 
 
@@ -0,0 +1,104 @@
+use core::fmt;
+
+
+/// what to do when an inserted row has the same primary key as a row already in the table
+#[derive(Debug, Clone)]
+pub enum ConflictPolicy {
+    /// fail with `DBError::DuplicatePrimaryKey` (the default)
+    Error,
+    /// keep the existing row, and skip the new one
+    Ignore,
+    /// overwrite every column of the existing row with the new row
+    Replace,
+    /// only overwrite the listed columns of the existing row (an "upsert")
+    Update(Vec<String>),
+}
+
+
+/// what ended up happening to a row inserted with a `ConflictPolicy`
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsertOutcome {
+    Inserted,
+    Ignored,
+    Updated,
+}
+
+
+/// what `Table::merge_from()` does to a target row which has a matching row in the source table
+#[derive(Debug, Clone)]
+pub enum MatchedAction {
+    /// overwrite the target row with every column the source row shares with it
+    Update,
+    /// only overwrite the listed columns
+    UpdateColumns(Vec<String>),
+    /// leave the target row as is
+    Nothing,
+}
+
+
+/// what `Table::merge_from()` does with a source row that has no matching row in the target table
+#[derive(Debug, Clone)]
+pub enum NotMatchedAction {
+    Insert,
+    Nothing,
+}
+
+
+/// how many rows were changed by `Table::merge_from()`
+#[derive(Debug, Clone, Default)]
+pub struct MergeSummary {
+    pub updated: u32,
+    pub inserted: u32,
+    pub skipped: u32,
+}
+
+
+impl MatchedAction {
+    /// parses "update" or "nothing", the word that follows `WHEN MATCHED` in a query
+    pub fn parse_str(str: &str) -> Option<MatchedAction> {
+        match str.trim().to_lowercase().as_str() {
+            "update"  => Some(MatchedAction::Update),
+            "nothing" => Some(MatchedAction::Nothing),
+            _ => None
+        }
+    }
+}
+
+
+impl NotMatchedAction {
+    /// parses "insert" or "nothing", the word that follows `WHEN NOT MATCHED` in a query
+    pub fn parse_str(str: &str) -> Option<NotMatchedAction> {
+        match str.trim().to_lowercase().as_str() {
+            "insert"  => Some(NotMatchedAction::Insert),
+            "nothing" => Some(NotMatchedAction::Nothing),
+            _ => None
+        }
+    }
+}
+
+
+impl ConflictPolicy {
+    /// parses "nothing", "replace" or "update", the words that follow `ON CONFLICT DO` in a query.
+    /// `update_columns` is only used for "update".
+    pub fn parse_str(str: &str, update_columns: Vec<String>) -> Option<ConflictPolicy> {
+        match str.trim().to_lowercase().as_str() {
+            "nothing" | "ignore" => Some(ConflictPolicy::Ignore),
+            "replace"            => Some(ConflictPolicy::Replace),
+            "update"             => Some(ConflictPolicy::Update(update_columns)),
+            "error"              => Some(ConflictPolicy::Error),
+            _ => None
+        }
+    }
+}
+
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictPolicy::Error           => write!(f, "error"),
+            ConflictPolicy::Ignore          => write!(f, "do nothing"),
+            ConflictPolicy::Replace         => write!(f, "replace"),
+            ConflictPolicy::Update(columns) => write!(f, "update ({})", columns.join(", ")),
+        }
+    }
+}
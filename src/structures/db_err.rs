@@ -1,10 +1,52 @@
 use core::fmt;
-use std::error::Error;
+use std::{error::Error, time::Duration};
 
-use super::{column::DataType, filter::{FilterCondition, FilterConditionValue}};
+use super::{column::{DataType, FieldValue}, filter::{FilterCondition, FilterConditionValue}};
 
 
 
+/// points at the exact value which broke a constraint, so errors from bulk changes can be tracked down
+#[derive(Debug, Clone)]
+pub struct ConstraintContext {
+    pub table: String,
+    pub column: String,
+    pub value: FieldValue,
+    /// the index of the row involved, if there is one
+    pub row: Option<usize>,
+}
+
+
+impl fmt::Display for ConstraintContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value '{}' in column '{}' of table '{}'", self.value, self.column, self.table)
+    }
+}
+
+
+/// points at the token of a query which couldn't be parsed, and says what was expected there instead
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub query: String,
+    /// the offset (in characters) of the offending token in `query`
+    pub position: usize,
+    /// the offending token, or `None` if the query ended too early
+    pub token: Option<String>,
+    pub expected: String,
+}
+
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.token {
+            Some(token) => write!(f, "expected {}, but found '{}' at position {}", self.expected, token, self.position)?,
+            None => write!(f, "expected {}, but the query ended", self.expected)?,
+        }
+        // underline where the problem is
+        write!(f, "\n    {}\n    {}^", self.query, " ".repeat(self.position))
+    }
+}
+
+
 #[derive(Debug)]
 pub enum DBError {
 
@@ -17,10 +59,22 @@ pub enum DBError {
 
     InvalidColumn(String),
 
+    /// thrown when a column name could refer to columns of more than one table. first is the name, second is what it could be
+    AmbiguousColumn(String, Vec<String>),
+
     MissingModifyCriteria(FilterCondition),
 
-    /// primary key column name
-    DuplicatePrimaryKey(String),
+    /// the row is the existing row which already has the value
+    DuplicatePrimaryKey(ConstraintContext),
+
+    /// thrown when a value written to a row doesn't match its column. second is the column's datatype
+    WrongValueType(ConstraintContext, DataType),
+
+    /// thrown when a value written to a unique column is already in another row, which is the row held
+    DuplicateValue(ConstraintContext),
+
+    /// thrown when NULL is written to a column which doesn't allow nulls
+    NullNotAllowed(ConstraintContext),
 
     /// thrown if a user tries to delete a primary key column
     MandatoryColumn(String),
@@ -35,7 +89,67 @@ pub enum DBError {
     MisMatchConditionDataType(FilterConditionValue, FilterConditionValue),
 
     /// first is filename, second is error message
-    IOFailure(String, String)
+    IOFailure(String, String),
+
+    /// thrown when a row index is out of bounds for the table
+    RowNotFound(usize),
+
+    /// thrown when a row was changed by someone else. first is expected version, second is actual
+    VersionConflict(u64, u64),
+
+    /// thrown when writing to a table which can only be read, i.e. a system table. holds the table name
+    ReadOnlyTable(String),
+
+    /// thrown when a session isn't allowed to run a statement. first is the session's user, second is the statement
+    PermissionDenied(String, String),
+
+    /// thrown when a table was saved by someone else after it was loaded, holds the table name
+    TableModified(String),
+
+    /// thrown when an operation was stopped early by the user
+    Cancelled,
+
+    /// thrown when a statement ran longer than its timeout, holds the timeout
+    TimedOut(Duration),
+
+    /// thrown when a statement produced more rows than it is allowed to, holds the limit
+    RowLimitExceeded(usize),
+
+    /// thrown when a cartesian join would produce too many rows. first is the size of the result, second is the limit
+    CartesianTooLarge(usize, usize),
+
+    /// thrown when a query can't be parsed, holds the query
+    InvalidQuery(String),
+
+    /// thrown when part of a query can't be parsed, points at the token which broke it
+    InvalidSyntax(SyntaxError),
+
+    /// thrown when a value can't be parsed as the expected datatype. first is the value, second is the expected datatype
+    InvalidValue(String, DataType),
+
+    /// thrown when a column name breaks the naming rules. first is the name, second is the reason
+    InvalidColumnName(String, String),
+
+    /// thrown when a table name breaks the naming rules. first is the name, second is the reason
+    InvalidTableName(String, String),
+
+    /// thrown when a value is written directly to a computed column
+    ComputedColumn(String),
+
+    /// thrown when a line of an imported file doesn't fit the table. first is the line number, second is the reason
+    MalformedRow(usize, String),
+
+    /// thrown when the number of values being inserted doesn't match the number of columns. first is expected, second is actual
+    ValueCountMismatch(usize, usize),
+
+    /// thrown when undoing, but no statement has changed a table since the last undo
+    NothingToUndo,
+
+    /// thrown when a tag can't be attached to a table or column. first is the tag name, second is the reason
+    InvalidTag(String, String),
+
+    /// thrown when a table has no saved layout with the given name. first is the table, second is the layout name
+    UnknownLayout(String, String),
 }
 
 
@@ -54,10 +168,26 @@ impl fmt::Display for DBError {
                 => write!(f, "expected datatype '{}', but got '{}'", expected, actual),
             DBError::InvalidColumn(name) 
                 => write!(f, "the column '{}' does not exist in the database", name),
+            DBError::AmbiguousColumn(name, candidates)
+                => write!(f, "the column '{}' is ambiguous, it could be any of: {}", name, candidates.join(", ")),
             DBError::MissingModifyCriteria(modify_type) 
                 => write!(f, "the row modify method '{}' is missing a value", modify_type),
-            DBError::DuplicatePrimaryKey(pk_col_name) 
-                => write!(f, "You cannot insert a primary key value that already exists in the column '{}'", pk_col_name),
+            DBError::DuplicatePrimaryKey(context) => match context.row {
+                Some(row) => write!(f, "the primary key {} already exists in row {}", context, row),
+                None => write!(f, "the primary key {} already exists", context),
+            },
+            DBError::WrongValueType(context, expected) => match context.row {
+                Some(row) => write!(f, "the {} (row {}) is a '{}', but the column holds '{}'", context, row, context.value.data_type(), expected),
+                None => write!(f, "the {} is a '{}', but the column holds '{}'", context, context.value.data_type(), expected),
+            },
+            DBError::DuplicateValue(context) => match context.row {
+                Some(row) => write!(f, "the {} already exists in row {}, but the column is unique", context, row),
+                None => write!(f, "the {} already exists, but the column is unique", context),
+            },
+            DBError::NullNotAllowed(context) => match context.row {
+                Some(row) => write!(f, "the column '{}' of table '{}' can't be null (row {})", context.column, context.table, row),
+                None => write!(f, "the column '{}' of table '{}' can't be null", context.column, context.table),
+            },
             DBError::MandatoryColumn(col_name) 
                 => write!(f, "The column '{}' is a requirement for this or other tables.", col_name),
             DBError::DataBaseFileFailure(file_path)
@@ -68,6 +198,46 @@ impl fmt::Display for DBError {
                 => write!(f, "expected condtion type '{}', got '{}' for a condition.", expected.name(), actual.name()),
             DBError::IOFailure(filename, msg)
                 => write!(f, "An error has occurred with file {}: {}", filename, msg),
+            DBError::RowNotFound(row_index)
+                => write!(f, "there is no row at index {}", row_index),
+            DBError::VersionConflict(expected, actual)
+                => write!(f, "expected row version {}, but the row is at version {}. It was modified by someone else.", expected, actual),
+            DBError::ReadOnlyTable(name)
+                => write!(f, "the table '{}' is read only", name),
+            DBError::PermissionDenied(user, statement)
+                => write!(f, "'{}' isn't allowed to run '{}', the session is read only", user, statement),
+            DBError::TableModified(name)
+                => write!(f, "the table '{}' was modified by another session since it was loaded. Reload it and try again", name),
+            DBError::Cancelled
+                => write!(f, "the operation was cancelled"),
+            DBError::TimedOut(timeout)
+                => write!(f, "the statement was stopped after running for longer than {} ms", timeout.as_millis()),
+            DBError::RowLimitExceeded(max_rows)
+                => write!(f, "the statement was stopped for producing more than {} row(s)", max_rows),
+            DBError::CartesianTooLarge(rows, max_rows)
+                => write!(f, "the cartesian join would produce {} rows, more than the limit of {}. Use ALLOW CARTESIAN to run it anyways", rows, max_rows),
+            DBError::InvalidQuery(query)
+                => write!(f, "unable to parse the query '{}'", query),
+            DBError::InvalidSyntax(context)
+                => write!(f, "unable to parse the query: {}", context),
+            DBError::InvalidValue(value, expected)
+                => write!(f, "'{}' is not a valid {}", value, expected),
+            DBError::InvalidColumnName(name, reason)
+                => write!(f, "'{}' is not a valid column name: {}", name, reason),
+            DBError::InvalidTableName(name, reason)
+                => write!(f, "'{}' is not a valid table name: {}", name, reason),
+            DBError::MalformedRow(line_number, reason)
+                => write!(f, "line {} can't be imported: {}", line_number, reason),
+            DBError::ValueCountMismatch(expected, actual)
+                => write!(f, "expected {} value(s), one for each column, but got {}", expected, actual),
+            DBError::NothingToUndo
+                => write!(f, "there is no change to undo"),
+            DBError::InvalidTag(key, reason)
+                => write!(f, "'{}' is not a valid tag: {}", key, reason),
+            DBError::UnknownLayout(table, name)
+                => write!(f, "the table '{}' has no layout called '{}'", table, name),
+            DBError::ComputedColumn(name)
+                => write!(f, "the column '{}' is computed from other columns, and can't be changed directly", name),
         }
     }
 }
\ No newline at end of file
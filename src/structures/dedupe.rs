@@ -0,0 +1,19 @@
+/// which row is kept when `Table::dedupe()` finds duplicates
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeepDuplicate {
+    /// keep the row which was inserted first
+    First,
+    /// keep the row which was inserted last
+    Last,
+}
+
+
+impl KeepDuplicate {
+    pub fn parse_str(str: &str) -> Option<KeepDuplicate> {
+        match str.trim().to_lowercase().as_str() {
+            "first" => Some(KeepDuplicate::First),
+            "last"  => Some(KeepDuplicate::Last),
+            _ => None
+        }
+    }
+}
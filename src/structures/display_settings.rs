@@ -0,0 +1,136 @@
+use std::{fs, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::RELATION_PATH;
+
+use super::{column::FieldValue, db_err::DBError};
+
+
+/// the file in the relation directory which keeps the display settings
+pub const DISPLAY_SETTINGS_FILE_NAME: &str = "display_settings.bin";
+
+
+/// how booleans are shown
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BooleanStyle {
+    /// `true` and `false` (the default)
+    Words,
+    /// `✓` and `✗`
+    Symbols,
+}
+
+
+/// how values are written out by `Table::to_ascii()`. These only change how values look, never the values themselves.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisplaySettings {
+    /// put between every 3 digits of a number's whole part, i.e. `1,234,567`. `None` shows the digits as they are
+    pub thousands_separator: Option<char>,
+    /// the number of digits shown after the decimal point, rounding if needed. `None` shows as many as it takes
+    pub decimal_places: Option<usize>,
+    pub boolean_style: BooleanStyle,
+    /// shown in place of an empty value
+    pub null_text: String,
+}
+
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings { thousands_separator: None, decimal_places: None, boolean_style: BooleanStyle::Words, null_text: String::from("Null") }
+    }
+}
+
+
+/// the settings last loaded or saved, so tables can be displayed without reading the settings file every time
+static LOADED_SETTINGS: Mutex<Option<DisplaySettings>> = Mutex::new(None);
+
+
+fn display_settings_file_path() -> String {
+    format!("{}/{}", RELATION_PATH, DISPLAY_SETTINGS_FILE_NAME)
+}
+
+
+impl DisplaySettings {
+
+    /// the saved display settings, or the defaults if none were saved.
+    ///
+    /// the file is only read the first time, after that the settings are kept in memory and replaced by
+    /// `DisplaySettings::save()`. Settings saved by another process are picked up by `DisplaySettings::reload()`
+    pub fn load() -> Self {
+        let mut loaded_settings = LOADED_SETTINGS.lock().unwrap_or_else(|e| e.into_inner());
+        loaded_settings.get_or_insert_with(DisplaySettings::read_file).clone()
+    }
+
+
+    /// reads the saved display settings again, instead of using the ones kept in memory
+    pub fn reload() -> Self {
+        let settings = DisplaySettings::read_file();
+        *LOADED_SETTINGS.lock().unwrap_or_else(|e| e.into_inner()) = Some(settings.clone());
+        settings
+    }
+
+
+    fn read_file() -> Self {
+        fs::read(display_settings_file_path())
+            .ok()
+            .and_then(|data| bincode::deserialize(&data).ok())
+            .unwrap_or_default()
+    }
+
+
+    /// saves the settings, so they are used every time a table is displayed
+    pub fn save(&self) -> Result<(), DBError> {
+        let file_path = display_settings_file_path();
+        let encoded_data = bincode::serialize(self).map_err(|_| DBError::DataBaseFileFailure(file_path.clone()))?;
+        fs::write(&file_path, encoded_data).map_err(|e| DBError::IOFailure(file_path, e.to_string()))?;
+        *LOADED_SETTINGS.lock().unwrap_or_else(|e| e.into_inner()) = Some(self.clone());
+        Ok(())
+    }
+
+
+    /// writes out a value with these settings
+    pub fn format(&self, value: &FieldValue) -> String {
+        match value {
+            FieldValue::Number(n)  => self.format_number(*n),
+            FieldValue::Boolean(b) => match (&self.boolean_style, b) {
+                (BooleanStyle::Words, _)       => b.to_string(),
+                (BooleanStyle::Symbols, true)  => String::from("✓"),
+                (BooleanStyle::Symbols, false) => String::from("✗"),
+            },
+            FieldValue::Null => self.null_text.clone(),
+            _ => value.to_string(),
+        }
+    }
+
+
+    fn format_number(&self, n: f64) -> String {
+        let text = match self.decimal_places {
+            Some(places) => format!("{:.*}", places, n),
+            None => n.to_string(),
+        };
+
+        let separator = match self.thousands_separator {
+            Some(separator) if n.is_finite() => separator,
+            _ => return text,
+        };
+
+        let (sign, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text.as_str()),
+        };
+        let (whole, fraction) = match unsigned.find('.') {
+            Some(point) => unsigned.split_at(point),
+            None => (unsigned, ""),
+        };
+
+        let mut grouped = String::new();
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+
+        format!("{sign}{grouped}{fraction}")
+    }
+}
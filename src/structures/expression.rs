@@ -0,0 +1,248 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{column::FieldValue, db_err::DBError};
+
+
+/// arithmetic over the number columns of a row, used to define computed columns (i.e. `price * qty`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Expression {
+    Number(f64),
+    Column(String),
+    Negate(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+}
+
+
+/// when the value of a computed column is worked out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ComputeMode {
+    /// computed whenever the row is inserted or changed, and saved along with the rest of the row
+    Stored,
+    /// never saved, computed again every time the table is loaded
+    Virtual,
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComputedColumn {
+    pub name: String,
+    pub expression: Expression,
+    pub mode: ComputeMode,
+}
+
+
+impl Expression {
+
+    /// parses an expression like `price * qty` or `("Unit Price" - discount) / 2`.
+    ///
+    /// column names with spaces or symbols in them must be wrapped in double quotes.
+    pub fn parse_str(str: &str) -> Result<Expression, DBError> {
+        let tokens = tokenize(str)?;
+        let mut parser = Parser { tokens: &tokens, position: 0, source: str };
+        let expression = parser.parse_sum()?;
+        if parser.position != tokens.len() {
+            return Err(parser.error("unexpected text after the end of the expression"));
+        }
+        Ok(expression)
+    }
+
+
+    /// every column the expression reads from
+    pub fn columns(&self) -> Vec<String> {
+        match self {
+            Expression::Number(_) => Vec::new(),
+            Expression::Column(c) => vec![c.clone()],
+            Expression::Negate(e) => e.columns(),
+            Expression::Add(l, r) | Expression::Subtract(l, r) | Expression::Multiply(l, r) | Expression::Divide(l, r) => {
+                let mut columns = l.columns();
+                columns.extend(r.columns());
+                columns
+            }
+        }
+    }
+
+
+    /// works out the value of the expression for a row. `column_value` gives the value in a column of the row.
+    ///
+    /// if any of the columns are null, or a division by zero happens, the result is null.
+    pub fn evaluate<'a, F>(&self, column_value: &F) -> Result<FieldValue, DBError> 
+    where F: Fn(&str) -> Option<&'a FieldValue> {
+
+        let number = match self.evaluate_number(column_value)? {
+            Some(n) => n,
+            None => return Ok(FieldValue::Null),
+        };
+        if number.is_finite() { Ok(FieldValue::Number(number)) } else { Ok(FieldValue::Null) }
+    }
+
+
+    fn evaluate_number<'a, F>(&self, column_value: &F) -> Result<Option<f64>, DBError> 
+    where F: Fn(&str) -> Option<&'a FieldValue> {
+
+        fn both<'a, F>(l: &Expression, r: &Expression, column_value: &F) -> Result<Option<(f64, f64)>, DBError>
+        where F: Fn(&str) -> Option<&'a FieldValue> {
+            match (l.evaluate_number(column_value)?, r.evaluate_number(column_value)?) {
+                (Some(a), Some(b)) => Ok(Some((a, b))),
+                _ => Ok(None)
+            }
+        }
+
+        Ok(match self {
+            Expression::Number(n) => Some(*n),
+            Expression::Column(c) => match column_value(c) {
+                Some(FieldValue::Number(n)) => Some(*n),
+                Some(FieldValue::Null) => None,
+                Some(other) => return Err(DBError::MisMatchDataType(super::column::DataType::Number, other.data_type())),
+                None => return Err(DBError::InvalidColumn(c.clone())),
+            },
+            Expression::Negate(e) => e.evaluate_number(column_value)?.map(|n| -n),
+            Expression::Add(l, r)      => both(l, r, column_value)?.map(|(a, b)| a + b),
+            Expression::Subtract(l, r) => both(l, r, column_value)?.map(|(a, b)| a - b),
+            Expression::Multiply(l, r) => both(l, r, column_value)?.map(|(a, b)| a * b),
+            Expression::Divide(l, r)   => both(l, r, column_value)?.and_then(|(a, b)| if b == 0.0 { None } else { Some(a / b) }),
+        })
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Column(String),
+    Operator(char),
+    OpenParen,
+    CloseParen,
+}
+
+
+fn tokenize(str: &str) -> Result<Vec<Token>, DBError> {
+    let invalid = |reason: &str| DBError::InvalidQuery(format!("{str} ({reason})"));
+
+    let mut tokens = Vec::new();
+    let mut chars = str.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => { chars.next(); },
+            '+' | '-' | '*' | '/' => { chars.next(); tokens.push(Token::Operator(c)); },
+            '(' => { chars.next(); tokens.push(Token::OpenParen); },
+            ')' => { chars.next(); tokens.push(Token::CloseParen); },
+            '"' => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(invalid("missing closing quote")),
+                    }
+                }
+                tokens.push(Token::Column(name));
+            },
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(&d) = chars.peek() {
+                    if !d.is_ascii_digit() && d != '.' { break }
+                    number.push(d);
+                    chars.next();
+                }
+                let n = number.parse::<f64>().map_err(|_| invalid("invalid number"))?;
+                tokens.push(Token::Number(n));
+            },
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&d) = chars.peek() {
+                    if !d.is_alphanumeric() && d != '_' { break }
+                    name.push(d);
+                    chars.next();
+                }
+                tokens.push(Token::Column(name));
+            },
+            _ => return Err(invalid(&format!("unexpected character '{c}'"))),
+        }
+    }
+    Ok(tokens)
+}
+
+
+/// recursive descent parser, where `*` and `/` bind tighter than `+` and `-`
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    source: &'a str,
+}
+
+impl Parser<'_> {
+
+    fn error(&self, reason: &str) -> DBError {
+        DBError::InvalidQuery(format!("{} ({reason})", self.source))
+    }
+
+    fn peek(&self) -> Option<&Token> { self.tokens.get(self.position) }
+
+    fn parse_sum(&mut self) -> Result<Expression, DBError> {
+        let mut left = self.parse_product()?;
+        while let Some(Token::Operator(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.parse_product()?;
+            left = if op == '+' {
+                Expression::Add(Box::new(left), Box::new(right))
+            } else {
+                Expression::Subtract(Box::new(left), Box::new(right))
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_product(&mut self) -> Result<Expression, DBError> {
+        let mut left = self.parse_term()?;
+        while let Some(Token::Operator(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.position += 1;
+            let right = self.parse_term()?;
+            left = if op == '*' {
+                Expression::Multiply(Box::new(left), Box::new(right))
+            } else {
+                Expression::Divide(Box::new(left), Box::new(right))
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_term(&mut self) -> Result<Expression, DBError> {
+        let token = self.peek().cloned().ok_or(self.error("expression ended early"))?;
+        self.position += 1;
+
+        match token {
+            Token::Number(n) => Ok(Expression::Number(n)),
+            Token::Column(c) => Ok(Expression::Column(c)),
+            Token::Operator('-') => Ok(Expression::Negate(Box::new(self.parse_term()?))),
+            Token::OpenParen => {
+                let inner = self.parse_sum()?;
+                if self.peek() != Some(&Token::CloseParen) { return Err(self.error("missing closing bracket")) }
+                self.position += 1;
+                Ok(inner)
+            },
+            _ => Err(self.error("expected a number, column or bracket")),
+        }
+    }
+}
+
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Number(n)         => write!(f, "{n}"),
+            Expression::Column(c)         => write!(f, "\"{c}\""),
+            Expression::Negate(e)         => write!(f, "-({e})"),
+            Expression::Add(l, r)         => write!(f, "({l} + {r})"),
+            Expression::Subtract(l, r)    => write!(f, "({l} - {r})"),
+            Expression::Multiply(l, r)    => write!(f, "({l} * {r})"),
+            Expression::Divide(l, r)      => write!(f, "({l} / {r})"),
+        }
+    }
+}
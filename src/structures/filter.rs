@@ -1,7 +1,10 @@
 use core::fmt;
+use std::collections::HashMap;
 
 use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 
+use super::{column::FieldValue, db_err::DBError, relation::search::non_index_row_matches_search_critieria};
+
 #[derive(Debug, Clone)]
 pub enum FilterConditionValue {
     String(String),
@@ -88,21 +91,47 @@ pub enum FilterCondition {
     /// an inclusive range between two dates.
     DateBetween(FilterConditionValue),
 
+    /// the text of the cell is close to the given text. The second value is the minimum similarity, from 0 to 1.
+    ///
+    /// similarity is `1 - (edit distance / length of the longer text)`, ignoring case.
+    SimilarTo(String, f64),
+
+    /// the text of the cell matches a regular expression. The pattern is compiled once, when the condition is made.
+    #[cfg(feature = "regex")]
+    Matches(regex::Regex),
 }
 
 impl FilterCondition {
     pub fn parse_str(input: &str) -> Option<FilterCondition> {
         
         println!("parsing `{:?}` as a filter condition...", input);
+
+        // MATCHES (pattern), checked before anything is lowercased since patterns are case sensitive
+        #[cfg(feature = "regex")]
+        if let Some(pattern) = input.trim().strip_prefix("matches ").or(input.trim().strip_prefix("MATCHES ")) {
+            return FilterCondition::matches(pattern.trim().trim_matches('"')).ok();
+        }
+
         let condition_components: Vec<String> = input
             .trim()
             .to_lowercase()
             .split_whitespace()
             .map(|s| str::to_string(s))
             .collect();
+        if condition_components.is_empty() { return None }
 
         println!("conditioncomponents[0] = {}", &condition_components[0]);
 
+        // SIMILAR_TO (text) (threshold)
+        if condition_components[0] == "similar_to" {
+            let original_components: Vec<&str> = input.split_whitespace().collect();
+            let threshold = original_components.last()?.parse::<f64>().ok()?;
+            if original_components.len() < 3 || !(0.0..=1.0).contains(&threshold) { return None }
+
+            let text = original_components[1..original_components.len() - 1].join(" ");
+            return Some(FilterCondition::SimilarTo(text.trim_matches(|c| c == '\'' || c == '"').to_string(), threshold));
+        }
+
         // check if the filter condition is an inequality, equals, or not equals
         let valid_relational_operators = vec!["<", "<=", "=", "!=", ">=", ">"];
         if valid_relational_operators.contains(&condition_components[0].as_str()) {
@@ -128,8 +157,8 @@ impl FilterCondition {
                     return Some(FilterCondition::DateBetween(FilterConditionValue::DateRange(lower_bound, upper_bound)))
                 }
                 "numbers" => {
-                    let lower_bound = condition_components[1].parse::<f64>().unwrap();
-                    let upper_bound = condition_components[2].parse::<f64>().unwrap();
+                    let lower_bound = condition_components.get(2)?.parse::<f64>().ok()?;
+                    let upper_bound = condition_components.get(3)?.parse::<f64>().ok()?;
                     return Some( FilterCondition::NumberBetween(FilterConditionValue::NumberRange(lower_bound, upper_bound)))
                 }
                 _ => (),
@@ -146,6 +175,88 @@ impl FilterCondition {
 }
 
 
+/// a combination of conditions on one or more columns, i.e. `age > 30 AND city = 'Ottawa' OR active = true`
+///
+/// `And` and `Or` only check their second expression if the first doesn't decide the result.
+#[derive(Debug, Clone)]
+pub enum FilterExpr {
+    /// a single condition on a column
+    Leaf(String, FilterCondition),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+    Not(Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    pub fn leaf(column_name: &str, condition: FilterCondition) -> FilterExpr {
+        FilterExpr::Leaf(column_name.to_string(), condition)
+    }
+
+    pub fn and(self, other: FilterExpr) -> FilterExpr { FilterExpr::And(Box::new(self), Box::new(other)) }
+
+    pub fn or(self, other: FilterExpr) -> FilterExpr { FilterExpr::Or(Box::new(self), Box::new(other)) }
+
+    pub fn negate(self) -> FilterExpr { FilterExpr::Not(Box::new(self)) }
+
+
+    /// every column the expression has a condition on, in the order they appear
+    pub fn columns(&self) -> Vec<&String> {
+        match self {
+            FilterExpr::Leaf(column_name, _) => vec![column_name],
+            FilterExpr::And(left, right) | FilterExpr::Or(left, right) => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            },
+            FilterExpr::Not(expr) => expr.columns(),
+        }
+    }
+
+
+    /// the same expression with every column renamed by `rename`, stopping at the first error
+    pub fn try_map_columns<F>(self, rename: &F) -> Result<FilterExpr, DBError>
+    where F: Fn(&str) -> Result<String, DBError> {
+        Ok(match self {
+            FilterExpr::Leaf(column_name, condition) => FilterExpr::Leaf(rename(&column_name)?, condition),
+            FilterExpr::And(left, right) => left.try_map_columns(rename)?.and( right.try_map_columns(rename)? ),
+            FilterExpr::Or(left, right) => left.try_map_columns(rename)?.or( right.try_map_columns(rename)? ),
+            FilterExpr::Not(expr) => expr.try_map_columns(rename)?.negate(),
+        })
+    }
+
+
+    /// determines if a row matches the expression. Columns missing from the row are treated as `Null`
+    pub fn matches(&self, row: &HashMap<String, FieldValue>) -> Result<bool, DBError> {
+        match self {
+            FilterExpr::Leaf(column_name, condition) => {
+                let row_value = row.get(column_name).unwrap_or(&FieldValue::Null);
+                non_index_row_matches_search_critieria(row_value, condition)
+            },
+            FilterExpr::And(left, right) => Ok( left.matches(row)? && right.matches(row)? ),
+            FilterExpr::Or(left, right) => Ok( left.matches(row)? || right.matches(row)? ),
+            FilterExpr::Not(expr) => Ok( !expr.matches(row)? ),
+        }
+    }
+}
+
+impl From<(String, FilterCondition)> for FilterExpr {
+    fn from((column_name, condition): (String, FilterCondition)) -> Self {
+        FilterExpr::Leaf(column_name, condition)
+    }
+}
+
+
+#[cfg(feature = "regex")]
+impl FilterCondition {
+    /// compiles `pattern` into a `FilterCondition::Matches`
+    pub fn matches(pattern: &str) -> Result<FilterCondition, crate::structures::db_err::DBError> {
+        regex::Regex::new(pattern)
+            .map(FilterCondition::Matches)
+            .map_err(|e| crate::structures::db_err::DBError::InvalidQuery(format!("{pattern} ({e})")))
+    }
+}
+
+
 fn parse_into_date(str: &str) -> Option<DateTime<Utc>> {
 
     let separator = if str.contains("-") {"-"} else {"/"};
@@ -192,6 +303,20 @@ impl fmt::Display for FilterCondition {
             FilterCondition::NotEqual(v)             => write!(f, "!= {v}"),
             FilterCondition::DateBetween(v)          => write!(f, "In the inclusive range {v}"), 
             FilterCondition::NumberBetween(v)        => write!(f, "In the inclusive range {v}"), 
+            FilterCondition::SimilarTo(text, t)      => write!(f, "Similar to '{text}' (at least {t})"),
+            #[cfg(feature = "regex")]
+            FilterCondition::Matches(pattern)        => write!(f, "Matches /{pattern}/"),
+        }
+    }
+}
+
+impl fmt::Display for FilterExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterExpr::Leaf(column_name, condition) => write!(f, "{column_name} {condition}"),
+            FilterExpr::And(left, right)             => write!(f, "({left} AND {right})"),
+            FilterExpr::Or(left, right)              => write!(f, "({left} OR {right})"),
+            FilterExpr::Not(expr)                    => write!(f, "NOT {expr}"),
         }
     }
 }
\ No newline at end of file
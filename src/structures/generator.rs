@@ -0,0 +1,122 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+
+use super::column::{Column, DataType, FieldValue};
+
+
+const FIRST_NAMES: [&str; 24] = [
+    "Alice", "Ben", "Chloe", "Daniel", "Emma", "Felix", "Grace", "Henry", "Isla", "Jack", "Kira", "Liam",
+    "Maya", "Noah", "Olivia", "Priya", "Quinn", "Ravi", "Sofia", "Theo", "Uma", "Victor", "Wen", "Zoe",
+];
+
+const LAST_NAMES: [&str; 20] = [
+    "Anderson", "Brown", "Chen", "Dubois", "Evans", "Fischer", "Garcia", "Hughes", "Ivanov", "Johnson",
+    "Kim", "Lopez", "Miller", "Nguyen", "O'Brien", "Patel", "Rossi", "Smith", "Tremblay", "Wilson",
+];
+
+const WORDS: [&str; 24] = [
+    "apple", "bridge", "canyon", "delta", "ember", "forest", "glacier", "harbor", "island", "jungle", "kettle", "lantern",
+    "meadow", "nebula", "orchard", "pepper", "quartz", "river", "summit", "timber", "umbra", "valley", "willow", "zephyr",
+];
+
+
+/// how `Table::generate()` makes up values for a column
+#[derive(Debug, Clone)]
+pub enum ValueGenerator {
+    /// a first and last name, i.e. "Grace Hughes"
+    Name,
+    /// an email address made from a name, i.e. "grace.hughes42@example.com"
+    Email,
+    /// a random word
+    Word,
+    /// a unique code made from the prefix and a counter, i.e. "ID-000042"
+    Identifier { prefix: String, next: u64 },
+    /// counts up by one from the given number, used for numeric primary keys
+    Sequence(f64),
+    /// a number spread evenly between the bounds, rounded to 2 decimal places
+    Uniform(f64, f64),
+    /// a number following a bell curve with the mean and standard deviation, rounded to 2 decimal places
+    Normal(f64, f64),
+    /// a date spread evenly between the bounds
+    DateRange(DateTime<Utc>, DateTime<Utc>),
+    /// true with the given probability
+    Boolean(f64),
+    /// a link to a made up page
+    Url,
+    /// picks one of the values
+    OneOf(Vec<FieldValue>),
+}
+
+
+impl ValueGenerator {
+
+    /// picks a generator based on the column's datatype, and its name for strings (i.e. "email" or "name" columns).
+    ///
+    /// primary keys always get a generator which never repeats a value.
+    pub fn default_for(column: &Column) -> ValueGenerator {
+        let name = column.get_name().to_lowercase();
+
+        match column.get_data_type() {
+            DataType::Number if column.is_primary_key() => ValueGenerator::Sequence(1.0),
+            DataType::String if column.is_primary_key() => ValueGenerator::Identifier { prefix: "ID-".to_string(), next: 1 },
+            DataType::String if name.contains("email") => ValueGenerator::Email,
+            DataType::String if name.contains("name") => ValueGenerator::Name,
+            DataType::String => ValueGenerator::Word,
+            DataType::Number => ValueGenerator::Uniform(0.0, 1000.0),
+            DataType::Date => ValueGenerator::DateRange(Utc::now() - Duration::days(365), Utc::now()),
+            DataType::Boolean => ValueGenerator::Boolean(0.5),
+            DataType::Url => ValueGenerator::Url,
+        }
+    }
+
+
+    /// makes the next value. Counting generators (`Sequence` and `Identifier`) move to their next value.
+    pub fn generate(&mut self, rng: &mut StdRng) -> FieldValue {
+        match self {
+            ValueGenerator::Name => {
+                FieldValue::String(format!("{} {}", FIRST_NAMES.choose(rng).unwrap(), LAST_NAMES.choose(rng).unwrap()))
+            },
+            ValueGenerator::Email => {
+                let first = FIRST_NAMES.choose(rng).unwrap().to_lowercase();
+                let last = LAST_NAMES.choose(rng).unwrap().to_lowercase().replace('\'', "");
+                FieldValue::String(format!("{}.{}{}@example.com", first, last, rng.gen_range(1..100)))
+            },
+            ValueGenerator::Word => FieldValue::String(WORDS.choose(rng).unwrap().to_string()),
+            ValueGenerator::Identifier { prefix, next } => {
+                let value = FieldValue::String(format!("{}{:06}", prefix, next));
+                *next += 1;
+                value
+            },
+            ValueGenerator::Sequence(next) => {
+                let value = FieldValue::Number(*next);
+                *next += 1.0;
+                value
+            },
+            ValueGenerator::Uniform(min, max) => {
+                let n = if min < max { rng.gen_range(*min..*max) } else { *min };
+                FieldValue::Number(round_to_cents(n))
+            },
+            ValueGenerator::Normal(mean, std_dev) => {
+                // Box-Muller transform, since rand doesn't come with a normal distribution
+                let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+                let u2: f64 = rng.gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                FieldValue::Number(round_to_cents(*mean + z * *std_dev))
+            },
+            ValueGenerator::DateRange(start, end) => {
+                let span = (*end - *start).num_seconds().max(0);
+                FieldValue::Date(*start + Duration::seconds(rng.gen_range(0..=span)))
+            },
+            ValueGenerator::Boolean(probability) => FieldValue::Boolean(rng.gen_bool(probability.clamp(0.0, 1.0))),
+            ValueGenerator::Url => {
+                FieldValue::Url(format!("https://example.com/{}/{}", WORDS.choose(rng).unwrap(), rng.gen_range(1..10_000)))
+            },
+            ValueGenerator::OneOf(values) => values.choose(rng).cloned().unwrap_or(FieldValue::Null),
+        }
+    }
+}
+
+
+fn round_to_cents(n: f64) -> f64 {
+    (n * 100.0).round() / 100.0
+}
@@ -0,0 +1,19 @@
+/// the most rows `Table::cartesian_join()` will produce before refusing to run
+pub const DEFAULT_MAX_CARTESIAN_ROWS: usize = 1_000_000;
+
+
+/// how `Table::cartesian_join_guarded()` treats joins which would produce a lot of rows
+#[derive(Debug, Clone, PartialEq)]
+pub enum CartesianGuard {
+    /// refuse the join if it would produce more than this many rows
+    Limit(usize),
+    /// run the join no matter how large it is
+    Allow,
+}
+
+
+impl Default for CartesianGuard {
+    fn default() -> Self {
+        CartesianGuard::Limit(DEFAULT_MAX_CARTESIAN_ROWS)
+    }
+}
@@ -2,4 +2,14 @@ pub mod column;
 pub mod db_err;
 pub mod filter;
 pub mod sort;
-pub mod relation;
\ No newline at end of file
+pub mod relation;
+pub mod aggregate;
+pub mod cancel;
+pub mod progress;
+pub mod conflict;
+pub mod expression;
+pub mod dedupe;
+pub mod validation;
+pub mod join;
+pub mod generator;
+pub mod display_settings;
@@ -0,0 +1,44 @@
+/// called with the number of records processed so far, and the estimated total number of records
+pub type ProgressCallback<'a> = &'a mut dyn FnMut(usize, usize);
+
+/// the callback is only called once every this many records, so it doesn't slow down the operation
+pub const PROGRESS_REPORT_INTERVAL: usize = 1000;
+
+
+/// wraps an optional progress callback so long running operations can report progress without
+/// checking if anyone is listening.
+pub struct ProgressReporter<'a> {
+    callback: Option<ProgressCallback<'a>>,
+    total: usize,
+}
+
+
+impl<'a> ProgressReporter<'a> {
+    pub fn new(callback: Option<ProgressCallback<'a>>, total: usize) -> Self {
+        ProgressReporter { callback, total }
+    }
+
+    /// reports that `processed` records have been handled so far
+    pub fn report(&mut self, processed: usize) {
+        if !processed.is_multiple_of(PROGRESS_REPORT_INTERVAL) && processed != self.total { return }
+        if let Some(callback) = self.callback.as_mut() {
+            callback(processed, self.total);
+        }
+    }
+}
+
+
+/// renders a text progress bar, i.e. `[##########..........]  50% (500/1000)`
+pub fn render_progress_bar(processed: usize, total: usize, width: usize) -> String {
+    let fraction = if total == 0 { 1.0 } else { (processed as f64 / total as f64).min(1.0) };
+    let filled = (fraction * width as f64).round() as usize;
+
+    format!(
+        "[{}{}] {:>3}% ({}/{})",
+        "#".repeat(filled),
+        ".".repeat(width - filled),
+        (fraction * 100.0).round() as usize,
+        processed,
+        total
+    )
+}
@@ -0,0 +1,135 @@
+use std::{collections::{hash_map::DefaultHasher, HashMap}, hash::{Hash, Hasher}};
+
+use crate::structures::{aggregate::{AggregateState, Aggregation}, cancel::{check_cancelled, check_row_limit, memory_budget_or}, column::{Column, DataType, FieldValue}, db_err::DBError};
+
+use super::{spill::{Row, SpillFile}, table::Table};
+
+
+/// the default number of groups kept in memory at once by `Table::group_by()`
+pub const DEFAULT_GROUP_MEMORY_BUDGET: usize = 100_000;
+
+/// the number of files rows are split into when the in-memory groups overflow
+const SPILL_PARTITIONS: u64 = 8;
+
+/// after this many rounds of spilling, the memory budget is ignored instead of spilling again
+const MAX_SPILL_DEPTH: u32 = 6;
+
+
+impl Table {
+
+    /// groups the rows of the table by the values in `group_column`, and computes each aggregation per group.
+    ///
+    /// the result has the group column, followed by one column per aggregation (i.e. "sum(price)").
+    /// This uses the statement's memory budget, or `DEFAULT_GROUP_MEMORY_BUDGET`, see `Table::group_by_with_budget()`.
+    pub fn group_by(&self, group_column: &String, aggregations: Vec<Aggregation>) -> Result<Table, DBError> {
+        self.group_by_with_budget(group_column, aggregations, memory_budget_or(DEFAULT_GROUP_MEMORY_BUDGET))
+    }
+
+
+    /// same as `Table::group_by()`, but at most `max_groups_in_memory` groups are kept in memory at once.
+    ///
+    /// once the budget is full, rows belonging to new groups are split into temporary files by their
+    /// group value, and each file is aggregated separately afterwards. The temporary files are always
+    /// deleted, even if the aggregation fails.
+    pub fn group_by_with_budget(&self, group_column: &String, aggregations: Vec<Aggregation>, max_groups_in_memory: usize) -> Result<Table, DBError> {
+
+        let group_col = self.column(group_column.to_string()).ok_or(DBError::InvalidColumn(group_column.to_string()))?;
+
+        let mut result_columns: Vec<Column> = vec![ Column::new(group_column.to_string(), group_col.get_data_type().clone(), false) ];
+
+        for aggregation in &aggregations {
+            let aggregated_column = match aggregation.column() {
+                Some(c) => Some(self.column(c.to_string()).ok_or(DBError::InvalidColumn(c.to_string()))?),
+                None => None,
+            };
+
+            // sums and averages only make sense on numbers
+            if let (Aggregation::Sum(_) | Aggregation::Average(_), Some(col)) = (aggregation, &aggregated_column) {
+                if !col.get_data_type().eq(&DataType::Number) {
+                    return Err(DBError::MisMatchDataType(DataType::Number, col.get_data_type().clone()));
+                }
+            }
+
+            let data_type = aggregation.result_data_type(aggregated_column.as_ref().map(|c| c.get_data_type()));
+            result_columns.push( Column::new(aggregation.to_string(), data_type, false) );
+        }
+
+        let mut result_table = Table::new(
+            format!("{} grouped by {}", &self.name, group_column),
+            result_columns,
+            true
+        );
+
+        let rows = self.rows.iter().map(|r| Ok(r.clone()));
+        aggregate_rows(rows, group_column, &aggregations, max_groups_in_memory.max(1), 0, &mut result_table)?;
+
+        Ok(result_table)
+    }
+}
+
+
+/// aggregates a stream of rows into `result_table`, spilling rows to disk when there are too many groups.
+fn aggregate_rows(
+    rows: impl Iterator<Item = Result<Row, DBError>>,
+    group_column: &String,
+    aggregations: &Vec<Aggregation>,
+    max_groups_in_memory: usize,
+    depth: u32,
+    result_table: &mut Table
+) -> Result<(), DBError> {
+
+    // FieldValue can't be hashed, so groups are keyed by their string form
+    let mut groups: HashMap<String, (FieldValue, Vec<AggregateState>)> = HashMap::new();
+    // keeps the output in the order the groups were first seen
+    let mut group_order: Vec<String> = Vec::new();
+    let mut spill_files: Vec<SpillFile> = Vec::new();
+
+    for row in rows {
+        check_cancelled()?;
+        let row = row?;
+        let group_value = row.get(group_column).cloned().unwrap_or(FieldValue::Null);
+        let key = group_value.to_key_string();
+
+        if !groups.contains_key(&key) && groups.len() >= max_groups_in_memory && depth < MAX_SPILL_DEPTH {
+            // there isn't room for another group, so this row gets dealt with later
+            if spill_files.is_empty() {
+                for partition in 0..SPILL_PARTITIONS {
+                    spill_files.push( SpillFile::create(&format!("group_{}_{}", depth, partition))? );
+                }
+            }
+            let mut hasher = DefaultHasher::new();
+            (depth, &key).hash(&mut hasher);
+            let partition = (hasher.finish() % SPILL_PARTITIONS) as usize;
+            spill_files[partition].write_row(&row)?;
+            continue;
+        }
+
+        let (_, states) = groups.entry(key.clone()).or_insert_with(|| {
+            group_order.push(key);
+            (group_value, aggregations.iter().map(AggregateState::new).collect())
+        });
+
+        for (state, aggregation) in states.iter_mut().zip(aggregations) {
+            state.update( aggregation.column().and_then(|c| row.get(c)) );
+        }
+    }
+
+    for key in group_order {
+        let (group_value, states) = groups.remove(&key).unwrap();
+        let mut result_row: Row = HashMap::new();
+        result_row.insert(group_column.to_string(), group_value);
+        for (state, aggregation) in states.iter().zip(aggregations) {
+            result_row.insert(aggregation.to_string(), state.result());
+        }
+        result_table.insert_row(&result_row)?;
+        check_row_limit(result_table.rows().len())?;
+    }
+
+    // every group in a spill file is disjoint from the groups above, so each file can be aggregated on its own
+    for mut spill_file in spill_files {
+        let spilled_rows = spill_file.read_rows()?;
+        aggregate_rows(spilled_rows, group_column, aggregations, max_groups_in_memory, depth + 1, result_table)?;
+    }
+
+    Ok(())
+}
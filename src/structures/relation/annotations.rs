@@ -0,0 +1,150 @@
+use std::{collections::{BTreeMap, HashMap}, fs};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::RELATION_PATH, structures::{column::{Column, DataType, FieldValue}, db_err::DBError}};
+
+use super::table::Table;
+
+
+/// the file in the relation directory which keeps the notes attached to rows of every table
+pub const ANNOTATIONS_FILE_NAME: &str = "row_annotations.bin";
+
+/// the column added by `Table::with_annotations()`
+pub const NOTE_COLUMN: &str = "Note";
+
+
+/// the primary key values of a row, as `(column, value)` pairs in the order of the table's primary keys
+type RowKey = Vec<(String, FieldValue)>;
+
+
+/// a note attached to a row, i.e. to label an anomaly found while reviewing data
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RowAnnotation {
+    /// the row's primary key values. Rows are found by their key, since their positions change when rows are deleted
+    pub key: RowKey,
+    pub note: String,
+    pub written_at: DateTime<Utc>,
+}
+
+
+fn annotations_file_path() -> String {
+    format!("{}/{}", RELATION_PATH, ANNOTATIONS_FILE_NAME)
+}
+
+
+/// the notes of every table, keyed by table name
+fn load_annotations() -> HashMap<String, Vec<RowAnnotation>> {
+    fs::read(annotations_file_path())
+        .ok()
+        .and_then(|data| bincode::deserialize(&data).ok())
+        .unwrap_or_default()
+}
+
+
+fn save_annotations(annotations: &HashMap<String, Vec<RowAnnotation>>) -> Result<(), DBError> {
+    let file_path = annotations_file_path();
+    let encoded_data = bincode::serialize(annotations).map_err(|_| DBError::DataBaseFileFailure(file_path.clone()))?;
+    fs::write(&file_path, encoded_data).map_err(|e| DBError::IOFailure(file_path, e.to_string()))
+}
+
+
+impl Table {
+
+    /// the primary key values of the row at `row_index`. Rows can only be annotated if the table has primary keys
+    fn row_key(&self, row_index: usize) -> Result<RowKey, DBError> {
+        let row = self.rows.get(row_index).ok_or(DBError::RowNotFound(row_index))?;
+
+        let mut key: RowKey = Vec::new();
+        for pk in &self.primary_keys {
+            let value = row.get(pk.get_name()).ok_or(DBError::MissingPrimaryKeys(vec![pk.get_name().to_string()]))?;
+            key.push( (pk.get_name().to_string(), value.clone()) );
+        }
+        if key.is_empty() { return Err(DBError::MissingPrimaryKeys(Vec::new())) }
+        Ok(key)
+    }
+
+
+    /// attaches a note to the row at `row_index`, replacing any note it already has.
+    ///
+    /// notes are kept separately from the table, so the rows themselves don't change and the table doesn't need to be saved.
+    pub fn annotate_row(&self, row_index: usize, note: &str) -> Result<(), DBError> {
+        let key = self.row_key(row_index)?;
+
+        let mut annotations = load_annotations();
+        let table_annotations = annotations.entry(self.name.clone()).or_default();
+        table_annotations.retain(|a| a.key != key);
+        table_annotations.push(RowAnnotation { key, note: note.to_string(), written_at: Utc::now() });
+        save_annotations(&annotations)
+    }
+
+
+    /// removes the note from the row at `row_index`. Returns false if the row had no note
+    pub fn remove_annotation(&self, row_index: usize) -> Result<bool, DBError> {
+        let key = self.row_key(row_index)?;
+
+        let mut annotations = load_annotations();
+        let table_annotations = match annotations.get_mut(&self.name) {
+            Some(table_annotations) => table_annotations,
+            None => return Ok(false),
+        };
+        let number_of_notes = table_annotations.len();
+        table_annotations.retain(|a| a.key != key);
+        if table_annotations.len() == number_of_notes { return Ok(false) }
+        if table_annotations.is_empty() { annotations.remove(&self.name); }
+
+        save_annotations(&annotations)?;
+        Ok(true)
+    }
+
+
+    /// the note on every annotated row, keyed by the row's current position.
+    /// Notes on rows which have since been deleted, or whose key was changed, are left out.
+    pub fn annotations(&self) -> HashMap<usize, RowAnnotation> {
+        let mut notes_by_key: BTreeMap<RowKey, RowAnnotation> = load_annotations()
+            .remove(&self.name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|a| (a.key.clone(), a))
+            .collect();
+        if notes_by_key.is_empty() { return HashMap::new() }
+
+        let mut annotations: HashMap<usize, RowAnnotation> = HashMap::new();
+        for row_index in 0..self.rows.len() {
+            if let Some(annotation) = self.row_key(row_index).ok().and_then(|key| notes_by_key.remove(&key)) {
+                annotations.insert(row_index, annotation);
+            }
+        }
+        annotations
+    }
+
+
+    /// the note on the row at `row_index`, if it has one
+    pub fn annotation(&self, row_index: usize) -> Option<RowAnnotation> {
+        self.annotations().remove(&row_index)
+    }
+
+
+    /// a copy of the table with an extra `Note` column holding each row's note (or null), so the notes
+    /// can be seen in `Table::to_ascii()` and exported along with the rows.
+    pub fn with_annotations(&self) -> Result<Table, DBError> {
+        if self.is_valid_column(&NOTE_COLUMN.to_string()) {
+            return Err(DBError::InvalidColumnName(NOTE_COLUMN.to_string(), "the table already has a column with this name".to_string()));
+        }
+
+        let mut annotations = self.annotations();
+
+        let mut table = self.clone();
+        table.name = format!("{} with notes", self.name);
+        table.columns.push( Column::new(NOTE_COLUMN.to_string(), DataType::String, false) );
+        for (row_index, row) in table.rows.iter_mut().enumerate() {
+            let note = match annotations.remove(&row_index) {
+                Some(annotation) => FieldValue::String(annotation.note),
+                None => FieldValue::Null,
+            };
+            row.insert(NOTE_COLUMN.to_string(), note);
+        }
+        Ok(table)
+    }
+}
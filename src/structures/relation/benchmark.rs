@@ -0,0 +1,132 @@
+use std::{fmt, fs, time::{Duration, Instant}};
+
+use comfy_table::presets::ASCII_MARKDOWN;
+
+use crate::{config::{INDEX_PATH, TEMP_PATH}, structures::{
+    column::{Column, DataType}, 
+    db_err::DBError, 
+    filter::{FilterCondition, FilterConditionValue}, 
+    generator::ValueGenerator, 
+    sort::SortCondition
+}};
+
+use super::{io::index_file_name, table::Table};
+
+
+/// the seed used for the benchmark's data, so every run works on the same rows
+const BENCHMARK_SEED: u64 = 4750;
+
+
+/// how long one step of `run_benchmark()` took
+#[derive(Debug, Clone)]
+pub struct BenchmarkStep {
+    pub name: String,
+    /// the number of rows the step worked on or produced
+    pub rows: usize,
+    pub elapsed: Duration,
+}
+
+
+/// the timings of every step of `run_benchmark()`
+#[derive(Debug, Clone)]
+pub struct BenchmarkReport {
+    pub rows: usize,
+    pub steps: Vec<BenchmarkStep>,
+}
+
+
+impl BenchmarkReport {
+    pub fn total(&self) -> Duration {
+        self.steps.iter().map(|s| s.elapsed).sum()
+    }
+}
+
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text_table = comfy_table::Table::new();
+        text_table.set_header(vec!["step", "rows", "time (ms)", "rows / second"]);
+
+        for step in &self.steps {
+            let seconds = step.elapsed.as_secs_f64();
+            let rate = if seconds > 0.0 { format!("{:.0}", step.rows as f64 / seconds) } else { "-".to_owned() };
+            text_table.add_row(vec![
+                step.name.clone(),
+                step.rows.to_string(),
+                format!("{:.2}", seconds * 1000.0),
+                rate,
+            ]);
+        }
+        text_table.load_preset(ASCII_MARKDOWN).remove_style(comfy_table::TableComponent::HorizontalLines);
+
+        write!(f, "benchmark with {} rows, {:.2} ms in total\n{}", self.rows, self.total().as_secs_f64() * 1000.0, text_table)
+    }
+}
+
+
+/// times a standard set of operations on a generated table with `n_rows` rows:
+/// bulk insert, point lookup, range filter, sort, join and CSV export.
+///
+/// the tables only exist for the benchmark, and their index and export files are deleted afterwards.
+pub fn run_benchmark(n_rows: usize) -> Result<BenchmarkReport, DBError> {
+    let table_name = format!("benchmark_{}", std::process::id());
+    let join_table_name = format!("{}_join", table_name);
+
+    let result = run_steps(&table_name, &join_table_name, n_rows);
+
+    let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(&table_name, "id")));
+    let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(&join_table_name, "key")));
+    result
+}
+
+
+fn run_steps(table_name: &str, join_table_name: &str, n_rows: usize) -> Result<BenchmarkReport, DBError> {
+    let mut report = BenchmarkReport { rows: n_rows, steps: Vec::new() };
+    let mut time_step = |name: &str, rows: usize, start: Instant| {
+        report.steps.push(BenchmarkStep { name: name.to_owned(), rows, elapsed: start.elapsed() });
+    };
+
+    let start = Instant::now();
+    let mut table = Table::generate(table_name.to_owned(), vec![
+        (Column::new("id".to_owned(), DataType::Number, true), ValueGenerator::Sequence(1.0)),
+        (Column::new("name".to_owned(), DataType::String, false), ValueGenerator::Name),
+        (Column::new("score".to_owned(), DataType::Number, false), ValueGenerator::Normal(500.0, 150.0)),
+    ], n_rows, BENCHMARK_SEED)?;
+    time_step("bulk insert", n_rows, start);
+
+    let start = Instant::now();
+    let lookup_id = (n_rows / 2).max(1) as f64;
+    let found = table.select_rows(&"id".to_owned(), FilterCondition::Equal(FilterConditionValue::Number(lookup_id)))?;
+    time_step("point lookup", found.rows().len(), start);
+
+    let start = Instant::now();
+    let filtered = table.select_rows(&"score".to_owned(), FilterCondition::GreaterThan(FilterConditionValue::Number(600.0)))?;
+    time_step("range filter", filtered.rows().len(), start);
+
+    let start = Instant::now();
+    table.sort_rows(SortCondition::NumericAscending, "score".to_owned())?;
+    time_step("sort", n_rows, start);
+
+    // the first tenth of the rows have a match in the other table.
+    // the join result needs a primary key of its own, which comes from "key"
+    let join_rows = (n_rows / 10).max(1);
+    let other = Table::generate(join_table_name.to_owned(), vec![
+        (Column::new("key".to_owned(), DataType::Number, true), ValueGenerator::Sequence(1.0)),
+        (Column::new("id".to_owned(), DataType::Number, false), ValueGenerator::Sequence(1.0)),
+        (Column::new("active".to_owned(), DataType::Boolean, false), ValueGenerator::Boolean(0.5)),
+    ], join_rows, BENCHMARK_SEED)?;
+    let start = Instant::now();
+    let joined = table.inner_join(&other, "id".to_owned())?;
+    time_step("join", joined.rows().len(), start);
+    // the join result indexes its primary key like any other table
+    for pk in joined.primary_keys() {
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(&joined.name, pk.get_name())));
+    }
+
+    let start = Instant::now();
+    table.export_to_csv(TEMP_PATH, ",")?;
+    time_step("csv export", n_rows, start);
+    let _ = fs::remove_file(format!("{}/{}", TEMP_PATH, table.file_name_for_export("csv")));
+
+    Ok(report)
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::structures::db_err::DBError;
+
+use super::{spill::Row, table::Table};
+
+
+/// the rows a statement changed, as they were before it ran, so the statement can be undone without keeping
+/// a copy of the whole table. Positions are the positions of the rows before the statement.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RowChanges {
+    /// rows which were changed in place, i.e. by an UPDATE or a soft delete
+    pub changed: Vec<(usize, Row)>,
+    /// rows which were removed from the table
+    pub removed: Vec<(usize, Row)>,
+    /// the number of rows added to the end of the table
+    pub added: usize,
+}
+
+
+impl RowChanges {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty() && self.added == 0
+    }
+}
+
+
+/// the rows remembered since `Table::track_changes()` was called
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChangeTracker {
+    /// the number of rows in the table when tracking started. Rows past it were added since, so they aren't remembered
+    rows_before: usize,
+    changed: BTreeMap<usize, Row>,
+    removed: BTreeMap<usize, Row>,
+}
+
+
+impl Table {
+
+    /// starts remembering the rows which are changed or removed, so they can be put back with `Table::revert_changes()`.
+    /// Only the rows which are touched are copied, the first time they are touched
+    pub fn track_changes(&mut self) {
+        self.change_tracker = Some(ChangeTracker { rows_before: self.rows.len(), ..Default::default() });
+    }
+
+
+    /// stops tracking changes, returning every row changed, removed or added since `Table::track_changes()`
+    pub fn take_changes(&mut self) -> RowChanges {
+        let tracker = match self.change_tracker.take() {
+            Some(tracker) => tracker,
+            None => return RowChanges::default(),
+        };
+        RowChanges {
+            added: (self.rows.len() + tracker.removed.len()).saturating_sub(tracker.rows_before),
+            changed: tracker.changed.into_iter().collect(),
+            removed: tracker.removed.into_iter().collect(),
+        }
+    }
+
+
+    /// remembers the row at `position` before it is changed in place
+    pub(super) fn remember_changed_row(&mut self, position: usize) {
+        let Some(tracker) = self.change_tracker.as_mut() else { return };
+        if position >= tracker.rows_before || tracker.changed.contains_key(&position) { return }
+        tracker.changed.insert(position, self.rows[position].clone());
+    }
+
+
+    /// remembers the rows at `positions` before they are removed. A removed row which was changed earlier
+    /// is remembered as it was before that change
+    pub(super) fn remember_removed_rows(&mut self, positions: &[usize]) {
+        let Some(tracker) = self.change_tracker.as_mut() else { return };
+        for &position in positions {
+            if position >= tracker.rows_before { continue }
+            let row = tracker.changed.remove(&position).unwrap_or_else(|| self.rows[position].clone());
+            tracker.removed.insert(position, row);
+        }
+    }
+
+
+    /// puts back the rows from `changes`: added rows are dropped, removed rows are put back where they were,
+    /// and changed rows get their old values. The indexes aren't touched, see `Table::restore()`
+    pub fn revert_changes(&mut self, changes: RowChanges) -> Result<(), DBError> {
+        let rows_after = self.rows.len();
+        if changes.added > rows_after {
+            return Err(DBError::RowNotFound(rows_after));
+        }
+        self.rows.truncate(rows_after - changes.added);
+
+        // in order, so every row goes back in front of the rows which were after it
+        for (position, row) in changes.removed {
+            if position > self.rows.len() { return Err(DBError::RowNotFound(position)) }
+            self.rows.insert(position, row);
+        }
+        for (position, row) in changes.changed {
+            let old_row = self.rows.get_mut(position).ok_or(DBError::RowNotFound(position))?;
+            *old_row = row;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::structures::{column::{Column, DataType, FieldValue}, conflict::ConflictPolicy, filter::{FilterCondition, FilterConditionValue, FilterExpr}, relation::table::Table};
+
+    fn numbers_table(numbers: &[f64]) -> Table {
+        let mut table = Table::new("changes test".to_owned(), vec![Column::new("n".to_owned(), DataType::Number, false)], true);
+        for n in numbers {
+            table.insert_row(&HashMap::from([("n".to_owned(), FieldValue::Number(*n))])).unwrap();
+        }
+        table
+    }
+
+    fn n_is(n: f64) -> FilterExpr {
+        FilterExpr::leaf("n", FilterCondition::Equal(FilterConditionValue::Number(n)))
+    }
+
+    #[test]
+    fn only_touched_rows_are_remembered() {
+        let mut table = numbers_table(&[1.0, 2.0, 3.0, 2.0]);
+        table.track_changes();
+        table.edit_rows_where("n", &n_is(2.0), FieldValue::Number(7.0)).unwrap();
+
+        let changes = table.take_changes();
+        assert_eq!(changes.changed.iter().map(|(p, _)| *p).collect::<Vec<usize>>(), vec![1, 3]);
+        assert_eq!(changes.changed[0].1["n"], FieldValue::Number(2.0));
+        assert!(changes.removed.is_empty());
+        assert_eq!(changes.added, 0);
+    }
+
+    #[test]
+    fn reverting_puts_back_changed_removed_and_added_rows() {
+        let original = numbers_table(&[1.0, 2.0, 3.0, 2.0, 4.0]);
+
+        let mut table = original.clone();
+        table.track_changes();
+        table.edit_rows_where("n", &n_is(3.0), FieldValue::Number(9.0)).unwrap();
+        let changes = table.take_changes();
+        table.revert_changes(changes).unwrap();
+        assert_eq!(table.rows, original.rows);
+
+        table.track_changes();
+        table.delete_rows_where(&n_is(2.0)).unwrap();
+        assert_eq!(table.rows.len(), 3);
+        let changes = table.take_changes();
+        table.revert_changes(changes).unwrap();
+        assert_eq!(table.rows, original.rows);
+
+        table.track_changes();
+        table.insert_row_on_conflict(&HashMap::from([("n".to_owned(), FieldValue::Number(5.0))]), &ConflictPolicy::Error).unwrap();
+        let changes = table.take_changes();
+        assert_eq!(changes.added, 1);
+        table.revert_changes(changes).unwrap();
+        assert_eq!(table.rows, original.rows);
+    }
+
+    #[test]
+    fn nothing_is_remembered_without_tracking() {
+        let mut table = numbers_table(&[1.0, 2.0]);
+        table.delete_rows_where(&n_is(1.0)).unwrap();
+        assert!(table.take_changes().is_empty());
+
+        table.track_changes();
+        table.delete_rows_where(&n_is(8.0)).unwrap();
+        assert!(table.take_changes().is_empty());
+    }
+}
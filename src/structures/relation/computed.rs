@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use crate::structures::{column::{validate_column_name, Column, DataType, FieldValue}, db_err::DBError, expression::{ComputeMode, ComputedColumn, Expression}};
+
+use super::table::Table;
+
+
+impl Table {
+
+    pub fn computed_columns(&self) -> &Vec<ComputedColumn> { &self.computed_columns }
+
+    pub fn is_computed_column(&self, column_name: &str) -> bool {
+        self.computed_columns.iter().any(|c| c.name == column_name)
+    }
+
+
+    /// adds a number column whose value is worked out from other number columns in the row,
+    /// i.e. `table.add_computed_column("Total".to_string(), "price * qty", ComputeMode::Stored)`.
+    ///
+    /// the value is kept up to date whenever a row is inserted or edited, and can't be changed directly.
+    pub fn add_computed_column(&mut self, column_name: String, expression: &str, mode: ComputeMode) -> Result<(), DBError> {
+
+        validate_column_name(&column_name)?;
+        if self.is_valid_column(&column_name) {
+            return Err(DBError::InvalidColumnName(column_name, "a column with that name already exists".to_owned()));
+        }
+
+        let expression = Expression::parse_str(expression)?;
+        for referenced_column in expression.columns() {
+            let column = self.column(referenced_column.clone()).ok_or(DBError::InvalidColumn(referenced_column))?;
+            if column.get_data_type() != &DataType::Number {
+                return Err(DBError::MisMatchDataType(DataType::Number, column.get_data_type().clone()));
+            }
+        }
+
+        self.columns.push( Column::new(column_name.clone(), DataType::Number, false) );
+        self.computed_columns.push( ComputedColumn { name: column_name, expression, mode } );
+        self.refresh_computed_columns()
+    }
+
+
+    /// works out every computed value for a row, overwriting anything already in those columns
+    pub(super) fn fill_computed_values(&self, row: &mut HashMap<String, FieldValue>) -> Result<(), DBError> {
+        for computed_column in &self.computed_columns {
+            let value = computed_column.expression.evaluate(&|c: &str| row.get(c))?;
+            row.insert(computed_column.name.clone(), value);
+        }
+        Ok(())
+    }
+
+
+    /// recomputes the computed columns of every row in the table
+    pub(super) fn refresh_computed_columns(&mut self) -> Result<(), DBError> {
+        if self.computed_columns.is_empty() { return Ok(()) }
+
+        let mut rows = std::mem::take(&mut self.rows);
+        let result = rows.iter_mut().try_for_each(|row| self.fill_computed_values(row));
+        self.rows = rows;
+        result
+    }
+
+
+    /// a copy of the table without the values of virtual columns, which is what gets saved to disk
+    pub(super) fn without_virtual_values(&self) -> Option<Table> {
+        let virtual_columns: Vec<&String> = self.computed_columns
+            .iter()
+            .filter(|c| c.mode == ComputeMode::Virtual)
+            .map(|c| &c.name)
+            .collect();
+        if virtual_columns.is_empty() { return None }
+
+        let mut table = self.clone();
+        for row in &mut table.rows {
+            for column in &virtual_columns { row.remove(*column); }
+        }
+        Some(table)
+    }
+}
@@ -1,15 +1,20 @@
 use std::collections::{BTreeMap, HashMap};
 
-use crate::{config::INDEX_PATH, structures::{column::{Column, DataType, FieldValue}, db_err::DBError, filter::FilterCondition}};
+use crate::{config::INDEX_PATH, structures::{column::{Column, DataType, FieldValue}, conflict::{ConflictPolicy, InsertOutcome}, db_err::{ConstraintContext, DBError}, filter::{FilterCondition, FilterExpr}, progress::{ProgressCallback, ProgressReporter}}};
 
-use super::{io::{load_index, save_index}, table::Table};
+use super::{io::{load_index, save_index}, table::Table, versioning::bump_row_version};
 
 
 impl Table {
      // TODO: implement Aggregate functions
-    pub fn new(name: String, columns: Vec<Column>, disable_primary_keys: bool) -> Self {
+    pub fn new(name: String, mut columns: Vec<Column>, disable_primary_keys: bool) -> Self {
         // get the primary keys
         let mut primary_keys: Vec<Column> = Vec::new();
+
+        // tables without keys (i.e. the results of filters) don't keep the unique constraints of the columns they copied
+        if disable_primary_keys {
+            for c in &mut columns { c.set_unique(false); }
+        }
         
         if !disable_primary_keys {
 
@@ -33,11 +38,11 @@ impl Table {
             columns.push(id_column);
         }
 
-        let instance = Self { name, columns, primary_keys: primary_keys.clone(), rows: Vec::new() };
+        let instance = Self { name, generation: 0, columns, primary_keys: primary_keys.clone(), rows: Vec::new(), soft_delete: false, row_versions: false, computed_columns: Vec::new(), validation_rules: Vec::new(), ttl: None, lineage: None, temporary_since: None, change_tracker: None };
 
-        // generate indexes on all primary keys
-        for pk in &primary_keys {
-            let _ = instance.index_column(pk.get_name().to_owned());
+        // generate indexes on all primary keys and unique columns
+        for column in instance.constrained_columns() {
+            let _ = instance.index_column(column.get_name().to_owned());
         }
 
         instance
@@ -46,16 +51,38 @@ impl Table {
     
     /// makes an index on `column_name` and automatically saves it to the config directory
     pub fn index_column(&self, column_name: String) -> Result<(), DBError> {
+        self.build_index(column_name, None)
+    }
+
+
+    /// same as `Table::index_column()`, but `on_progress` is called with the number of rows indexed so far
+    /// and the total number of rows in the table.
+    pub fn index_column_with_progress(&self, column_name: String, on_progress: ProgressCallback) -> Result<(), DBError> {
+        self.build_index(column_name, Some(on_progress))
+    }
+
+
+    fn build_index(&self, column_name: String, on_progress: Option<ProgressCallback>) -> Result<(), DBError> {
+        let index = self.index_in_memory(&column_name, on_progress)?;
+        save_index(INDEX_PATH, &self.name, &column_name, index);
+
+        Ok(())
+    }
+
+
+    /// the index `Table::index_column()` would save for the column, built from the rows in memory without saving it
+    pub(super) fn index_in_memory(&self, column_name: &str, on_progress: Option<ProgressCallback>) -> Result<BTreeMap<FieldValue, Vec<usize>>, DBError> {
         
-        if self.column(column_name.clone()).is_none() { return Err(DBError::InvalidColumn(column_name.clone())) }
+        if self.column(column_name.to_string()).is_none() { return Err(DBError::InvalidColumn(column_name.to_string())) }
 
 
         let mut index: BTreeMap<FieldValue, Vec<usize>> = BTreeMap::new();
+        let mut progress = ProgressReporter::new(on_progress, self.rows.len());
 
         // Iterate over each row and build the index
         for (row_index, row) in self.rows().iter().enumerate() {
             // Get the value of the specified column in the current row
-            if let Some(index_key) = row.get(&column_name) {
+            if let Some(index_key) = row.get(column_name) {
                 // Check if the key is already in the index
                 // If it exists, push the row index to the vector, 
                 // otherwise insert a new vector with the row index
@@ -63,12 +90,10 @@ impl Table {
                     .and_modify(|v| v.push(row_index)) // Add to existing vector if key exists
                     .or_insert_with(|| vec![row_index]); // Insert new vector with the current row index
             }
-
+            progress.report(row_index + 1);
         }
 
-        save_index(INDEX_PATH, &self.name, &column_name, index);
-
-        Ok(())
+        Ok(index)
     }
 
 
@@ -89,9 +114,8 @@ impl Table {
             let new_row_field_value_at_pk = row_data.get(pk_name).unwrap();
             let pk_index = load_index( INDEX_PATH, &self.name, pk_name ).unwrap();
 
-            if pk_index.contains_key( new_row_field_value_at_pk ) {
-                println!("already have {}", new_row_field_value_at_pk);
-                return Err(DBError::DuplicatePrimaryKey(pk_name.to_string()))
+            if let Some(existing_rows) = pk_index.get( new_row_field_value_at_pk ) {
+                return Err(DBError::DuplicatePrimaryKey(self.constraint_context(pk_name, new_row_field_value_at_pk, existing_rows.first().copied())))
             }
         }
 
@@ -110,19 +134,34 @@ impl Table {
 
             // validate datatypes match
             if !given_field_value.eq(&FieldValue::Null) && !col.get_data_type().eq(&given_field_value.data_type()) {
-                return Err(DBError::MisMatchDataType(col.get_data_type().clone(), given_field_value.data_type()));
+                return Err(DBError::WrongValueType(self.constraint_context(col_name, given_field_value, None), col.get_data_type().clone()));
             }
         }
 
+        // a column left out of the row is null too
+        for col in self.writable_columns() {
+            let value = row_data.get(col.get_name()).unwrap_or(&FieldValue::Null);
+            self.check_null_allowed(col, value, None)?;
+        }
+
+        for col in self.unique_columns() {
+            let value = row_data.get(col.get_name()).unwrap_or(&FieldValue::Null);
+            self.check_unique_value(col.get_name(), value, None)?;
+        }
+
         // if there aren't any missing primary keys, push the hashmap and return unit
+        let mut row_data = row_data.clone();
+        if self.soft_delete { self.stamp_inserted_row(&mut row_data); }
+        if self.row_versions { self.stamp_row_version(&mut row_data); }
+        self.fill_computed_values(&mut row_data)?;
         self.rows.push( row_data.clone() );
 
-        for indexed_column in self.primary_keys() {
+        for indexed_column in self.constrained_columns() {
             let column_name = indexed_column.get_name();
 
             self.update_index_insertion( 
                 &column_name, 
-                row_data.get(column_name).unwrap(), 
+                row_data.get(column_name).unwrap_or(&FieldValue::Null), 
                 self.rows.len() - 1 
             )?;
         }
@@ -133,11 +172,147 @@ impl Table {
 
 
 
+    /// inserts a new row into the database, using `policy` to decide what happens if a row with the
+    /// same primary key already exists.
+    /// 
+    /// conflicts are found using the indexes on the primary keys.
+    pub fn insert_row_on_conflict(&mut self, row_data: &HashMap<String, FieldValue>, policy: &ConflictPolicy) -> Result<InsertOutcome, DBError> {
+
+        // find the first existing row which shares a primary key with the new row
+        let mut conflicting_row: Option<usize> = None;
+        for pk in self.primary_keys() {
+            let pk_name = pk.get_name();
+            let new_value = match row_data.get(pk_name) {
+                Some(v) => v,
+                None => continue,
+            };
+            let pk_index = self.index_on(pk_name)?;
+            if let Some(row_indices) = pk_index.get(new_value) {
+                conflicting_row = row_indices.first().copied();
+                break;
+            }
+        }
+
+        let row_index = match conflicting_row {
+            Some(idx) => idx,
+            None => {
+                self.insert_row(row_data)?;
+                return Ok(InsertOutcome::Inserted);
+            }
+        };
+
+        match policy {
+            ConflictPolicy::Error => {
+                // let insert_row figure out which key collided
+                self.insert_row(row_data)?;
+                Ok(InsertOutcome::Inserted)
+            },
+            ConflictPolicy::Ignore => Ok(InsertOutcome::Ignored),
+            ConflictPolicy::Replace => {
+                self.overwrite_row(row_index, row_data)?;
+                Ok(InsertOutcome::Updated)
+            },
+            ConflictPolicy::Update(columns) => {
+                let mut new_values: HashMap<String, FieldValue> = HashMap::new();
+                for col in columns {
+                    let value = row_data.get(col).ok_or(DBError::InvalidColumn(col.to_string()))?;
+                    new_values.insert(col.to_string(), value.clone());
+                }
+                self.overwrite_row(row_index, &new_values)?;
+                Ok(InsertOutcome::Updated)
+            },
+        }
+    }
+
+
+    /// overwrites some (or all) of the values in the row at `row_index`, keeping the primary key and unique indexes up to date.
+    pub(super) fn overwrite_row(&mut self, row_index: usize, new_values: &HashMap<String, FieldValue>) -> Result<(), DBError> {
+
+        if row_index >= self.rows.len() {
+            return Err(DBError::RowNotFound(row_index));
+        }
+
+        // make sure the new values can actually be written to the row
+        for (col_name, new_value) in new_values {
+            let col = self.column(col_name.to_string()).ok_or(DBError::InvalidColumn(col_name.to_string()))?;
+
+            if !new_value.eq(&FieldValue::Null) && !col.get_data_type().eq(&new_value.data_type()) {
+                return Err(DBError::WrongValueType(self.constraint_context(col_name, new_value, Some(row_index)), col.get_data_type().clone()));
+            }
+            self.check_null_allowed(&col, new_value, Some(row_index))?;
+        }
+
+        // every constraint is checked before anything is written, so a rejected change leaves the row and its indexes alone
+        let mut index_changes: Vec<(&str, &FieldValue, &FieldValue)> = Vec::new();
+
+        for pk in self.primary_keys() {
+            let pk_name = pk.get_name();
+            let new_value = match new_values.get(pk_name) {
+                Some(v) => v,
+                None => continue,
+            };
+            let old_value = self.rows[row_index].get(pk_name).unwrap();
+            if old_value.eq(new_value) { continue; }
+
+            let index = self.index_on(pk_name)?;
+            if let Some(existing_rows) = index.get(new_value) {
+                return Err(DBError::DuplicatePrimaryKey(self.constraint_context(pk_name, new_value, existing_rows.first().copied())));
+            }
+            index_changes.push( (pk_name, old_value, new_value) );
+        }
+
+        // the same for unique columns, which can also hold nulls
+        for col in self.unique_columns() {
+            let col_name = col.get_name();
+            let new_value = match new_values.get(col_name) {
+                Some(v) => v,
+                None => continue,
+            };
+            let old_value = self.rows[row_index].get(col_name).unwrap_or(&FieldValue::Null);
+            if old_value.eq(new_value) { continue; }
+
+            self.check_unique_value(col_name, new_value, Some(row_index))?;
+            index_changes.push( (col_name, old_value, new_value) );
+        }
+
+        let mut row = self.rows[row_index].clone();
+        for (col_name, new_value) in new_values {
+            row.insert( col_name.to_string(), new_value.clone() );
+        }
+        self.fill_computed_values(&mut row)?;
+        if self.row_versions { bump_row_version(&mut row); }
+
+        // keep the primary key and unique indexes in sync with the changed values
+        for (col_name, old_value, new_value) in index_changes {
+            self.move_in_index(col_name, old_value, new_value, row_index)?;
+        }
+        self.remember_changed_row(row_index);
+        self.rows[row_index] = row;
+
+        Ok(())
+    }
+
+
+    /// fails with `DBError::NullNotAllowed` if `value` is null but `column` doesn't allow nulls
+    pub(super) fn check_null_allowed(&self, column: &Column, value: &FieldValue, row: Option<usize>) -> Result<(), DBError> {
+        if value.eq(&FieldValue::Null) && !column.allows_nulls() {
+            return Err(DBError::NullNotAllowed(self.constraint_context(column.get_name(), value, row)));
+        }
+        Ok(())
+    }
+
+
+    pub(super) fn constraint_context(&self, column_name: &str, value: &FieldValue, row: Option<usize>) -> ConstraintContext {
+        ConstraintContext { table: self.name.clone(), column: column_name.to_string(), value: value.clone(), row }
+    }
+
+
     fn update_index_insertion(&self, column_name: &str, fv_from_inserted_row: &FieldValue, row_index: usize) -> Result<(), DBError> {
 
         let mut index = self.index_on(column_name)?;
 
-        index.insert( fv_from_inserted_row.clone() , vec![row_index] );
+        // unique columns can have more than one null
+        index.entry( fv_from_inserted_row.clone() ).or_default().push(row_index);
 
         save_index( INDEX_PATH, &self.name, column_name, index );
         Ok(())
@@ -145,6 +320,8 @@ impl Table {
     } 
 
 
+    /// changes `column_to_edit` to `new_value` in every row where `filter_column_name` matches `search_criteria`.
+    /// Returns the number of rows changed, see `Table::edit_rows_where()`.
     pub fn edit_rows(
         &mut self, 
         filter_column_name: String,
@@ -152,120 +329,56 @@ impl Table {
         search_criteria: FilterCondition, 
         new_value: FieldValue
     ) -> Result<u32, DBError>{
-    
-        let filter_result: Result<Table, DBError> = self.select_rows(&filter_column_name, search_criteria);
-
-        match filter_result { Err(e) => return Err(e), Ok(_) => () };
-        let rows_to_edit = filter_result.unwrap();
-        let rows_to_edit = rows_to_edit.rows();
-
-        let mut updated_rows: Vec<HashMap<String, FieldValue>> = Vec::new();
-
-        /* 
-        in order to update the indexes for this table, we need the following information:
-        1. all the indexes available for this table
-        2. all the field values for all rows being updated, for all the columns
-        3. the field value which is replacing the outdated values
-        
-        heres pseudocode of my algorithm:
-        for all of the indexes (which iterates over a vector of referenced columns):
-            load the index into memory
-            for all of the rows being updated:
-                load the field value from that row and column (grabbed from the outer for loop)
-                delete that field value from the index, which will return the row indices being stored there
-                
-                if the field value doesn't already exist in the index (i.e. this is after the first iteration): 
-                    insert the new field value with the row index from the previously deleted field value into the index
-                otherwise:
-                    get the vector of indices being stored at that fieldvalue in the index
-                    concatenate the recently retrieved indices to that vector
-                    override the existing index value with the newly concatenated vector of row indices
-            save the index
-        */ 
-
-        for indexed_column in self.primary_keys() {
-            let indexed_column_name = indexed_column.get_name();
-            let mut index = self.index_on(indexed_column_name)?;
-
-            for row in rows_to_edit {
-                let old_field_value = row.get(indexed_column_name).unwrap();
-                index.remove( old_field_value );
-
-                
-                let row_index = self.rows().iter().position(|r| r == row).unwrap();
-                if index.contains_key( &new_value ) {
-                    let mut existing_row_indices = index.remove( &new_value ).unwrap();
-                    existing_row_indices.push(row_index);
-                    index.insert( new_value.clone() , existing_row_indices );
-
-                } else {
-                    index.insert(new_value.clone(), vec![row_index] );
-                }
-            }
-            save_index(INDEX_PATH, &self.name, indexed_column_name, index);
-        }
+        self.edit_rows_where(&column_to_edit, &FilterExpr::leaf(&filter_column_name, search_criteria), new_value)
+    }
 
 
+    /// deletes every row where `column_name` matches `search_criteria`.
+    /// 
+    /// returns a u32 of the number of rows deleted if the function does not fail.
+    /// 
+    /// if soft deletes are enabled, the rows are only marked as deleted. See `Table::enable_soft_delete()`
+    pub fn delete_rows(&mut self, column_name: String, search_criteria: FilterCondition ) -> Result<u32, DBError> {
 
-        // I honestly have no idea how this works but whatever, have fun debugging this later dipshit
-        for mut row in self.rows().clone() {
-            if rows_to_edit.contains( &row ) {
-                *row.get_mut(&column_to_edit).unwrap() = new_value.clone();
-                updated_rows.push( row );
-            } else { updated_rows.push(row);}
+        if self.soft_delete {
+            return self.soft_delete_rows(&column_name, search_criteria);
         }
 
-        let number_of_changed_rows = rows_to_edit.len() as u32;
+        let rows_to_delete = self.rows_matching(&FilterExpr::leaf(&column_name, search_criteria))?;
+        if rows_to_delete.is_empty() { return Ok(0) }
 
-        self.rows = updated_rows;
-
-        Ok(number_of_changed_rows)
+        self.remove_rows_at(&rows_to_delete)?;
+        Ok( rows_to_delete.len() as u32 )
     }
 
 
-    /// uses the `Table::filter_rows()` function to determine which rows are to be deleted.
-    /// 
-    /// returns a u32 of the number of rows deleted if the function does not fail.
-    pub fn delete_rows(&mut self, column_name: String, search_criteria: FilterCondition ) -> Result<u32, DBError> {
+    /// permanently removes the rows at `positions`, then rebuilds every saved index on the table.
+    ///
+    /// the rows after each removed row move up, so the indexes are rebuilt rather than edited. This covers the
+    /// primary keys and unique columns, and any other column indexed with `Table::index_column()`.
+    pub(super) fn remove_rows_at(&mut self, positions: &[usize]) -> Result<(), DBError> {
+        let mut positions = positions.to_vec();
+        positions.sort_unstable();
+        self.remember_removed_rows(&positions);
 
-        let temp_index = load_index(INDEX_PATH, &self.name, "A" ).unwrap();
-        println!(" wayy before deleting data in index on {}: ", "A");
-        for (k, v) in &temp_index {
-            println!("fv: {} | row idx: {:?}", k, v);
-        }
-        println!("=== END OF INDEX ===\n\n");
-
-        let filtered_table = self.select_rows(&column_name, search_criteria)?;
-        let rows_to_delete = filtered_table.rows();
-        
-        let kept_rows: Vec<HashMap<String, FieldValue>> = self
-            .rows()
+        let indexed_columns: Vec<String> = self
+            .columns()
             .iter()
-            .filter(|r| !rows_to_delete.contains(*r) )
-            .cloned()
+            .map(|c| c.get_name().to_string())
+            .filter(|c| self.index_available(c, INDEX_PATH) || self.constrained_columns().iter().any(|k| k.get_name() == c))
             .collect();
-        
-        
-        // iterate through the indexed columns, deleting the values from any rows that have been removed
-        for indexed_column in self.primary_keys() {
-            let mut index = load_index(INDEX_PATH, &self.name, indexed_column.get_name() ).unwrap();
-            
-            for row in rows_to_delete {
-
-                let column_name = indexed_column.get_name();
-    
-                    index.remove(row.get(column_name).unwrap());
-            }
-            
-           save_index( INDEX_PATH, &self.name, indexed_column.get_name(), index );
-        }
-
-        // override old row data
-        self.rows = kept_rows;
 
+        let mut row_index = 0;
+        self.rows.retain(|_| {
+            let keep = positions.binary_search(&row_index).is_err();
+            row_index += 1;
+            keep
+        });
 
-        let number_of_deleted_rows = rows_to_delete.len() as u32; 
-        Ok( number_of_deleted_rows )
+        for column_name in indexed_columns {
+            self.index_column(column_name)?;
+        }
+        Ok(())
     }
     
     
@@ -287,4 +400,30 @@ impl Table {
 
         Ok(())
     }
+
+
+    /// moves the columns in `order` to the front of the table, in the given order. Any columns which aren't
+    /// listed keep their current order after them. The new order is kept when the table is saved.
+    ///
+    /// to change the order columns are shown in without changing the table, see `Table::set_display_order()`.
+    pub fn reorder_columns(&mut self, order: &[String]) -> Result<(), DBError> {
+        let mut reordered_columns: Vec<Column> = Vec::new();
+
+        for column_name in order {
+            let column = self.column(column_name.to_string()).ok_or(DBError::InvalidColumn(column_name.to_string()))?;
+            if reordered_columns.iter().any(|c| c.get_name() == column_name) {
+                return Err(DBError::InvalidColumnName(column_name.to_string(), "it is listed more than once".to_string()));
+            }
+            reordered_columns.push(column);
+        }
+
+        for column in &self.columns {
+            if !order.contains(&column.get_name().to_string()) {
+                reordered_columns.push(column.clone());
+            }
+        }
+
+        self.columns = reordered_columns;
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+
+use crate::structures::{cancel::check_cancelled, column::FieldValue, db_err::DBError, dedupe::KeepDuplicate};
+
+use super::{soft_delete::{DELETED_AT_COLUMN, INSERTED_AT_COLUMN}, table::Table, versioning::ROW_VERSION_COLUMN};
+
+
+impl Table {
+
+    /// removes rows which have the same values in all of `columns` as another row, keeping one row from each
+    /// set of duplicates. Returns how many rows were removed.
+    ///
+    /// if `columns` is empty, every column except the primary keys and the soft delete / row version columns is compared.
+    /// if soft deletes are enabled, the duplicates are only marked as deleted.
+    pub fn dedupe(&mut self, columns: &[String], keep: KeepDuplicate) -> Result<u32, DBError> {
+
+        let key_columns: Vec<String> = if columns.is_empty() {
+            let bookkeeping_columns = [INSERTED_AT_COLUMN, DELETED_AT_COLUMN, ROW_VERSION_COLUMN];
+            self.columns
+                .iter()
+                .filter(|c| !c.is_primary_key() && !bookkeeping_columns.contains(&c.get_name()))
+                .map(|c| c.get_name().to_string())
+                .collect()
+        } else {
+            for c in columns {
+                if !self.is_valid_column(c) { return Err(DBError::InvalidColumn(c.to_string())) }
+            }
+            columns.to_vec()
+        };
+
+        let row_indices: Vec<usize> = match keep {
+            KeepDuplicate::First => (0..self.rows.len()).collect(),
+            KeepDuplicate::Last => (0..self.rows.len()).rev().collect(),
+        };
+
+        // FieldValue can't be hashed, so rows are keyed by the string form of their values
+        let mut seen_keys: HashSet<String> = HashSet::new();
+        let mut duplicate_rows: HashSet<usize> = HashSet::new();
+        let now = Utc::now();
+
+        for row_index in row_indices {
+            check_cancelled()?;
+            let row = &self.rows[row_index];
+            if !self.is_live(row, now) { continue }
+
+            let key: Vec<String> = key_columns
+                .iter()
+                .map(|c| row.get(c).unwrap_or(&FieldValue::Null).to_key_string())
+                .collect();
+
+            if !seen_keys.insert(key.join("\u{1f}")) {
+                duplicate_rows.insert(row_index);
+            }
+        }
+
+        if duplicate_rows.is_empty() { return Ok(0) }
+
+        if self.soft_delete {
+            let now = FieldValue::Date(Utc::now());
+            for row_index in &duplicate_rows {
+                self.rows[*row_index].insert( DELETED_AT_COLUMN.to_string(), now.clone() );
+            }
+        } else {
+            // row positions change, so the indexes are rebuilt as well
+            self.remove_rows_at(&duplicate_rows.iter().copied().collect::<Vec<usize>>())?;
+        }
+
+        Ok(duplicate_rows.len() as u32)
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::structures::{cancel::{check_cancelled, check_row_limit}, column::{Column, DataType, FieldValue}, db_err::DBError};
+
+use super::{spill::Row, table::Table};
+
+
+/// the column added by `TableDiff::to_table()` which says how each row changed
+pub const CHANGE_COLUMN: &str = "Change";
+
+
+/// a row which is in both tables, but with different values
+#[derive(Debug, Clone)]
+pub struct RowChange {
+    pub before: Row,
+    pub after: Row,
+    /// the columns whose values are different
+    pub changed_columns: Vec<String>,
+}
+
+
+/// the differences between two versions of a table, made by `Table::diff()`
+#[derive(Debug, Clone)]
+pub struct TableDiff {
+    pub key_columns: Vec<String>,
+    /// every column in both tables, which the comparison is done on
+    pub columns: Vec<Column>,
+    pub added: Vec<Row>,
+    pub removed: Vec<Row>,
+    pub changed: Vec<RowChange>,
+    /// the number of rows which are the same in both tables
+    pub unchanged: usize,
+}
+
+
+impl Table {
+
+    /// compares this table (the old version) against `other` (the new version), matching rows by `key_columns`.
+    ///
+    /// rows only in `other` are added, rows only in this table are removed, and rows in both with
+    /// different values are changed. Only the columns both tables share are compared.
+    pub fn diff(&self, other: &Table, key_columns: &[String]) -> Result<TableDiff, DBError> {
+
+        if key_columns.is_empty() { return Err(DBError::PrimaryKeyRequired) }
+        for key in key_columns {
+            if !self.is_valid_column(key) || !other.is_valid_column(key) {
+                return Err(DBError::InvalidColumn(key.to_string()));
+            }
+        }
+
+        let shared_columns: Vec<Column> = self.columns
+            .iter()
+            .filter(|c| other.is_valid_column(&c.get_name().to_string()))
+            .cloned()
+            .collect();
+
+        let key_of = |row: &Row| -> String {
+            key_columns
+                .iter()
+                .map(|k| row.get(k).unwrap_or(&FieldValue::Null).to_key_string())
+                .collect::<Vec<String>>()
+                .join("\u{1f}")
+        };
+
+        let now = Utc::now();
+        let mut old_rows: HashMap<String, &Row> = HashMap::new();
+        for row in self.rows.iter().filter(|r| self.is_live(r, now)) {
+            old_rows.entry(key_of(row)).or_insert(row);
+        }
+
+        let mut diff = TableDiff {
+            key_columns: key_columns.to_vec(),
+            columns: shared_columns.clone(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            unchanged: 0,
+        };
+
+        for new_row in other.rows.iter().filter(|r| other.is_live(r, now)) {
+            check_cancelled()?;
+
+            let old_row = match old_rows.remove(&key_of(new_row)) {
+                Some(r) => r,
+                None => {
+                    diff.added.push(new_row.clone());
+                    check_row_limit(diff.added.len() + diff.changed.len())?;
+                    continue;
+                }
+            };
+
+            let changed_columns: Vec<String> = shared_columns
+                .iter()
+                .map(|c| c.get_name())
+                .filter(|c| {
+                    let old_value = old_row.get(*c).unwrap_or(&FieldValue::Null);
+                    let new_value = new_row.get(*c).unwrap_or(&FieldValue::Null);
+                    old_value.to_key_string() != new_value.to_key_string()
+                })
+                .map(|c| c.to_string())
+                .collect();
+
+            if changed_columns.is_empty() {
+                diff.unchanged += 1;
+            } else {
+                diff.changed.push(RowChange { before: old_row.clone(), after: new_row.clone(), changed_columns });
+                check_row_limit(diff.added.len() + diff.changed.len())?;
+            }
+        }
+
+        // anything left over wasn't in the new table. Walking the old rows again keeps them in table order
+        diff.removed = self.rows
+            .iter()
+            .filter(|r| old_rows.get(&key_of(r)).is_some_and(|old| std::ptr::eq(*old, *r)))
+            .cloned()
+            .collect();
+        check_row_limit(diff.added.len() + diff.changed.len() + diff.removed.len())?;
+
+        Ok(diff)
+    }
+}
+
+
+impl TableDiff {
+
+    /// i.e. "3 added, 1 removed, 2 changed, 40 unchanged"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} changed, {} unchanged", 
+            self.added.len(), self.removed.len(), self.changed.len(), self.unchanged
+        )
+    }
+
+
+    /// puts every difference into a table, with a "Change" column saying if the row was added, removed, or changed.
+    /// changed rows show their new values.
+    ///
+    /// fails if the compared tables share a column named `CHANGE_COLUMN`, since its values would be overwritten.
+    pub fn to_table(&self) -> Result<Table, DBError> {
+        if self.columns.iter().any(|c| c.get_name() == CHANGE_COLUMN) {
+            return Err(DBError::InvalidColumnName(
+                CHANGE_COLUMN.to_string(),
+                "the compared tables already have this column, which the differences use to say how each row changed".to_string()
+            ));
+        }
+
+        let mut columns = vec![ Column::new(CHANGE_COLUMN.to_string(), DataType::String, false) ];
+        columns.extend(self.columns.iter().cloned());
+
+        let mut table = Table::new("differences".to_string(), columns, true);
+
+        let changes = self.added.iter().map(|r| ("added", r))
+            .chain(self.removed.iter().map(|r| ("removed", r)))
+            .chain(self.changed.iter().map(|c| ("changed", &c.after)));
+
+        for (change, row) in changes {
+            let mut diff_row: Row = self.columns
+                .iter()
+                .map(|c| (c.get_name().to_string(), row.get(c.get_name()).cloned().unwrap_or(FieldValue::Null)))
+                .collect();
+            diff_row.insert(CHANGE_COLUMN.to_string(), FieldValue::String(change.to_string()));
+            table.insert_row(&diff_row)?;
+        }
+        Ok(table)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::structures::{column::{Column, DataType, FieldValue}, db_err::DBError, relation::table::Table};
+
+    use super::CHANGE_COLUMN;
+
+    /// a table with a number `id` and a text column named `value_column`, holding a row per pair
+    fn table(value_column: &str, rows: &[(f64, &str)]) -> Table {
+        let columns = vec![
+            Column::new("id".to_owned(), DataType::Number, false),
+            Column::new(value_column.to_owned(), DataType::String, false),
+        ];
+        let mut table = Table::new("diff test".to_owned(), columns, true);
+        for (id, value) in rows {
+            table.insert_row(&HashMap::from([
+                ("id".to_owned(), FieldValue::Number(*id)),
+                (value_column.to_owned(), FieldValue::String(value.to_string())),
+            ])).unwrap();
+        }
+        table
+    }
+
+    #[test]
+    fn differences_are_listed_with_how_each_row_changed() {
+        let old = table("name", &[(1.0, "a"), (2.0, "b"), (3.0, "c")]);
+        let new = table("name", &[(1.0, "a"), (2.0, "B"), (4.0, "d")]);
+
+        let diff = old.diff(&new, &["id".to_owned()]).unwrap();
+        assert_eq!(diff.summary(), "1 added, 1 removed, 1 changed, 1 unchanged");
+
+        let differences = diff.to_table().unwrap();
+        let changes: Vec<(FieldValue, FieldValue)> = differences.rows()
+            .iter()
+            .map(|r| (r[CHANGE_COLUMN].clone(), r["id"].clone()))
+            .collect();
+        assert_eq!(changes, vec![
+            (FieldValue::String("added".to_owned()), FieldValue::Number(4.0)),
+            (FieldValue::String("removed".to_owned()), FieldValue::Number(3.0)),
+            (FieldValue::String("changed".to_owned()), FieldValue::Number(2.0)),
+        ]);
+    }
+
+    #[test]
+    fn a_shared_change_column_is_an_error() {
+        let old = table(CHANGE_COLUMN, &[(1.0, "a")]);
+        let new = table(CHANGE_COLUMN, &[(1.0, "b")]);
+
+        let diff = old.diff(&new, &["id".to_owned()]).unwrap();
+        assert!(matches!(diff.to_table(), Err(DBError::InvalidColumnName(name, _)) if name == CHANGE_COLUMN));
+    }
+}
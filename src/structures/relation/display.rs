@@ -1,34 +1,230 @@
+use std::{collections::HashMap, fs, sync::Mutex};
+
 use comfy_table::presets::ASCII_MARKDOWN;
 
+use crate::{config::RELATION_PATH, structures::{column::{Column, FieldValue}, db_err::DBError, display_settings::DisplaySettings}};
+
 use super::table::Table;
 
 
+/// the file in the relation directory which keeps the display order preferred for each table
+pub const DISPLAY_ORDER_FILE_NAME: &str = "display_orders.bin";
+
+
+/// the display orders last loaded or saved, so tables can be displayed without reading the file every time
+static LOADED_DISPLAY_ORDERS: Mutex<Option<HashMap<String, Vec<String>>>> = Mutex::new(None);
+
+
+fn display_order_file_path() -> String {
+    format!("{}/{}", RELATION_PATH, DISPLAY_ORDER_FILE_NAME)
+}
+
+
+/// the preferred display order of `table_name`, if one was set. The file is only read the first time
+fn load_display_order(table_name: &str) -> Option<Vec<String>> {
+    let mut loaded_orders = LOADED_DISPLAY_ORDERS.lock().unwrap_or_else(|e| e.into_inner());
+    loaded_orders.get_or_insert_with(read_display_orders).get(table_name).cloned()
+}
+
+
+/// the preferred display order of each table, keyed by table name
+fn load_display_orders() -> HashMap<String, Vec<String>> {
+    let mut loaded_orders = LOADED_DISPLAY_ORDERS.lock().unwrap_or_else(|e| e.into_inner());
+    loaded_orders.get_or_insert_with(read_display_orders).clone()
+}
+
+
+fn read_display_orders() -> HashMap<String, Vec<String>> {
+    fs::read(display_order_file_path())
+        .ok()
+        .and_then(|data| bincode::deserialize(&data).ok())
+        .unwrap_or_default()
+}
+
+
+fn save_display_orders(display_orders: &HashMap<String, Vec<String>>) -> Result<(), DBError> {
+    let file_path = display_order_file_path();
+    let encoded_data = bincode::serialize(display_orders).map_err(|_| DBError::DataBaseFileFailure(file_path.clone()))?;
+    fs::write(&file_path, encoded_data).map_err(|e| DBError::IOFailure(file_path, e.to_string()))?;
+    *LOADED_DISPLAY_ORDERS.lock().unwrap_or_else(|e| e.into_inner()) = Some(display_orders.clone());
+    Ok(())
+}
+
 
 impl Table {
-    pub fn to_ascii(&self) -> String {
 
-        let mut text_table = comfy_table::Table::new();
+    /// sets the order the table's columns are shown in by `Table::to_ascii()`, without changing the table itself.
+    /// The columns in `order` are shown first, and the rest are shown after them in their usual order.
+    ///
+    /// the preference is kept separately from the table, so it doesn't need to be saved again.
+    /// to change the order of the columns in the table, see `Table::reorder_columns()`.
+    pub fn set_display_order(&self, order: &[String]) -> Result<(), DBError> {
+        for (i, column_name) in order.iter().enumerate() {
+            if !self.is_valid_column(column_name) {
+                return Err(DBError::InvalidColumn(column_name.to_string()));
+            }
+            if order[..i].contains(column_name) {
+                return Err(DBError::InvalidColumnName(column_name.to_string(), "it is listed more than once".to_string()));
+            }
+        }
+
+        let mut display_orders = load_display_orders();
+        display_orders.insert(self.name.clone(), order.to_vec());
+        save_display_orders(&display_orders)
+    }
+
+
+    /// removes the table's display order preference, so columns are shown in the order they are in the table
+    pub fn clear_display_order(&self) -> Result<(), DBError> {
+        let mut display_orders = load_display_orders();
+        if display_orders.remove(&self.name).is_none() { return Ok(()) }
+        save_display_orders(&display_orders)
+    }
 
-        let mut header_row: Vec<comfy_table::Cell> = Vec::new();
-        for col in self.columns() {
-            let cell = comfy_table::Cell::new(format!("{}\n<{}>", col.get_name(), col.get_data_type() ))
-            .set_alignment(comfy_table::CellAlignment::Center);
-            header_row.push(cell);
 
+    /// the columns of the table in the order they are shown in, see `Table::set_display_order()`.
+    ///
+    /// columns which were removed since the preference was set are skipped, and new columns are shown last.
+    pub fn display_order(&self) -> Vec<&Column> {
+        let preferred_order = load_display_order(&self.name).unwrap_or_default();
+
+        let mut ordered_columns: Vec<&Column> = preferred_order
+            .iter()
+            .filter_map(|name| self.columns.iter().find(|c| c.get_name() == name))
+            .collect();
+
+        for column in &self.columns {
+            if !preferred_order.contains(&column.get_name().to_string()) {
+                ordered_columns.push(column);
+            }
         }
+        ordered_columns
+    }
 
-        text_table.set_header(header_row);
 
-        for row in self.rows() {
-            let mut formatted_row: Vec<String> = Vec::new();
-            for col in self.columns() {
-                formatted_row.push( row.get(col.get_name()).unwrap().to_string() )
+    /// the table as text, with values written out using the saved `DisplaySettings`
+    pub fn to_ascii(&self) -> String {
+        self.to_ascii_with(&DisplaySettings::load())
+    }
+
+
+    /// same as `Table::to_ascii()`, but with the given display settings instead of the saved ones
+    pub fn to_ascii_with(&self, settings: &DisplaySettings) -> String {
+        let rows: Vec<&HashMap<String, FieldValue>> = self.rows().iter().collect();
+        format!("\n{}", render_ascii(&self.display_order(), &rows, settings))
+    }
+
+
+    /// splits the table into pages of text, for tables too long or too wide to read all at once.
+    /// Every page repeats the header, so the columns can always be told apart.
+    ///
+    /// each page has up to `layout.rows_per_page` rows and, if set, `layout.columns_per_page` columns.
+    /// Pages go through all the columns for a set of rows before moving on to the next rows.
+    /// with `layout.sticky_keys`, the primary key columns are shown first on every page, on top of its other columns.
+    pub fn to_ascii_pages(&self, layout: &PageLayout) -> Vec<String> {
+        let settings = DisplaySettings::load();
+        let columns = self.display_order();
+
+        let (key_columns, other_columns): (Vec<&Column>, Vec<&Column>) = if layout.sticky_keys {
+            columns.into_iter().partition(|c| self.primary_keys().iter().any(|pk| pk.get_name() == c.get_name()))
+        } else {
+            (Vec::new(), columns)
+        };
+
+        // the keys take up part of every page, but there is always room for at least one other column
+        let columns_per_frame = match layout.columns_per_page {
+            Some(n) => n.saturating_sub(key_columns.len()).max(1),
+            None => other_columns.len().max(1),
+        };
+        let mut frames: Vec<Vec<&Column>> = other_columns
+            .chunks(columns_per_frame)
+            .map(|chunk| key_columns.iter().chain(chunk).copied().collect())
+            .collect();
+        if frames.is_empty() { frames.push(key_columns) }
+
+        let rows: Vec<&HashMap<String, FieldValue>> = self.rows().iter().collect();
+        let mut row_chunks: Vec<&[&HashMap<String, FieldValue>]> = rows.chunks(layout.rows_per_page.max(1)).collect();
+        if row_chunks.is_empty() { row_chunks.push(&[]) }
+
+        let total_pages = row_chunks.len() * frames.len();
+        let mut pages: Vec<String> = Vec::new();
+        for (row_chunk_index, row_chunk) in row_chunks.iter().enumerate() {
+            for frame in &frames {
+                let first_row = row_chunk_index * layout.rows_per_page.max(1);
+                pages.push(format!(
+                    "\npage {} of {} (rows {}-{} of {})\n{}",
+                    pages.len() + 1, total_pages,
+                    if row_chunk.is_empty() { 0 } else { first_row + 1 }, first_row + row_chunk.len(), rows.len(),
+                    render_ascii(frame, row_chunk, &settings)
+                ));
             }
-            text_table.add_row(formatted_row);
         }
+        pages
+    }
+}
+
+
+/// how `Table::to_ascii_pages()` splits a table into pages
+#[derive(Debug, Clone)]
+pub struct PageLayout {
+    pub rows_per_page: usize,
+    /// the most columns on a page, including the key columns. `None` fits every column on one page
+    pub columns_per_page: Option<usize>,
+    /// show the primary key columns on every page
+    pub sticky_keys: bool,
+}
+
+
+impl Default for PageLayout {
+    fn default() -> Self {
+        PageLayout { rows_per_page: 20, columns_per_page: None, sticky_keys: true }
+    }
+}
+
+
+/// draws the given columns of the rows as a text table, with a header showing each column's name and datatype
+fn render_ascii(columns: &[&Column], rows: &[&HashMap<String, FieldValue>], settings: &DisplaySettings) -> String {
+
+    let mut text_table = comfy_table::Table::new();
+
+    let mut header_row: Vec<comfy_table::Cell> = Vec::new();
+    for col in columns {
+        let cell = comfy_table::Cell::new(format!("{}\n<{}>", col.get_name(), col.get_data_type() ))
+        .set_alignment(comfy_table::CellAlignment::Center);
+        header_row.push(cell);
+
+    }
+
+    text_table.set_header(header_row);
+
+    for row in rows {
+        let mut formatted_row: Vec<String> = Vec::new();
+        for col in columns {
+            formatted_row.push( settings.format(row.get(col.get_name()).unwrap_or(&FieldValue::Null)) )
+        }
+        text_table.add_row(formatted_row);
+    }
+
+    text_table.load_preset(ASCII_MARKDOWN).remove_style(comfy_table::TableComponent::HorizontalLines);
+
+    text_table.to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::{column::{Column, DataType}, relation::table::Table};
+
+    #[test]
+    fn display_orders_are_used_until_cleared() {
+        let columns = ["a", "b", "c"].iter().map(|name| Column::new(name.to_string(), DataType::Number, false)).collect();
+        let table = Table::new("display_test_order".to_owned(), columns, true);
+        let names = |table: &Table| table.display_order().iter().map(|c| c.get_name().to_string()).collect::<Vec<String>>();
+
+        table.set_display_order(&["c".to_owned()]).unwrap();
+        assert_eq!(names(&table), vec!["c", "a", "b"]);
 
-        text_table.load_preset(ASCII_MARKDOWN).remove_style(comfy_table::TableComponent::HorizontalLines);
-        
-        format!("\n{}", text_table.to_string())
+        table.clear_display_order().unwrap();
+        assert_eq!(names(&table), vec!["a", "b", "c"]);
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,168 @@
+use std::{fs::{self, File}, io::{BufReader, BufWriter, Read, Write}, path::Path};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::db_err::DBError};
+
+
+/// every dump file starts with these bytes, so other files are rejected before trying to decode them
+const DUMP_MAGIC: &[u8; 8] = b"SEQLDUMP";
+
+/// bumped whenever the layout of `DumpArchive` changes
+pub const DUMP_FORMAT_VERSION: u32 = 1;
+
+
+/// describes what is inside a dump
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpManifest {
+    pub format_version: u32,
+    /// when the dump was made, in RFC 3339 format
+    pub created_at: String,
+    /// file names of every table in the dump
+    pub tables: Vec<String>,
+    /// file names of every index in the dump
+    pub indexes: Vec<String>,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum DumpedFileKind {
+    Relation,
+    Index,
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct DumpedFile {
+    kind: DumpedFileKind,
+    file_name: String,
+    contents: Vec<u8>,
+}
+
+
+#[derive(Serialize, Deserialize)]
+struct DumpArchive {
+    manifest: DumpManifest,
+    files: Vec<DumpedFile>,
+}
+
+
+/// writes every table and index in the database into a single archive at `out_path`,
+/// so the whole database can be backed up or moved to another machine with `restore_database()`.
+pub fn dump_database(out_path: &str) -> Result<DumpManifest, DBError> {
+
+    let mut files = read_directory(RELATION_PATH, DumpedFileKind::Relation, "db_")?;
+    files.extend( read_directory(INDEX_PATH, DumpedFileKind::Index, "idx_")? );
+
+    let manifest = DumpManifest {
+        format_version: DUMP_FORMAT_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        tables: names_of_kind(&files, DumpedFileKind::Relation),
+        indexes: names_of_kind(&files, DumpedFileKind::Index),
+    };
+    let archive = DumpArchive { manifest: manifest.clone(), files };
+
+    let file = File::create(out_path).map_err(|e| DBError::IOFailure(out_path.to_owned(), e.to_string()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(DUMP_MAGIC).map_err(|e| DBError::IOFailure(out_path.to_owned(), e.to_string()))?;
+    bincode::serialize_into(&mut writer, &archive).map_err(|e| DBError::IOFailure(out_path.to_owned(), e.to_string()))?;
+    writer.flush().map_err(|e| DBError::IOFailure(out_path.to_owned(), e.to_string()))?;
+
+    Ok(manifest)
+}
+
+
+/// reads the manifest of a dump, without restoring anything
+pub fn read_dump_manifest(archive_path: &str) -> Result<DumpManifest, DBError> {
+    Ok(read_archive(archive_path)?.manifest)
+}
+
+
+/// restores every table and index from a dump made by `dump_database()`.
+///
+/// if `overwrite` is false, nothing is restored when any of the tables or indexes already exist.
+pub fn restore_database(archive_path: &str, overwrite: bool) -> Result<DumpManifest, DBError> {
+
+    let archive = read_archive(archive_path)?;
+
+    // check every file first, so a failed restore doesn't leave the database half restored
+    for file in &archive.files {
+        let destination = destination_path(file)?;
+        if !overwrite && Path::new(&destination).exists() {
+            return Err(DBError::IOFailure(destination, "file already exists, restore with overwrite to replace it".to_owned()));
+        }
+    }
+
+    fs::create_dir_all(RELATION_PATH).map_err(|e| DBError::IOFailure(RELATION_PATH.to_owned(), e.to_string()))?;
+    fs::create_dir_all(INDEX_PATH).map_err(|e| DBError::IOFailure(INDEX_PATH.to_owned(), e.to_string()))?;
+
+    for file in &archive.files {
+        let destination = destination_path(file)?;
+        fs::write(&destination, &file.contents).map_err(|e| DBError::IOFailure(destination, e.to_string()))?;
+    }
+
+    Ok(archive.manifest)
+}
+
+
+fn read_archive(archive_path: &str) -> Result<DumpArchive, DBError> {
+    let file = File::open(archive_path).map_err(|e| DBError::IOFailure(archive_path.to_owned(), e.to_string()))?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 8];
+    if reader.read_exact(&mut magic).is_err() || &magic != DUMP_MAGIC {
+        return Err(DBError::IOFailure(archive_path.to_owned(), "not a sequel dump file".to_owned()));
+    }
+
+    let archive: DumpArchive = bincode::deserialize_from(reader)
+        .map_err(|_| DBError::IOFailure(archive_path.to_owned(), "corrupted dump file".to_owned()))?;
+
+    if archive.manifest.format_version != DUMP_FORMAT_VERSION {
+        return Err(DBError::IOFailure(
+            archive_path.to_owned(), 
+            format!("unsupported dump format version {} (expected {})", archive.manifest.format_version, DUMP_FORMAT_VERSION)
+        ));
+    }
+    Ok(archive)
+}
+
+
+/// reads every `.bin` file in `directory` starting with `prefix`
+fn read_directory(directory: &str, kind: DumpedFileKind, prefix: &str) -> Result<Vec<DumpedFile>, DBError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(e) => e,
+        // nothing has been saved there yet
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !file_name.starts_with(prefix) || !file_name.ends_with(".bin") { continue }
+
+        let contents = fs::read(entry.path()).map_err(|e| DBError::IOFailure(file_name.clone(), e.to_string()))?;
+        files.push(DumpedFile { kind, file_name, contents });
+    }
+    files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(files)
+}
+
+
+fn names_of_kind(files: &[DumpedFile], kind: DumpedFileKind) -> Vec<String> {
+    files.iter().filter(|f| f.kind == kind).map(|f| f.file_name.clone()).collect()
+}
+
+
+/// where a file from the dump is restored to. File names with a path in them are rejected,
+/// so a tampered dump can't write outside of the database directories.
+fn destination_path(file: &DumpedFile) -> Result<String, DBError> {
+    if file.file_name.contains(['/', '\\']) || file.file_name.contains("..") {
+        return Err(DBError::IOFailure(file.file_name.clone(), "invalid file name in dump".to_owned()));
+    }
+    let directory = match file.kind {
+        DumpedFileKind::Relation => RELATION_PATH,
+        DumpedFileKind::Index => INDEX_PATH,
+    };
+    Ok(format!("{}/{}", directory, file.file_name))
+}
@@ -1,9 +1,11 @@
 
-use std::{collections::{BTreeMap, HashMap}, fs::File};
+use std::{collections::{BTreeMap, HashMap}, fs::File, ops::Bound};
 
-use crate::{config::INDEX_PATH, structures::{column::{Column, FieldValue}, db_err::DBError, filter::{FilterCondition, FilterConditionValue}}};
+use chrono::{DateTime, Utc};
 
-use super::{io::{index_file_name, load_index}, search::non_index_row_matches_search_critieria, table::Table};
+use crate::{config::INDEX_PATH, structures::{cancel::{check_cancelled, check_row_limit}, column::{Column, DataType, FieldValue}, db_err::DBError, filter::{FilterCondition, FilterConditionValue, FilterExpr}}};
+
+use super::{io::{index_file_name, load_index}, lineage::{Lineage, LineageOperation}, masking::masking_key, search::{non_index_row_matches_search_critieria, similarity}, table::Table};
 
 
 impl Table {
@@ -15,10 +17,16 @@ impl Table {
         if !self.is_valid_column( &column_name ) { 
             return Err(DBError::InvalidColumn(column_name.to_string()))
         }
+        let lineage = Lineage::new(
+            LineageOperation::Filter,
+            vec![self.as_lineage_source()],
+            format!("where {}", FilterExpr::leaf(column_name, search_criteria.clone()))
+        );
 
 
-        let matching_rows = if self.index_available(column_name, INDEX_PATH) {
+        let mut matching_rows = if self.index_available(column_name, INDEX_PATH) {
             let index = load_index(INDEX_PATH, &self.name, &column_name).unwrap();
+            self.record_index_lookup(column_name);
             // O(n^0.67)
             self.search_with_index(index, search_criteria)?
         } else {
@@ -26,11 +34,19 @@ impl Table {
             self.search_without_index(column_name, search_criteria)?
         };
 
+        // rows which have been soft deleted or expired should never show up in a search
+        let now = Utc::now();
+        matching_rows.retain(|r| self.is_live(r, now));
+
         // a new name is required because this table would override the actual table, incluidng index data 
         let mut filtered_table = Table::new(format!("temp table {} with filtered rows on column {}",&self.name, column_name), self.columns().clone(), true);
+        filtered_table.lineage = Some(lineage);
+        filtered_table.mark_temporary();
 
         for r in matching_rows {
-            filtered_table.insert_row( r )?
+            check_cancelled()?;
+            filtered_table.insert_row( r )?;
+            check_row_limit(filtered_table.rows.len())?;
         }
 
         Ok( filtered_table )
@@ -53,6 +69,13 @@ impl Table {
                 .collect::<Vec<usize>>()
         }
 
+        /// the rows from `lower_bound` to `upper_bound` (inclusive). Nothing is between bounds given the wrong way around,
+        /// which `BTreeMap::range()` would panic on
+        fn find_row_indices_between(index: BTreeMap<FieldValue, Vec<usize>>, lower_bound: FieldValue, upper_bound: FieldValue) -> Vec<usize> {
+            if lower_bound > upper_bound { return Vec::new() }
+            find_row_indices(index, lower_bound..=upper_bound)
+        }
+
         /// makes sure that, to ensure the range is properly built for the index 
         fn validate_condition_is_number(condition: &FilterConditionValue ) -> Result<(), DBError> {
             if condition.number().is_none() {
@@ -74,6 +97,16 @@ impl Table {
             return Ok(())
         }
 
+        fn validate_condition_is_date_range(condition: &FilterConditionValue ) -> Result<(), DBError> {
+            if condition.date_range().is_none() {
+                return Err(DBError::MisMatchConditionDataType(
+                    FilterConditionValue::DateRange(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MIN_UTC),
+                    condition.clone()
+                ))
+            }
+            Ok(())
+        }
+
         fn search_index_for_bool_or_null(index: BTreeMap<FieldValue, Vec<usize>>, fv: &FieldValue) -> Vec<usize> {
             match index.get(fv) {
                 Some(indices) => indices.clone(),
@@ -86,12 +119,12 @@ impl Table {
             FilterCondition::LessThan(condition_value) => {
                 validate_condition_is_number(&condition_value)?;
                 let search_value = FieldValue::Number(condition_value.number().unwrap());
-                find_row_indices(index, ..search_value)
+                find_row_indices(index, (Bound::Excluded(FieldValue::Null), Bound::Excluded(search_value)))
             },
             FilterCondition::LessThanOrEqualTo(condition_value) => {
                 validate_condition_is_number(&condition_value)?;
                 let search_value = FieldValue::Number(condition_value.number().unwrap());
-                find_row_indices(index, ..=search_value)
+                find_row_indices(index, (Bound::Excluded(FieldValue::Null), Bound::Included(search_value)))
             },
             FilterCondition::GreaterThan(condition_value) => {
                 validate_condition_is_number(&condition_value)?;
@@ -119,21 +152,33 @@ impl Table {
                 let (lower_bound, upper_bound) = condition_value.number_range().unwrap();
                 let lower_bound = FieldValue::Number(lower_bound);
                 let upper_bound = FieldValue::Number(upper_bound);
-                find_row_indices(index, lower_bound..=upper_bound)
+                find_row_indices_between(index, lower_bound, upper_bound)
             },
             FilterCondition::DateBetween(condition_value) => {
-                validate_condition_is_number_range(&condition_value)?;                
+                validate_condition_is_date_range(&condition_value)?;
 
                 let (lower_bound, upper_bound) = condition_value.date_range().unwrap();
                 let lower_bound = FieldValue::Date(lower_bound);
                 let upper_bound = FieldValue::Date(upper_bound);
-                find_row_indices(index, upper_bound..=lower_bound)
+                find_row_indices_between(index, lower_bound, upper_bound)
             },
             FilterCondition::NotEqual(_) => return Err(DBError::ActionNotImplemented("Indexing on inequality".to_owned())),
             FilterCondition::NotNull     => return Err(DBError::ActionNotImplemented("Indexing on non-null values".to_owned())),
             FilterCondition::True  => search_index_for_bool_or_null(index, &FieldValue::Boolean(true)  ),
             FilterCondition::False => search_index_for_bool_or_null(index, &FieldValue::Boolean(false) ),
             FilterCondition::Null  => search_index_for_bool_or_null(index, &FieldValue::Null           ),
+            // only has to check each distinct value once
+            FilterCondition::SimilarTo(text, threshold) => index
+                .iter()
+                .filter(|(fv, _)| !fv.eq(&&FieldValue::Null) && similarity(&fv.to_string(), &text) >= threshold)
+                .flat_map(|(_, v)| v.iter().copied())
+                .collect(),
+            #[cfg(feature = "regex")]
+            FilterCondition::Matches(pattern) => index
+                .iter()
+                .filter(|(fv, _)| !fv.eq(&&FieldValue::Null) && pattern.is_match(&fv.to_string()))
+                .flat_map(|(_, v)| v.iter().copied())
+                .collect(),
         };
 
         let mut rows: Vec<&HashMap<String, FieldValue>> = Vec::with_capacity( eligible_row_indices.len() );
@@ -152,6 +197,7 @@ impl Table {
         let mut matching_rows: Vec<&HashMap<String, FieldValue>> = Vec::new(); 
 
         for row in &self.rows {
+            check_cancelled()?;
             let row_value: &FieldValue = row.get(column_name).unwrap();
 
             if non_index_row_matches_search_critieria(&row_value, &criteria)? {
@@ -186,6 +232,8 @@ impl Table {
     
 
         let mut reduced_table = Table::new( format!("{} with filtered columns", table_name), table_columns, true );
+        reduced_table.lineage = Some(Lineage::new(LineageOperation::Projection, vec![self.as_lineage_source()], column_names.join(", ")));
+        reduced_table.mark_temporary();
         
 
         // get new reduced rows
@@ -205,6 +253,117 @@ impl Table {
         
         Ok( reduced_table )
     }
+
+
+    /// creates a copy of the table where every sensitive column is masked using its `MaskingStrategy`.
+    /// 
+    /// this should be used on any table being shown to a user who isn't allowed to see sensitive data, see `Table::for_reader()`.
+    /// masked columns become `String` columns, since the masked values are no longer their original datatype.
+    pub fn redact(&self) -> Result<Table, DBError> {
+        let key = masking_key()?;
+
+        let mut redacted_columns: Vec<Column> = Vec::new();
+        for col in self.columns() {
+            if !col.is_sensitive() { 
+                redacted_columns.push( col.clone() );
+                continue; 
+            }
+            let mut c = Column::new(col.get_name().to_string(), DataType::String, false);
+            c.set_mask( col.get_mask().cloned() );
+            redacted_columns.push( c );
+        }
+
+        let mut redacted_table = Table::new( format!("redacted version of '{}'", &self.name), redacted_columns, true );
+
+        for row in &self.rows {
+            let mut redacted_row: HashMap<String, FieldValue> = HashMap::new();
+
+            for col in self.columns() {
+                let value = row.get(col.get_name()).unwrap_or(&FieldValue::Null);
+                let redacted_value = match col.get_mask() {
+                    Some(mask) => mask.apply(value, &key),
+                    None => value.clone(),
+                };
+                redacted_row.insert( col.get_name().to_string(), redacted_value );
+            }
+            redacted_table.insert_row(&redacted_row)?;
+        }
+
+        Ok( redacted_table )
+    }
  
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::structures::{column::{Column, DataType, FieldValue}, filter::{FilterCondition, FilterConditionValue}, relation::table::Table};
+
+    /// a table of one date per day in january, with the index `index_column()` would build on it
+    fn dated_table() -> (Table, BTreeMap<FieldValue, Vec<usize>>) {
+        let mut table = Table::new("dates".to_owned(), vec![Column::new("day".to_owned(), DataType::Date, false)], true);
+        let mut index: BTreeMap<FieldValue, Vec<usize>> = BTreeMap::new();
+        for (position, day) in (1..=31).enumerate() {
+            let date = FieldValue::Date(Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap());
+            table.insert_row(&HashMap::from([("day".to_owned(), date.clone())])).unwrap();
+            index.entry(date).or_default().push(position);
+        }
+        (table, index)
+    }
+
+    #[test]
+    fn less_than_on_an_index_skips_nulls() {
+        let mut table = Table::new("numbers".to_owned(), vec![Column::new("n".to_owned(), DataType::Number, false)], true);
+        let mut index: BTreeMap<FieldValue, Vec<usize>> = BTreeMap::new();
+        for (position, n) in [FieldValue::Null, FieldValue::Number(1.0), FieldValue::Number(5.0)].into_iter().enumerate() {
+            table.insert_row(&HashMap::from([("n".to_owned(), n.clone())])).unwrap();
+            index.entry(n).or_default().push(position);
+        }
+
+        for condition in [FilterCondition::LessThan(FilterConditionValue::Number(5.0)), FilterCondition::LessThanOrEqualTo(FilterConditionValue::Number(5.0))] {
+            let rows = table.search_with_index(index.clone(), condition.clone()).unwrap();
+            assert!(rows.iter().all(|r| r["n"] != FieldValue::Null));
+            assert_eq!(rows, table.search_without_index(&"n".to_owned(), condition).unwrap());
+        }
+    }
+
+    #[test]
+    fn date_between_on_an_index_matches_the_range() {
+        let (table, index) = dated_table();
+        let range = FilterConditionValue::DateRange(
+            Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 12, 0, 0, 0).unwrap()
+        );
+
+        let rows = table.search_with_index(index.clone(), FilterCondition::DateBetween(range.clone())).unwrap();
+        let days: Vec<&FieldValue> = rows.iter().map(|r| &r["day"]).collect();
+        let expected: Vec<FieldValue> = (10..=12).map(|d| FieldValue::Date(Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap())).collect();
+        assert_eq!(days, expected.iter().collect::<Vec<&FieldValue>>());
+
+        // the index and a scan agree
+        assert_eq!(rows, table.search_without_index(&"day".to_owned(), FilterCondition::DateBetween(range)).unwrap());
+    }
+
+    #[test]
+    fn date_between_on_an_index_with_reversed_bounds_is_empty() {
+        let (table, index) = dated_table();
+        let range = FilterConditionValue::DateRange(
+            Utc.with_ymd_and_hms(2024, 1, 12, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap()
+        );
+
+        assert!(table.search_with_index(index, FilterCondition::DateBetween(range)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn date_between_on_an_index_rejects_a_number_range() {
+        let (table, index) = dated_table();
+        let range = FilterConditionValue::NumberRange(1.0, 2.0);
+
+        assert!(table.search_with_index(index, FilterCondition::DateBetween(range)).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use chrono::Utc;
+
+use crate::structures::{cancel::{check_cancelled, check_row_limit}, column::FieldValue, db_err::DBError, filter::FilterExpr};
+
+use super::{lineage::{Lineage, LineageOperation}, soft_delete::DELETED_AT_COLUMN, table::Table, versioning::bump_row_version};
+
+
+impl Table {
+
+    /// the positions of every live row which matches the expression
+    pub(super) fn rows_matching(&self, expr: &FilterExpr) -> Result<Vec<usize>, DBError> {
+        for column_name in expr.columns() {
+            if !self.is_valid_column(column_name) {
+                return Err(DBError::InvalidColumn(column_name.to_string()));
+            }
+        }
+
+        let now = Utc::now();
+        let mut matching_rows: Vec<usize> = Vec::new();
+        for (row_index, row) in self.rows.iter().enumerate() {
+            check_cancelled()?;
+            if self.is_live(row, now) && expr.matches(row)? {
+                matching_rows.push(row_index);
+            }
+        }
+        Ok(matching_rows)
+    }
+
+
+    /// same as `Table::select_rows()`, but rows are kept if they match a combination of conditions,
+    /// i.e. `age > 30 AND city = 'Ottawa'`.
+    ///
+    /// a single condition is passed on to `Table::select_rows()`, so it can still use an index.
+    pub fn select_rows_where(&mut self, expr: &FilterExpr) -> Result<Table, DBError> {
+        if let FilterExpr::Leaf(column_name, condition) = expr {
+            return self.select_rows(column_name, condition.clone());
+        }
+
+        let matching_rows = self.rows_matching(expr)?;
+
+        // a new name is required because this table would override the actual table, including index data
+        let mut filtered_table = Table::new(format!("temp table {} with filtered rows where {}", &self.name, expr), self.columns().clone(), true);
+        filtered_table.lineage = Some(Lineage::new(LineageOperation::Filter, vec![self.as_lineage_source()], format!("where {}", expr)));
+        filtered_table.mark_temporary();
+
+        for row_index in matching_rows {
+            check_cancelled()?;
+            filtered_table.insert_row( &self.rows[row_index] )?;
+            check_row_limit(filtered_table.rows.len())?;
+        }
+
+        Ok(filtered_table)
+    }
+
+
+    /// same as `Table::delete_rows()`, but deletes every row matching a combination of conditions.
+    /// Returns the number of rows deleted.
+    ///
+    /// every saved index on the table is rebuilt afterwards since the row positions change.
+    /// If soft deletes are enabled, the rows are only marked as deleted.
+    pub fn delete_rows_where(&mut self, expr: &FilterExpr) -> Result<u32, DBError> {
+        let matching_rows = self.rows_matching(expr)?;
+        if matching_rows.is_empty() { return Ok(0) }
+
+        if self.soft_delete {
+            let now = FieldValue::Date(Utc::now());
+            // rows which were already deleted aren't live, so they never match
+            for row_index in &matching_rows {
+                self.remember_changed_row(*row_index);
+                self.rows[*row_index].insert( DELETED_AT_COLUMN.to_string(), now.clone() );
+            }
+            return Ok(matching_rows.len() as u32);
+        }
+
+        self.remove_rows_at(&matching_rows)?;
+        Ok(matching_rows.len() as u32)
+    }
+
+
+    /// same as `Table::edit_rows()`, but changes `column_to_edit` to `new_value` in every row matching
+    /// a combination of conditions. Returns the number of rows changed.
+    ///
+    /// a primary key can only be changed in a single row, to a value which isn't already used.
+    pub fn edit_rows_where(&mut self, column_to_edit: &str, expr: &FilterExpr, new_value: FieldValue) -> Result<u32, DBError> {
+
+        if self.is_computed_column(column_to_edit) {
+            return Err(DBError::ComputedColumn(column_to_edit.to_string()));
+        }
+        let column = self.column(column_to_edit.to_string()).ok_or(DBError::InvalidColumn(column_to_edit.to_string()))?;
+        if !new_value.eq(&FieldValue::Null) && !column.get_data_type().eq(&new_value.data_type()) {
+            return Err(DBError::WrongValueType(self.constraint_context(column_to_edit, &new_value, None), column.get_data_type().clone()));
+        }
+        self.check_null_allowed(&column, &new_value, None)?;
+
+        let matching_rows = self.rows_matching(expr)?;
+        if matching_rows.is_empty() { return Ok(0) }
+
+        let is_primary_key = self.primary_keys().iter().any(|pk| pk.get_name() == column_to_edit);
+        if is_primary_key || column.is_unique() {
+            // another row already holds the value, or more than one row would end up with it
+            let duplicate = self.check_unique_value(column_to_edit, &new_value, Some(matching_rows[0])).and_then(|_| {
+                if matching_rows.len() == 1 || new_value.eq(&FieldValue::Null) { return Ok(()) }
+                Err(DBError::DuplicateValue(self.constraint_context(column_to_edit, &new_value, Some(matching_rows[0]))))
+            });
+            match duplicate {
+                Err(DBError::DuplicateValue(context)) if is_primary_key => return Err(DBError::DuplicatePrimaryKey(context)),
+                other => other?,
+            }
+        }
+
+        let bump_row_versions = self.row_versions;
+        for row_index in &matching_rows {
+            self.remember_changed_row(*row_index);
+            let row = &mut self.rows[*row_index];
+            row.insert( column_to_edit.to_string(), new_value.clone() );
+            if bump_row_versions { bump_row_version(row); }
+        }
+
+        if is_primary_key || column.is_unique() {
+            self.index_column( column_to_edit.to_string() )?;
+        }
+        self.refresh_computed_columns()?;
+
+        Ok(matching_rows.len() as u32)
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+
+use chrono::Utc;
+
+use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::{cancel::{check_cancelled, check_row_limit}, column::{parse_field_value, FieldValue, ParseMode}, db_err::DBError}};
+
+use super::{io::{load_database, load_index, relation_file_path}, masking::ReadAccess, summary::summarize_all_tables, table::Table};
+
+
+/// a cell holding the value being searched for
+#[derive(Debug, Clone)]
+pub struct ValueMatch {
+    pub table: String,
+    pub column: String,
+    /// the position of the row in the table
+    pub row: usize,
+    /// the primary key values of the row, written as `column=value`, so the row can be found again
+    pub key: String,
+}
+
+
+impl fmt::Display for ValueMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} | row {} | {}", self.table, self.column, self.row, self.key)
+    }
+}
+
+
+impl Table {
+
+    /// finds every cell of the table equal to `value`, read as the datatype of each column.
+    /// Columns which `value` can't be read as are skipped, i.e. `abc` is never looked for in a Number column.
+    ///
+    /// indexed columns are searched with their index, the rest are scanned. Deleted and expired rows are skipped.
+    /// Unless `access` is `ReadAccess::Admin`, sensitive columns aren't searched and sensitive keys are masked.
+    pub fn find_value(&self, value: &str, access: ReadAccess) -> Result<Vec<ValueMatch>, DBError> {
+        let now = Utc::now();
+        let mut matches: Vec<ValueMatch> = Vec::new();
+        // the keys are read from the masked table, which keeps the rows in the same positions
+        let readable = self.for_reader(access)?;
+
+        for column in &self.columns {
+            // a match would tell the reader what the hidden value is
+            if access == ReadAccess::Masked && column.is_sensitive() { continue }
+
+            let target = match parse_field_value(value, ParseMode::Strict(column.get_data_type().clone())) {
+                Ok(FieldValue::Null) | Err(_) => continue,
+                Ok(target) => target,
+            };
+            let column_name = column.get_name();
+
+            let row_indices: Vec<usize> = if self.index_available(column_name, INDEX_PATH) {
+                let index = load_index(INDEX_PATH, &self.name, column_name).ok_or(DBError::DataBaseFileFailure(self.name.clone()))?;
+                self.record_index_lookup(column_name);
+                index.get(&target).cloned().unwrap_or_default()
+            } else {
+                let mut row_indices: Vec<usize> = Vec::new();
+                for (row_index, row) in self.rows.iter().enumerate() {
+                    check_cancelled()?;
+                    if row.get(column_name) == Some(&target) { row_indices.push(row_index) }
+                }
+                row_indices
+            };
+
+            for row_index in row_indices {
+                if !self.rows.get(row_index).is_some_and(|row| self.is_live(row, now)) { continue }
+                let row = &readable.rows[row_index];
+                let key = self.primary_keys
+                    .iter()
+                    .map(|pk| format!("{}={}", pk.get_name(), row.get(pk.get_name()).unwrap_or(&FieldValue::Null)))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                matches.push(ValueMatch { table: self.name.clone(), column: column_name.to_string(), row: row_index, key });
+                check_row_limit(matches.len())?;
+            }
+        }
+
+        Ok(matches)
+    }
+}
+
+
+/// searches every saved table for cells equal to `value` (see `Table::find_value()`), for when you know
+/// a value but not which table it is in. Matches are sorted by table, then by column, then by row.
+///
+/// tables which can't be read are skipped, the same way they are left out of `summarize_all_tables()`.
+pub fn find_value_in_all_tables(value: &str, access: ReadAccess) -> Result<Vec<ValueMatch>, DBError> {
+    let mut matches: Vec<ValueMatch> = Vec::new();
+
+    for summary in summarize_all_tables()? {
+        check_cancelled()?;
+        let file_path = relation_file_path(RELATION_PATH, &summary.name);
+        let table = match load_database(&file_path) {
+            Ok(table) => table,
+            Err(_) => continue,
+        };
+        matches.extend( table.find_value(value, access)? );
+        check_row_limit(matches.len())?;
+    }
+
+    matches.sort_by(|a, b| (&a.table, &a.column, a.row).cmp(&(&b.table, &b.column, b.row)));
+    Ok(matches)
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, io::{Read, Seek, SeekFrom}};
+
+use bincode::Options;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::structures::{column::{Column, DataType, FieldValue}, db_err::DBError};
+
+use super::table::Table;
+
+
+/// every table file saved since the file format was versioned starts with these bytes.
+/// Files without them were saved before, in the layout read by `LegacyTable`
+const TABLE_FILE_MAGIC: &[u8; 8] = b"SEQLTABL";
+
+/// bumped whenever the layout of a saved `Table` changes, so files saved by older versions can still be read
+///
+/// - 1: the header holds the generation
+/// - 2: the header also says if the table has a TTL
+/// - 3: the header also says when a temporary table was made
+pub const TABLE_FORMAT_VERSION: u32 = 3;
+
+/// the generation of a table loaded from a file saved before the format was versioned, which has no generation in it.
+/// Those files count as saved once, so they are only overwritten by a table loaded from them
+pub(super) const LEGACY_GENERATION: u64 = 1;
+
+
+/// written right after `TABLE_FILE_MAGIC`, before the table itself. Fields added by later format versions are
+/// written after the ones before them, so older headers are read by stopping early (see `read_file_header()`)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TableFileHeader {
+    /// the version of the format the file was saved in, see `TABLE_FORMAT_VERSION`
+    pub(super) format_version: u32,
+    /// the generation the table was saved with, see `Table::generation()`
+    pub(super) generation: u64,
+    /// true if rows of the table expire (see `Table::set_ttl()`), so what it returns changes over time without it being saved.
+    /// Added in format version 2
+    pub(super) has_ttl: bool,
+    /// when the table was made, if it is temporary (see `Table::is_temporary()`). Added in format version 3,
+    /// so it is always `None` in older files even if the table is temporary
+    pub(super) temporary_since: Option<DateTime<Utc>>,
+}
+
+
+/// the fields of the header from format version 1
+#[derive(Serialize, Deserialize)]
+struct HeaderV1 {
+    format_version: u32,
+    generation: u64,
+}
+
+
+/// the layout of a `Column` before the file format was versioned
+#[derive(Deserialize)]
+pub(super) struct LegacyColumn {
+    name: String,
+    data_type: DataType,
+    is_primary_key: bool,
+}
+
+
+impl From<LegacyColumn> for Column {
+    fn from(column: LegacyColumn) -> Self {
+        Column::new(column.name, column.data_type, column.is_primary_key)
+    }
+}
+
+
+/// the layout of a `Table` before the file format was versioned. These files have no header,
+/// and none of the settings added since (i.e. soft deletes or row versions), so they are all turned off.
+#[derive(Deserialize)]
+struct LegacyTable {
+    name: String,
+    columns: Vec<LegacyColumn>,
+    primary_keys: Vec<LegacyColumn>,
+    rows: Vec<HashMap<String, FieldValue>>,
+}
+
+
+impl From<LegacyTable> for Table {
+    fn from(table: LegacyTable) -> Self {
+        Table {
+            name: table.name,
+            generation: LEGACY_GENERATION,
+            columns: table.columns.into_iter().map(Column::from).collect(),
+            primary_keys: table.primary_keys.into_iter().map(Column::from).collect(),
+            rows: table.rows,
+            soft_delete: false,
+            row_versions: false,
+            computed_columns: Vec::new(),
+            validation_rules: Vec::new(),
+            ttl: None,
+            lineage: None,
+            temporary_since: None,
+            change_tracker: None,
+        }
+    }
+}
+
+
+/// the bincode options every table file is written with. The limit stops a damaged file
+/// from claiming a huge length and running out of memory
+fn file_options(limit: u64) -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit)
+}
+
+
+/// the bytes of a table file holding `table`, saved with `generation`
+pub(super) fn encode_table(table: &Table, generation: u64) -> Result<Vec<u8>, bincode::Error> {
+    let header = HeaderV1 { format_version: TABLE_FORMAT_VERSION, generation };
+
+    let mut encoded_data = TABLE_FILE_MAGIC.to_vec();
+    encoded_data.extend( bincode::serialize(&header)? );
+    encoded_data.extend( bincode::serialize(&table.ttl.is_some())? );
+    encoded_data.extend( bincode::serialize(&table.temporary_since)? );
+    encoded_data.extend( bincode::serialize(table)? );
+    Ok(encoded_data)
+}
+
+
+/// reads the header at the start of a table file, leaving `reader` just after it.
+///
+/// returns `None` for files saved before the format was versioned, with `reader` moved back to the start of the file.
+/// Fails if the file was saved by a newer version of sequel, or can't be read at all.
+pub(super) fn read_file_header<R: Read + Seek>(reader: &mut R, file_path: &str, file_size: u64) -> Result<Option<TableFileHeader>, DBError> {
+    let mut magic = [0u8; 8];
+    let has_magic = reader.read_exact(&mut magic).is_ok() && &magic == TABLE_FILE_MAGIC;
+    if !has_magic {
+        reader.seek(SeekFrom::Start(0)).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?;
+        return Ok(None);
+    }
+
+    let header: HeaderV1 = file_options(file_size)
+        .deserialize_from(&mut *reader)
+        .map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?;
+
+    if header.format_version > TABLE_FORMAT_VERSION {
+        return Err(DBError::IOFailure(
+            file_path.to_owned(),
+            format!("saved by a newer version of sequel (table format {}, expected at most {})", header.format_version, TABLE_FORMAT_VERSION)
+        ));
+    }
+
+    let has_ttl: bool = match header.format_version {
+        1 => false,
+        _ => file_options(file_size).deserialize_from(&mut *reader).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?,
+    };
+    let temporary_since: Option<DateTime<Utc>> = match header.format_version {
+        1 | 2 => None,
+        _ => file_options(file_size).deserialize_from(&mut *reader).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?,
+    };
+    Ok(Some(TableFileHeader { format_version: header.format_version, generation: header.generation, has_ttl, temporary_since }))
+}
+
+
+/// decodes a table file, whichever version of the format it was saved in
+pub(super) fn decode_table(bytes: &[u8], file_path: &str) -> Result<Table, DBError> {
+    let mut reader = std::io::Cursor::new(bytes);
+    let options = file_options(bytes.len() as u64);
+
+    let table = match read_file_header(&mut reader, file_path, bytes.len() as u64)? {
+        Some(header) => {
+            let mut table: Table = options.deserialize_from(&mut reader).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?;
+            table.generation = header.generation;
+            table
+        },
+        None => {
+            let table: LegacyTable = options.deserialize_from(&mut reader).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?;
+            Table::from(table)
+        },
+    };
+    Ok(table)
+}
+
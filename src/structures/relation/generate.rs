@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::structures::{cancel::{check_cancelled, check_row_limit}, column::{Column, DataType, FieldValue}, db_err::DBError, generator::ValueGenerator};
+
+use super::{spill::Row, table::Table};
+
+
+impl Table {
+
+    /// makes a new table with `n_rows` rows of made up data, using each column's generator.
+    ///
+    /// the same seed always gives the same rows, so generated tables can be used for repeatable tests and benchmarks.
+    pub fn generate(name: String, schema: Vec<(Column, ValueGenerator)>, n_rows: usize, seed: u64) -> Result<Table, DBError> {
+        let columns: Vec<Column> = schema.iter().map(|(c, _)| c.clone()).collect();
+        let mut generators: Vec<(String, ValueGenerator)> = schema
+            .into_iter()
+            .map(|(c, g)| (c.get_name().to_string(), g))
+            .collect();
+
+        let mut table = Table::new(name, columns, false);
+        table.insert_rows_from_generators(&mut generators, n_rows, seed)?;
+        Ok(table)
+    }
+
+
+    /// adds `n_rows` rows of made up data to the table, picking a generator for each column with
+    /// `ValueGenerator::default_for()`. Returns how many rows were added.
+    ///
+    /// numeric primary keys continue counting from the largest value already in the table. Computed columns and the
+    /// columns kept up to date by the table itself (soft deletes and row versions) are left for the table to fill.
+    pub fn insert_generated_rows(&mut self, n_rows: usize, seed: u64) -> Result<u32, DBError> {
+        let mut generators: Vec<(String, ValueGenerator)> = Vec::new();
+
+        for column in self.writable_columns() {
+            let name = column.get_name().to_string();
+
+            let generator = match ValueGenerator::default_for(column) {
+                ValueGenerator::Sequence(_) => ValueGenerator::Sequence(self.largest_number_in(&name) + 1.0),
+                ValueGenerator::Identifier { prefix, .. } => {
+                    let next = self.largest_identifier_in(&name, &prefix) + 1;
+                    ValueGenerator::Identifier { prefix, next }
+                },
+                generator => generator,
+            };
+            generators.push((name, generator));
+        }
+
+        self.insert_rows_from_generators(&mut generators, n_rows, seed)
+    }
+
+
+    fn insert_rows_from_generators(&mut self, generators: &mut [(String, ValueGenerator)], n_rows: usize, seed: u64) -> Result<u32, DBError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for generated in 0..n_rows {
+            check_cancelled()?;
+            check_row_limit(generated + 1)?;
+            let row: Row = generators
+                .iter_mut()
+                .map(|(column_name, generator)| (column_name.clone(), generator.generate(&mut rng)))
+                .collect::<HashMap<String, FieldValue>>();
+            self.insert_row(&row)?;
+        }
+        Ok(n_rows as u32)
+    }
+
+
+    /// the largest number in a numeric column, or 0 if there aren't any
+    fn largest_number_in(&self, column_name: &str) -> f64 {
+        if !self.column(column_name.to_string()).is_some_and(|c| c.get_data_type().eq(&DataType::Number)) {
+            return 0.0;
+        }
+        self.rows
+            .iter()
+            .filter_map(|r| match r.get(column_name) {
+                Some(FieldValue::Number(n)) => Some(*n),
+                _ => None,
+            })
+            .fold(0.0, f64::max)
+    }
+
+
+    /// the largest counter of an identifier (i.e. 42 for "ID-000042") in a column, or 0 if there aren't any
+    fn largest_identifier_in(&self, column_name: &str, prefix: &str) -> u64 {
+        self.rows
+            .iter()
+            .filter_map(|r| match r.get(column_name) {
+                Some(FieldValue::String(s)) => s.strip_prefix(prefix)?.parse::<u64>().ok(),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::HashMap, fmt, fs, sync::Mutex};
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::db_err::DBError};
+
+use super::{io::{index_file_name, relation_file_path}, summary::summarize_all_tables, table::Table};
+
+
+/// the file in the index directory which keeps count of how often each index is used
+pub const INDEX_STATS_FILE_NAME: &str = "index_stats.bin";
+
+
+/// the number of lookups counted in memory before they are added to the stats file
+pub const INDEX_STATS_FLUSH_LOOKUPS: u64 = 100;
+
+
+/// lookups counted since the stats file was last written, keyed by the path of their table's file
+/// and then by index file name. See `flush_index_stats()`
+static PENDING_LOOKUPS: Mutex<Option<HashMap<String, HashMap<String, IndexCounter>>>> = Mutex::new(None);
+
+
+/// how often an index has been used to answer a search, keyed by its file name in the stats file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct IndexCounter {
+    lookups: u64,
+    last_used: Option<DateTime<Utc>>,
+}
+
+
+/// how much an index saved on disk has been used
+#[derive(Debug, Clone)]
+pub struct IndexUsage {
+    pub table: String,
+    pub column: String,
+    /// the number of searches which were answered with the index
+    pub lookups: u64,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+
+impl fmt::Display for IndexUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let last_used = match self.last_used {
+            Some(time) => DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string(),
+            None => "never".to_owned(),
+        };
+        write!(f, "{}.{} | {} lookup(s) | last used {}", self.table, self.column, self.lookups, last_used)
+    }
+}
+
+
+fn stats_file_path() -> String {
+    format!("{}/{}", INDEX_PATH, INDEX_STATS_FILE_NAME)
+}
+
+
+fn load_counters() -> HashMap<String, IndexCounter> {
+    fs::read(stats_file_path())
+        .ok()
+        .and_then(|data| bincode::deserialize(&data).ok())
+        .unwrap_or_default()
+}
+
+
+impl Table {
+
+    /// counts one use of the index on `column_name`. Temporary tables which were never saved aren't counted.
+    ///
+    /// lookups are counted in memory and only written to the stats file every `INDEX_STATS_FLUSH_LOOKUPS` lookups,
+    /// or by `flush_index_stats()`. The statistics are only informational, so failing to save them never fails
+    /// the search that used the index.
+    pub(super) fn record_index_lookup(&self, column_name: &str) {
+        let mut pending = PENDING_LOOKUPS.lock().unwrap_or_else(|e| e.into_inner());
+        let pending = pending.get_or_insert_with(HashMap::new);
+
+        let counter = pending
+            .entry(relation_file_path(RELATION_PATH, &self.to_file_name()))
+            .or_default()
+            .entry(index_file_name(&self.name, column_name))
+            .or_default();
+        counter.lookups += 1;
+        counter.last_used = Some(Utc::now());
+
+        let pending_lookups: u64 = pending.values().flat_map(|counters| counters.values()).map(|c| c.lookups).sum();
+        if pending_lookups >= INDEX_STATS_FLUSH_LOOKUPS {
+            let _ = write_pending_lookups(pending);
+        }
+    }
+}
+
+
+/// adds the lookups counted in memory to the stats file. Lookups which haven't been flushed are lost when the
+/// program exits, so this should be called before then (a `Session` calls it when it ends).
+pub fn flush_index_stats() -> Result<(), DBError> {
+    let mut pending = PENDING_LOOKUPS.lock().unwrap_or_else(|e| e.into_inner());
+    match pending.as_mut() {
+        Some(pending) => write_pending_lookups(pending),
+        None => Ok(()),
+    }
+}
+
+
+/// adds `pending` to the stats file and empties it. Lookups on tables which were never saved are dropped
+fn write_pending_lookups(pending: &mut HashMap<String, HashMap<String, IndexCounter>>) -> Result<(), DBError> {
+    if pending.is_empty() { return Ok(()) }
+
+    let mut counters = load_counters();
+    for (table_path, table_counters) in pending.drain() {
+        if fs::metadata(table_path).is_err() { continue }
+        for (index, pending_counter) in table_counters {
+            let counter = counters.entry(index).or_default();
+            counter.lookups += pending_counter.lookups;
+            counter.last_used = counter.last_used.max(pending_counter.last_used);
+        }
+    }
+
+    let file_path = stats_file_path();
+    let encoded_data = bincode::serialize(&counters).map_err(|_| DBError::DataBaseFileFailure(file_path.clone()))?;
+    fs::write(&file_path, encoded_data).map_err(|e| DBError::IOFailure(file_path, e.to_string()))
+}
+
+
+/// lists the usage of every index belonging to a saved table, sorted by table and then column.
+///
+/// indexes which were never used to answer a search have zero lookups.
+pub fn index_usage() -> Result<Vec<IndexUsage>, DBError> {
+    flush_index_stats()?;
+    let counters = load_counters();
+
+    let mut usage = Vec::new();
+    for summary in summarize_all_tables()? {
+        for column in summary.indexes {
+            let counter = counters.get(&index_file_name(&summary.name, &column)).cloned().unwrap_or_default();
+            usage.push(IndexUsage { table: summary.name.clone(), column, lookups: counter.lookups, last_used: counter.last_used });
+        }
+    }
+    usage.sort_by(|a, b| a.table.cmp(&b.table).then(a.column.cmp(&b.column)));
+    Ok(usage)
+}
+
+
+/// lists every index which has never been used to answer a search. These still have to be updated
+/// whenever their table changes, so dropping them saves work on every write.
+pub fn unused_indexes() -> Result<Vec<IndexUsage>, DBError> {
+    Ok( index_usage()?.into_iter().filter(|u| u.lookups == 0).collect() )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::{column::{Column, DataType, FieldValue}, filter::{FilterCondition, FilterConditionValue}, relation::{io::{index_file_name, relation_file_path, relation_lock_file_name}, table::Table}}};
+
+    use super::{flush_index_stats, load_counters};
+
+    #[test]
+    fn lookups_are_saved_in_batches() {
+        let table_name = "index_stats_test_batches";
+        let mut table = Table::new(table_name.to_owned(), vec![Column::new("n".to_owned(), DataType::Number, false)], true);
+        for n in 0..5 {
+            table.insert_row(&HashMap::from([("n".to_owned(), FieldValue::Number(n as f64))])).unwrap();
+        }
+        table.save(RELATION_PATH.to_owned()).unwrap();
+        table.index_column("n".to_owned()).unwrap();
+        let index = index_file_name(table_name, "n");
+        let saved_lookups = || load_counters().get(&index).map(|c| c.lookups).unwrap_or(0);
+        let lookups_before = saved_lookups();
+
+        for _ in 0..3 {
+            table.select_rows(&"n".to_owned(), FilterCondition::Equal(FilterConditionValue::Number(2.0))).unwrap();
+        }
+        assert_eq!(saved_lookups(), lookups_before);
+
+        flush_index_stats().unwrap();
+        assert_eq!(saved_lookups(), lookups_before + 3);
+
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index));
+        let _ = fs::remove_file(relation_file_path(RELATION_PATH, table_name));
+        let _ = fs::remove_file(format!("{}/{}", RELATION_PATH, relation_lock_file_name(table_name)));
+    }
+}
@@ -0,0 +1,271 @@
+use std::{collections::{BTreeMap, HashSet}, fmt, fs};
+
+use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::{cancel::check_cancelled, column::FieldValue, db_err::DBError}};
+
+use super::{io::{index_file_name, load_database, load_index, relation_file_path}, table::Table};
+
+
+/// a problem found by `check_integrity()`
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntegrityIssue {
+    /// the table file can't be decoded. Holds the file name
+    UnreadableTable(String),
+    /// a primary key or unique column has no index file. Holds the table and column
+    MissingIndex(String, String),
+    /// a primary key index doesn't point at the rows holding its values. Holds the table and column
+    StaleIndex(String, String),
+    /// an index file which doesn't belong to any column of a saved table. Holds the file name
+    OrphanedIndex(String),
+}
+
+
+impl IntegrityIssue {
+
+    /// what can be done to fix the issue
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            IntegrityIssue::UnreadableTable(_) 
+                => "restore the table from the latest dump with `restore_database()`",
+            IntegrityIssue::MissingIndex(_, _) | IntegrityIssue::StaleIndex(_, _) 
+                => "rebuild the index with `repair_indexes()`",
+            IntegrityIssue::OrphanedIndex(_) 
+                => "delete the index file with `repair_indexes()`",
+        }
+    }
+
+
+    /// true if `repair_indexes()` can fix the issue
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, IntegrityIssue::UnreadableTable(_))
+    }
+}
+
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::UnreadableTable(file) 
+                => write!(f, "the table file '{}' can't be read", file),
+            IntegrityIssue::MissingIndex(table, column) 
+                => write!(f, "the column '{}' of table '{}' has no index", column, table),
+            IntegrityIssue::StaleIndex(table, column) 
+                => write!(f, "the index on '{}' of table '{}' doesn't match the table's rows", column, table),
+            IntegrityIssue::OrphanedIndex(file) 
+                => write!(f, "the index file '{}' doesn't belong to any table", file),
+        }
+    }
+}
+
+
+/// the result of `check_integrity()`
+#[derive(Debug, Clone)]
+pub struct IntegrityReport {
+    pub tables_checked: usize,
+    pub indexes_checked: usize,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+
+impl IntegrityReport {
+    pub fn is_healthy(&self) -> bool { self.issues.is_empty() }
+}
+
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f, "checked {} table(s) and {} index(es), found {} issue(s)", 
+            self.tables_checked, self.indexes_checked, self.issues.len()
+        )?;
+        for issue in &self.issues {
+            writeln!(f, "  - {} ({})", issue, issue.suggestion())?;
+        }
+        Ok(())
+    }
+}
+
+
+/// checks every saved table and index without changing anything:
+/// each table file must be readable, each primary key and unique column must have an index which points at the right rows,
+/// and every index file must belong to a saved table.
+///
+/// this is meant to be run after the database was shut down uncleanly, before trusting its files again.
+pub fn check_integrity() -> Result<IntegrityReport, DBError> {
+
+    let mut report = IntegrityReport { tables_checked: 0, indexes_checked: 0, issues: Vec::new() };
+    // every index file a saved table could have, anything else is an orphan
+    let mut expected_index_files: HashSet<String> = HashSet::new();
+
+    for file_name in file_names_in(RELATION_PATH, "db_")? {
+        check_cancelled()?;
+        report.tables_checked += 1;
+
+        let table = match load_database(&format!("{}/{}", RELATION_PATH, file_name)) {
+            Ok(t) => t,
+            Err(_) => {
+                report.issues.push(IntegrityIssue::UnreadableTable(file_name));
+                continue;
+            }
+        };
+
+        for column in table.columns() {
+            expected_index_files.insert(index_file_name(&table.name, column.get_name()));
+        }
+
+        for column in table.constrained_columns() {
+            let column_name = column.get_name();
+            match load_index(INDEX_PATH, &table.name, column_name) {
+                None => report.issues.push(IntegrityIssue::MissingIndex(table.name.clone(), column_name.to_string())),
+                Some(index) => {
+                    report.indexes_checked += 1;
+                    if !index_matches_rows(&table, column_name, &index) {
+                        report.issues.push(IntegrityIssue::StaleIndex(table.name.clone(), column_name.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    for file_name in file_names_in(INDEX_PATH, "idx_")? {
+        if !expected_index_files.contains(&file_name) {
+            report.issues.push(IntegrityIssue::OrphanedIndex(file_name));
+        }
+    }
+
+    Ok(report)
+}
+
+
+/// fixes every repairable issue in the report, rebuilding missing and stale indexes and deleting orphaned ones.
+/// returns how many issues were fixed. Unreadable tables have to be restored from a dump instead.
+pub fn repair_indexes(report: &IntegrityReport) -> Result<u32, DBError> {
+    let mut number_of_repairs: u32 = 0;
+
+    for issue in &report.issues {
+        match issue {
+            IntegrityIssue::MissingIndex(table_name, column) | IntegrityIssue::StaleIndex(table_name, column) => {
+                let file_path = relation_file_path(RELATION_PATH, table_name);
+                let table = load_database(&file_path)?;
+                table.index_column(column.to_string())?;
+            },
+            IntegrityIssue::OrphanedIndex(file_name) => {
+                let file_path = format!("{}/{}", INDEX_PATH, file_name);
+                fs::remove_file(&file_path).map_err(|e| DBError::IOFailure(file_path, e.to_string()))?;
+            },
+            IntegrityIssue::UnreadableTable(_) => continue,
+        }
+        number_of_repairs += 1;
+    }
+
+    Ok(number_of_repairs)
+}
+
+
+/// every row must be in the index under its own value, and the index can't point anywhere else
+fn index_matches_rows(table: &Table, column_name: &str, index: &BTreeMap<FieldValue, Vec<usize>>) -> bool {
+    let mut indexed_rows = 0;
+    for (value, row_indices) in index {
+        for row_index in row_indices {
+            match table.rows.get(*row_index).and_then(|r| r.get(column_name)) {
+                Some(row_value) if row_value.eq(value) => indexed_rows += 1,
+                _ => return false,
+            }
+        }
+    }
+    indexed_rows == table.rows.len()
+}
+
+
+pub(super) fn file_names_in(directory: &str, prefix: &str) -> Result<Vec<String>, DBError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(e) => e,
+        // nothing has been saved there yet
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut file_names: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with(prefix) && name.ends_with(".bin"))
+        .collect();
+    file_names.sort();
+    Ok(file_names)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::{BTreeMap, HashMap}, fs};
+
+    use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::{column::{Column, DataType, FieldValue}, relation::{io::{index_file_name, relation_file_path, relation_lock_file_name, save_index}, table::Table}}};
+
+    use super::{check_integrity, repair_indexes, IntegrityIssue, IntegrityReport};
+
+    /// saves a table keyed by `id` with a row per id, and removes the index on `id` so it can be checked
+    fn save_table(table_name: &str, ids: &[f64]) {
+        let mut table = Table::new(table_name.to_owned(), vec![Column::new("id".to_owned(), DataType::Number, true)], false);
+        for id in ids {
+            table.insert_row(&HashMap::from([("id".to_owned(), FieldValue::Number(*id))])).unwrap();
+        }
+        table.save(RELATION_PATH.to_owned()).unwrap();
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(table_name, "id")));
+    }
+
+    fn remove_table(table_name: &str) {
+        let _ = fs::remove_file(relation_file_path(RELATION_PATH, table_name));
+        let _ = fs::remove_file(format!("{}/{}", RELATION_PATH, relation_lock_file_name(table_name)));
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(table_name, "id")));
+    }
+
+    /// the issues found in the whole database which are about `table_name`, since other tests save tables too
+    fn issues_with(table_name: &str) -> IntegrityReport {
+        let mut report = check_integrity().unwrap();
+        report.issues.retain(|issue| match issue {
+            IntegrityIssue::MissingIndex(table, _) | IntegrityIssue::StaleIndex(table, _) => table == table_name,
+            IntegrityIssue::UnreadableTable(file) | IntegrityIssue::OrphanedIndex(file) => file.contains(table_name),
+        });
+        report
+    }
+
+    #[test]
+    fn missing_and_stale_indexes_are_found_and_repaired() {
+        let table_name = "integrity_test_indexes";
+        save_table(table_name, &[1.0, 2.0]);
+
+        let report = issues_with(table_name);
+        assert_eq!(report.issues, vec![IntegrityIssue::MissingIndex(table_name.to_owned(), "id".to_owned())]);
+        assert!(report.issues[0].is_repairable());
+        assert_eq!(repair_indexes(&report).unwrap(), 1);
+        assert!(issues_with(table_name).is_healthy());
+
+        // an index pointing at the wrong row
+        save_index(INDEX_PATH, table_name, "id", BTreeMap::from([(FieldValue::Number(1.0), vec![1]), (FieldValue::Number(2.0), vec![0])]));
+        let report = issues_with(table_name);
+        assert_eq!(report.issues, vec![IntegrityIssue::StaleIndex(table_name.to_owned(), "id".to_owned())]);
+        assert_eq!(repair_indexes(&report).unwrap(), 1);
+        assert!(issues_with(table_name).is_healthy());
+
+        remove_table(table_name);
+    }
+
+    #[test]
+    fn orphaned_indexes_and_unreadable_tables_are_reported() {
+        let orphan_name = "integrity_test_orphan";
+        let orphan_path = format!("{}/{}", INDEX_PATH, index_file_name(orphan_name, "id"));
+        save_index(INDEX_PATH, orphan_name, "id", BTreeMap::new());
+
+        let report = issues_with(orphan_name);
+        assert_eq!(report.issues, vec![IntegrityIssue::OrphanedIndex(index_file_name(orphan_name, "id"))]);
+        assert_eq!(repair_indexes(&report).unwrap(), 1);
+        assert!(fs::metadata(&orphan_path).is_err());
+
+        let unreadable_name = "integrity_test_unreadable";
+        fs::write(relation_file_path(RELATION_PATH, unreadable_name), b"not a table").unwrap();
+        let report = issues_with(&unreadable_name.to_uppercase());
+        assert!(matches!(&report.issues[..], [IntegrityIssue::UnreadableTable(_)]));
+        assert!(!report.issues[0].is_repairable());
+        assert_eq!(repair_indexes(&report).unwrap(), 0);
+
+        remove_table(unreadable_name);
+    }
+}
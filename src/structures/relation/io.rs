@@ -1,38 +1,141 @@
-use std::{collections::{BTreeMap, HashMap}, fs::{self, File, OpenOptions}, io::{Read, Write}};
+use std::{collections::{BTreeMap, HashMap}, fs::{self, File, OpenOptions}, io::{self, Read, Write}};
 
-use rust_xlsxwriter::Workbook;
+use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
 
-use crate::structures::{column::{self, parse_into_field_value, Column, DataType, FieldValue}, db_err::DBError};
-use super::table::Table;
+use crate::structures::{cancel::check_cancelled, column::{self, parse_as_data_type, validate_column_name, Column, DataType, FieldValue, INVALID_IDENTIFIER_CHARS}, db_err::DBError, filter::FilterCondition, progress::{ProgressCallback, ProgressReporter}};
+use super::{format::{decode_table, encode_table, read_file_header, LEGACY_GENERATION}, lineage::import_lineage, masking::ReadAccess, search::non_index_row_matches_search_critieria, table::Table};
 
 
 
 ///  -----------
 ///    SAVING 
 ///  -----------
+/// the extension of the file next to each table file which saves are locked on, see `Table::save()`
+pub const TABLE_LOCK_EXTENSION: &str = "lock";
+
+/// the extension of the file a table is written to before it replaces the table file
+const TABLE_TEMP_EXTENSION: &str = "tmp";
+
+
+/// what the header of a saved table says about it, read without loading the table. See `saved_table_state()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedTableState {
+    /// see `Table::generation()`
+    pub generation: u64,
+    /// true if the table's rows expire, see `Table::set_ttl()`
+    pub has_ttl: bool,
+}
+
+
+/// reads the header of the table saved at `file_path`, or `None` if there's no readable table there.
+/// Files saved before the format was versioned have `LEGACY_GENERATION`, and no TTL
+pub fn saved_table_state(file_path: &str) -> Option<SavedTableState> {
+    let file = File::open(file_path).ok()?;
+    let file_size = file.metadata().ok()?.len();
+    if file_size == 0 { return None }
+
+    match read_file_header(&mut std::io::BufReader::new(file), file_path, file_size).ok()? {
+        Some(header) => Some(SavedTableState { generation: header.generation, has_ttl: header.has_ttl }),
+        None => Some(SavedTableState { generation: LEGACY_GENERATION, has_ttl: false }),
+    }
+}
+
+
+/// reads the generation of the table saved at `file_path`, or `None` if there's no readable table there
+fn saved_generation(file_path: &str) -> Option<u64> {
+    saved_table_state(file_path).map(|state| state.generation)
+}
+
+
+/// locks the table file at `file_path` against other saves, until the returned file is dropped.
+/// The lock is taken on a `.lock` file next to it, since the table file itself is replaced by every save
+fn lock_table_file(file_path: &str) -> Result<File, DBError> {
+    let lock_path = format!("{}.{}", file_path.trim_end_matches(".bin"), TABLE_LOCK_EXTENSION);
+    let lock_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|e| DBError::IOFailure(lock_path.clone(), e.to_string()))?;
+
+    lock_file.lock().map_err(|e| DBError::IOFailure(lock_path, e.to_string()))?;
+    Ok(lock_file)
+}
+
+
+/// writes `data` to a temporary file next to `file_path`, then renames it over `file_path`,
+/// so the file is either fully replaced or not changed at all
+fn replace_file(file_path: &str, data: &[u8]) -> Result<(), DBError> {
+    let temp_path = format!("{}.{}", file_path, TABLE_TEMP_EXTENSION);
+
+    let written = File::create(&temp_path)
+        .and_then(|mut file| { file.write_all(data)?; file.sync_all() })
+        .and_then(|_| fs::rename(&temp_path, file_path));
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(DBError::DataBaseFileFailure(file_path.to_owned()))
+    }
+    Ok(())
+}
+
+
 impl Table {
-    pub fn save(&self, local_path: String) -> Result<(), DBError> {
 
-        let file_path = format!("{}/{}",local_path, relation_file_name( &self.to_file_name() ));
-        let encoded_data = bincode::serialize(&self);
+    /// saves the table to its file in `local_path`.
+    ///
+    /// fails with `DBError::TableModified` if the file was saved by someone else since this table was loaded,
+    /// instead of overwriting their changes. New tables (which were never saved) replace whatever is there.
+    ///
+    /// the file is locked while it is checked and written, and is replaced in one step, so a failed or
+    /// interrupted save leaves the previous file as it was.
+    pub fn save(&mut self, local_path: String) -> Result<(), DBError> {
+
+        let file_path = relation_file_path(&local_path, &self.to_file_name());
+        let _lock = lock_table_file(&file_path)?;
+
+        if self.generation > 0 {
+            if let Some(saved_generation) = saved_generation(&file_path) {
+                if saved_generation != self.generation {
+                    return Err(DBError::TableModified(self.name.clone()));
+                }
+            }
+        }
+
+        // the file is written with the next generation, but this table only moves to it once the write succeeds
+        // virtual columns are never saved, they are computed again when the table is loaded
+        let encoded_data = match self.without_virtual_values() {
+            Some(table) => encode_table(&table, self.generation + 1),
+            None => encode_table(self, self.generation + 1),
+        };
         if encoded_data.is_err() { return Err(DBError::DataBaseFileFailure(file_path.to_owned())) }
         let encoded_data = encoded_data.unwrap();
 
-        // open the file in a way that it appends data to the end of the file, not overriding the data 
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&file_path);
+        replace_file(&file_path, &encoded_data)?;
 
-        if file.is_err() { return Err(DBError::DataBaseFileFailure(file_path.to_owned())) }
-        let mut file = file.unwrap();
-        
-        let r = file.write_all(&encoded_data);
-        if r.is_err() { return Err(DBError::DataBaseFileFailure(file_path)) }
-        
+        self.generation += 1;
+        Ok(())
+    }
+
+
+    /// saves a copy of the table taken earlier over its file in `local_path`, even if the file was saved since
+    /// the copy was taken. The indexes on its primary keys and unique columns are rebuilt, since the row positions may have changed.
+    pub fn restore(&mut self, local_path: String) -> Result<(), DBError> {
+        let file_path = relation_file_path(&local_path, &self.to_file_name());
+        self.generation = saved_generation(&file_path).unwrap_or(0);
+        self.save(local_path)?;
+
+        for column in self.constrained_columns() {
+            self.index_column( column.get_name().to_owned() )?;
+        }
         Ok(())
     }
+
+
+    /// the number of times the table has been saved, which changes whenever its file is written
+    pub fn generation(&self) -> u64 { self.generation }
 }
 pub fn save_index(save_dir: &str, table_name: &str, column_name: &str, tree: BTreeMap<FieldValue, Vec<usize>>) {
 
@@ -48,7 +151,7 @@ pub fn save_index(save_dir: &str, table_name: &str, column_name: &str, tree: BTr
 
 impl Table{
     #[allow(dead_code)]
-    fn file_name_for_export(&self, file_extension: &str) -> String {
+    pub(super) fn file_name_for_export(&self, file_extension: &str) -> String {
         format!("sequelDB_{}.{}", &self.name, file_extension)
     }
 
@@ -65,69 +168,423 @@ pub fn import_xlsx() {
 }
 
 
-pub fn import_csv(filepath: &str, delimeter: &str) -> Result<Table, DBError>  {
+/// the outcome of importing a file
+#[derive(Debug)]
+pub struct ImportResult {
+    pub table: Table,
+    /// problems with the file which didn't stop the import, i.e. duplicate column names being renamed
+    pub warnings: Vec<String>,
+    /// the number of rows added to the table
+    pub rows_loaded: usize,
+    /// lines which couldn't be added to the table. When importing from a file on disk, these are also
+    /// written to a `.rejected` file next to it, see `rejected_file_path()`
+    pub rejected_rows: Vec<RejectedRow>,
+}
 
-    let file_data = fs::read( filepath ).map_err(
-        |_| DBError::IOFailure(filepath.to_string(), "unable to read data from file".to_string() )
+
+/// a line of an imported file which couldn't be added to the table
+#[derive(Debug, Clone)]
+pub struct RejectedRow {
+    pub line_number: usize,
+    pub line: String,
+    pub reason: String,
+}
+
+
+/// where the rejected rows of an import from `filepath` are written, i.e. "data.csv" -> "data.csv.rejected"
+pub fn rejected_file_path(filepath: &str) -> String {
+    format!("{}.rejected", filepath)
+}
+
+
+/// writes the rejected rows of an import to the `.rejected` file next to the imported file.
+/// Any old `.rejected` file is removed if every row was imported.
+fn write_rejected_rows(filepath: &str, rejected_rows: &[RejectedRow]) -> Result<(), DBError> {
+    // downloads and standard input have nowhere to put a `.rejected` file
+    if is_remote_path(filepath) || is_stdin_path(filepath) { return Ok(()) }
+
+    let rejected_path = rejected_file_path(filepath);
+
+    if rejected_rows.is_empty() {
+        let _ = fs::remove_file(&rejected_path);
+        return Ok(())
+    }
+
+    let mut contents = String::new();
+    for rejected in rejected_rows {
+        contents += &format!("line {}: {}\n    {}\n", rejected.line_number, rejected.reason, rejected.line);
+    }
+    fs::write(&rejected_path, contents).map_err(|e| DBError::IOFailure(rejected_path, e.to_string()))
+}
+
+
+/// the largest response body (in bytes) that will be accepted when importing from a URL
+pub const MAX_REMOTE_IMPORT_SIZE: u64 = 50 * 1024 * 1024;
+
+/// content types which are accepted as CSV data when importing from a URL
+const ACCEPTED_CSV_CONTENT_TYPES: [&str; 4] = ["text/csv", "text/plain", "application/csv", "application/octet-stream"];
+
+
+/// imports a table from a CSV file. 
+/// 
+/// `filepath` can either be a path on disk, an http(s) URL, in which case the file is 
+/// downloaded using `import_csv_from_url` with a size limit of `MAX_REMOTE_IMPORT_SIZE`,
+/// or `STDIN_PATH` ("-") to read the data piped or pasted into standard input.
+/// 
+/// rows which can't be parsed are skipped and listed in `ImportResult::rejected_rows`, instead of failing the import.
+pub fn import_csv(filepath: &str, delimeter: &str) -> Result<ImportResult, DBError>  {
+
+    if is_remote_path(filepath) {
+        return import_csv_from_url(filepath, delimeter, MAX_REMOTE_IMPORT_SIZE);
+    }
+
+    let file_data = read_csv_source(filepath)?;
+
+    let mut result = parse_csv_data(&file_data, delimeter, None)?;
+    result.table.lineage = Some(import_lineage(filepath, "csv"));
+    write_rejected_rows(filepath, &result.rejected_rows)?;
+    Ok(result)
+}
+
+
+/// same as `import_csv`, but `on_progress` is called with the number of rows imported so far 
+/// and the total number of rows in the file.
+pub fn import_csv_with_progress(filepath: &str, delimeter: &str, on_progress: ProgressCallback) -> Result<ImportResult, DBError> {
+
+    let file_data = read_csv_source(filepath)?;
+
+    let mut result = parse_csv_data(&file_data, delimeter, Some(on_progress))?;
+    result.table.lineage = Some(import_lineage(filepath, "csv"));
+    write_rejected_rows(filepath, &result.rejected_rows)?;
+    Ok(result)
+}
+
+
+/// downloads a CSV file from an http(s) URL and imports it as a table.
+/// 
+/// the response body is streamed into memory, and the import fails if it is larger than `max_bytes`,
+/// or if the server says the content isn't CSV (or plain text).
+pub fn import_csv_from_url(url: &str, delimeter: &str, max_bytes: u64) -> Result<ImportResult, DBError> {
+    let file_data = download_csv(url, max_bytes)?;
+    let mut result = parse_csv_data(&file_data, delimeter, None)?;
+    result.table.lineage = Some(import_lineage(url, "csv"));
+    Ok(result)
+}
+
+
+/// downloads the body of a CSV file, see `import_csv_from_url`
+fn download_csv(url: &str, max_bytes: u64) -> Result<Vec<u8>, DBError> {
+
+    if !is_remote_path(url) {
+        return Err(DBError::IOFailure(url.to_string(), "only http and https URLs can be imported".to_string()));
+    }
+
+    let response = ureq::get(url).call().map_err(
+        |e| DBError::IOFailure(url.to_string(), format!("request failed: {}", e))
     )?;
 
-    
-    let file_data_as_char = file_data
+    // the content type may have parameters attached to it, i.e. "text/csv; charset=utf-8"
+    let content_type = response.content_type().to_lowercase();
+    if !ACCEPTED_CSV_CONTENT_TYPES.contains(&content_type.as_str()) {
+        return Err(DBError::IOFailure(url.to_string(), format!("unexpected content type '{}'", content_type)));
+    }
+
+    // if the server tells us the size up front, fail before downloading anything
+    if let Some(length) = response.header("Content-Length").and_then(|l| l.parse::<u64>().ok()) {
+        if length > max_bytes {
+            return Err(DBError::IOFailure(url.to_string(), format!("file is larger than the {} byte limit", max_bytes)));
+        }
+    }
+
+    // read one byte past the limit so we can tell if the body was cut off
+    let mut file_data: Vec<u8> = Vec::new();
+    response.into_reader()
+        .take(max_bytes + 1)
+        .read_to_end(&mut file_data)
+        .map_err(|_| DBError::IOFailure(url.to_string(), "unable to read response body".to_string()))?;
+
+    if file_data.len() as u64 > max_bytes {
+        return Err(DBError::IOFailure(url.to_string(), format!("file is larger than the {} byte limit", max_bytes)));
+    }
+
+    Ok(file_data)
+}
+
+
+fn is_remote_path(path: &str) -> bool {
+    let lowercased = path.to_lowercase();
+    lowercased.starts_with("http://") || lowercased.starts_with("https://")
+}
+
+
+/// passing this as the path of an import reads the CSV data from standard input instead of a file
+pub const STDIN_PATH: &str = "-";
+
+
+fn is_stdin_path(path: &str) -> bool {
+    path == STDIN_PATH
+}
+
+
+/// reads the raw bytes of CSV data from wherever `filepath` points: a URL, standard input, or a file on disk
+fn read_csv_source(filepath: &str) -> Result<Vec<u8>, DBError> {
+    if is_remote_path(filepath) {
+        return download_csv(filepath, MAX_REMOTE_IMPORT_SIZE);
+    }
+
+    if is_stdin_path(filepath) {
+        // reads until standard input is closed, i.e. the end of a pipe, or ctrl+D after pasting into a terminal
+        let mut file_data: Vec<u8> = Vec::new();
+        io::stdin().lock().read_to_end(&mut file_data).map_err(
+            |e| DBError::IOFailure("standard input".to_string(), e.to_string())
+        )?;
+        return Ok(file_data);
+    }
+
+    fs::read( filepath ).map_err(
+        |_| DBError::IOFailure(filepath.to_string(), "unable to read data from file".to_string() )
+    )
+}
+
+
+/// imports a table from a CSV file using the columns in `schema`, instead of the datatypes written in the file.
+///
+/// the first line of the file must be the column names, which must match the schema (in any order), and every
+/// other line is data. Each cell must parse as its column's datatype, and nulls are only allowed in columns
+/// which allow them. Rows which don't fit the schema are rejected, the same as `import_csv`.
+///
+/// primary keys are enforced if any column in the schema is a primary key.
+pub fn import_csv_with_schema(filepath: &str, delimeter: &str, schema: Vec<Column>) -> Result<ImportResult, DBError> {
+
+    let file_data = read_csv_source(filepath)?;
+
+    let file_data = decompress_if_gzipped(file_data, filepath)?;
+    let file_data_as_char = decode_csv_data(&file_data);
+    let cells_of_data = split_csv_cells(&file_data_as_char, delimeter);
+    let header = cells_of_data.first().ok_or(DBError::IOFailure(filepath.to_string(), "file is empty".to_string()))?;
+
+    // find where each column of the schema is in the file
+    let mut column_positions: Vec<usize> = Vec::new();
+    for (i, column) in schema.iter().enumerate() {
+        validate_column_name(column.get_name())?;
+        if schema[..i].iter().any(|c| c.get_name() == column.get_name()) {
+            return Err(DBError::InvalidColumnName(column.get_name().to_string(), "it is in the schema more than once".to_string()));
+        }
+        let position = header
+            .iter()
+            .position(|h| h.trim() == column.get_name())
+            .ok_or(DBError::MalformedRow(1, format!("column '{}' is missing from the header", column.get_name())))?;
+        column_positions.push(position);
+    }
+    if header.len() != schema.len() {
+        return Err(DBError::MalformedRow(1, format!("expected {} columns, found {}", schema.len(), header.len())));
+    }
+
+    let has_primary_keys = schema.iter().any(|c| c.is_primary_key());
+    let mut table = Table::new("table from imported csv".to_string(), schema.clone(), !has_primary_keys);
+
+    // the header is line 1
+    let mut progress = ProgressReporter::new(None, cells_of_data.len() - 1);
+    let (rows_loaded, rejected_rows) = load_csv_rows(&mut table, &cells_of_data[1..], 2, delimeter, &schema, &column_positions, &mut progress)?;
+
+    table.lineage = Some(import_lineage(filepath, "csv with a schema"));
+    let result = ImportResult { table, warnings: Vec::new(), rows_loaded, rejected_rows };
+    write_rejected_rows(filepath, &result.rejected_rows)?;
+    Ok(result)
+}
+
+
+impl Table {
+
+    /// inserts rows from CSV data into the table, returning the number of rows inserted and the lines which were rejected.
+    ///
+    /// `filepath` is read the same way as `import_csv`, so `STDIN_PATH` ("-") inserts the rows piped or pasted into
+    /// standard input. The first line must be the names of the columns being filled in (in any order), and every
+    /// other line is a row. Each cell must parse as its column's datatype, and rows which can't be inserted are
+    /// rejected instead of failing the insert.
+    pub fn insert_csv_rows(&mut self, filepath: &str, delimeter: &str) -> Result<(usize, Vec<RejectedRow>), DBError> {
+
+        let file_data = read_csv_source(filepath)?;
+        let file_data = decompress_if_gzipped(file_data, filepath)?;
+        let file_data_as_char = decode_csv_data(&file_data);
+        let cells_of_data = split_csv_cells(&file_data_as_char, delimeter);
+        let header = cells_of_data.first().ok_or(DBError::IOFailure(filepath.to_string(), "file is empty".to_string()))?;
+
+        let mut columns: Vec<Column> = Vec::new();
+        for name in header {
+            let column = self.column(name.trim().to_string()).ok_or(DBError::InvalidColumn(name.trim().to_string()))?;
+            if columns.iter().any(|c| c.get_name() == column.get_name()) {
+                return Err(DBError::MalformedRow(1, format!("column '{}' is in the header more than once", column.get_name())));
+            }
+            columns.push(column);
+        }
+        let positions: Vec<usize> = (0..columns.len()).collect();
+
+        // the header is line 1
+        let mut progress = ProgressReporter::new(None, cells_of_data.len() - 1);
+        let (rows_loaded, rejected_rows) = load_csv_rows(self, &cells_of_data[1..], 2, delimeter, &columns, &positions, &mut progress)?;
+
+        write_rejected_rows(filepath, &rejected_rows)?;
+        Ok((rows_loaded, rejected_rows))
+    }
+}
+
+
+/// parses each line of cells into a row of `table`, where `columns[i]` is found at `positions[i]` in the line.
+///
+/// lines which can't be parsed or inserted are rejected instead of failing the import. Returns the number of rows
+/// loaded, and the rejected lines.
+fn load_csv_rows(
+    table: &mut Table,
+    lines: &[Vec<&str>],
+    first_line_number: usize,
+    delimeter: &str,
+    columns: &[Column],
+    positions: &[usize],
+    progress: &mut ProgressReporter
+) -> Result<(usize, Vec<RejectedRow>), DBError> {
+
+    fn parse_line(line: &[&str], columns: &[Column], positions: &[usize]) -> Result<HashMap<String, FieldValue>, String> {
+        if line.len() != columns.len() {
+            return Err(format!("expected {} cells, found {}", columns.len(), line.len()));
+        }
+
+        let mut row: HashMap<String, FieldValue> = HashMap::new();
+        for (column, position) in columns.iter().zip(positions.iter()) {
+            // the datatype of every column is known, so the values must match it
+            let cell_value = parse_as_data_type( line[*position], column.get_data_type() ).map_err(|e| e.to_string())?;
+
+            if cell_value.eq(&FieldValue::Null) && !column.allows_nulls() {
+                return Err(format!("column '{}' can't be null", column.get_name()));
+            }
+            row.insert( column.get_name().to_string(), cell_value );
+        }
+        Ok(row)
+    }
+
+    let mut rows_loaded = 0;
+    let mut rejected_rows: Vec<RejectedRow> = Vec::new();
+
+    for (row_number, line) in lines.iter().enumerate() {
+        check_cancelled()?;
+
+        // blank lines (i.e. at the end of the file) aren't rows
+        if line.len() == 1 && line[0].trim().is_empty() && columns.len() > 1 { 
+            progress.report(row_number + 1);
+            continue 
+        }
+
+        let outcome = parse_line(line, columns, positions)
+            .and_then(|row| table.insert_row(&row).map_err(|e| e.to_string()));
+
+        match outcome {
+            Ok(_) => rows_loaded += 1,
+            Err(reason) => rejected_rows.push(RejectedRow { 
+                line_number: first_line_number + row_number, 
+                line: line.join(delimeter), 
+                reason 
+            }),
+        }
+        progress.report(row_number + 1);
+    }
+
+    Ok((rows_loaded, rejected_rows))
+}
+
+
+/// every gzip file starts with these two bytes
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+
+
+/// decompresses the contents of a gzipped file (i.e. "data.csv.gz"), and leaves anything else as is.
+/// Files are recognized by their first bytes, not their extension, so this works for downloads as well.
+fn decompress_if_gzipped(file_data: Vec<u8>, filepath: &str) -> Result<Vec<u8>, DBError> {
+    if !file_data.starts_with(&GZIP_MAGIC_BYTES) { return Ok(file_data) }
+
+    let mut decompressed_data: Vec<u8> = Vec::new();
+    GzDecoder::new(file_data.as_slice())
+        .read_to_end(&mut decompressed_data)
+        .map_err(|e| DBError::IOFailure(filepath.to_string(), format!("unable to decompress file: {}", e)))?;
+    Ok(decompressed_data)
+}
+
+
+/// converts the raw bytes of a CSV file into text, one character per byte
+fn decode_csv_data(file_data: &[u8]) -> String {
+    file_data
         .iter()
         .map(|v| *v as char)
         .collect::<String>()
-    ;
+}
 
-    let idx_of_last_char_as_byte = file_data_as_char.len() - 1;
 
-    let file_data_as_rows = file_data_as_char
-        [0..idx_of_last_char_as_byte] // slice to remove linefeed char at the end
+/// splits CSV text into lines of cells
+fn split_csv_cells<'a>(file_data_as_char: &'a str, delimeter: &str) -> Vec<Vec<&'a str>> {
+    file_data_as_char
+        .strip_suffix('\n')  // remove the linefeed char at the end
+        .unwrap_or(file_data_as_char)
         .split("\n")
-        .collect::<Vec<&str>>()
-    ;
+        .map(|line| line.trim_end_matches('\r')) // files saved on windows end lines with "\r\n"
+        .map(|line| line.split(delimeter).collect())
+        .collect()
+}
+
+
+/// converts the raw bytes of a CSV file into a table.
+/// 
+/// the first line must be the column names, and the second line must be the column datatypes
+fn parse_csv_data(file_data: &[u8], delimeter: &str, on_progress: Option<ProgressCallback>) -> Result<ImportResult, DBError> {
     
-    let cells_of_data = file_data_as_rows
-        .iter()
-        .map(|s| 
-            s
-            .split(delimeter)
-            .collect()
-        )
-        .collect::<Vec<Vec<&str>>>()
-    ;
+    let file_data = decompress_if_gzipped(file_data.to_vec(), "imported csv")?;
+    let file_data_as_char = decode_csv_data(&file_data);
+    let cells_of_data = split_csv_cells(&file_data_as_char, delimeter);
 
     let mut column_names: Vec<String> = Vec::new();
     let mut column_datatypes: Vec<DataType> = Vec::new();
     let mut columns: Vec<Column> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+
+    let header = cells_of_data.first().ok_or(DBError::IOFailure("imported csv".to_string(), "file is empty".to_string()))?;
+    // the second line gives the datatype of each column
+    let datatypes = cells_of_data.get(1).ok_or(DBError::MalformedRow(2, "the line of column datatypes is missing".to_string()))?;
+
+    for column in header {
+        validate_column_name(column)?;
+
+        // rows are keyed by column name, so repeated names would overwrite each other.
+        // give every repeat a suffix instead, i.e. "name", "name_2", "name_3"
+        let mut unique_name = column.to_string();
+        let mut suffix = 2;
+        while column_names.contains(&unique_name) {
+            unique_name = format!("{}_{}", column, suffix);
+            suffix += 1;
+        }
+        if unique_name != *column {
+            warnings.push( format!("duplicate column '{}' was renamed to '{}'", column, unique_name) );
+        }
 
-    for column in &cells_of_data[0] {
-        column_names.push( column.to_string() );
+        column_names.push( unique_name );
     }
-    for datatype in &cells_of_data[1] {
+    for datatype in datatypes {
         column_datatypes.push( column::parse_str(&datatype) );   
     }
 
-    for (name, data_type) in column_names.iter().zip( column_datatypes ) {
-        columns.push( column::Column::new(name.to_string(), data_type, true) );
+    for (name, data_type) in column_names.iter().zip( column_datatypes.iter() ) {
+        columns.push( column::Column::new(name.to_string(), data_type.clone(), true) );
     }
     let mut table = Table::new(
         "table from imported csv".to_string(),
-        columns,
+        columns.clone(),
         true
     );
 
-    for row_data in cells_of_data[2..].iter() {
-        let mut row: HashMap<String, FieldValue> = HashMap::new();
-
-        for (idx, col) in column_names.iter().enumerate() {
-            let cell_value = parse_into_field_value( &row_data[idx].to_string() );
-            row.insert( col.to_string(), cell_value );
-        }
-        
-        table.insert_row(&row)?;
-    }
+    // the first two lines are the column names and datatypes
+    let data_lines = cells_of_data.get(2..).unwrap_or(&[]);
+    let positions: Vec<usize> = (0..columns.len()).collect();
+    let mut progress = ProgressReporter::new(on_progress, data_lines.len());
+    let (rows_loaded, rejected_rows) = load_csv_rows(&mut table, data_lines, 3, delimeter, &columns, &positions, &mut progress)?;
     
-    Ok(table)
+    Ok(ImportResult { table, warnings, rows_loaded, rejected_rows })
 }
 
 
@@ -139,14 +596,30 @@ pub fn import_csv(filepath: &str, delimeter: &str) -> Result<Table, DBError>  {
 impl Table {
 
     pub fn export_to_xlsx(&self, path: &str, row_offset: usize, col_offset: usize, min_col_width: f64) -> Result<(), DBError> {
+        self.export_to_xlsx_with_options(path, row_offset, col_offset, min_col_width, &ExportOptions::default())
+    }
+
+
+    /// same as `Table::export_to_xlsx()`, but only the rows and columns chosen by `options` are exported
+    pub fn export_to_xlsx_with_options(
+        &self, 
+        path: &str, 
+        row_offset: usize, 
+        col_offset: usize, 
+        min_col_width: f64,
+        options: &ExportOptions
+    ) -> Result<(), DBError> {
+        let readable = self.for_reader(options.access)?;
+        let (columns, rows) = readable.export_selection(options)?;
+
         let file_path = format!("{}/{}", path, self.file_name_for_export("xlsx"));
         let mut workbook = Workbook::new();
         let worksheet = workbook.add_worksheet();
         
         // set column widths
-        for (idx, col) in self.columns().iter().enumerate() {
+        for (idx, col) in columns.iter().enumerate() {
             let mut max_cell_size = 0 as usize;
-            for row in self.rows() {
+            for row in &rows {
                 let cell_size = row
                     .get(col.get_name())
                     .unwrap()
@@ -163,8 +636,8 @@ impl Table {
             worksheet.set_column_width( (row_offset+idx).try_into().unwrap() , col_width ).unwrap();
         }
     
-        for (row_idx , row) in self.rows().iter().enumerate() {
-            for (col_idx, col) in self.columns().iter().enumerate() {
+        for (row_idx , row) in rows.iter().enumerate() {
+            for (col_idx, col) in columns.iter().enumerate() {
                 let cell = row.get(col.get_name()).unwrap();
     
                 let xlxs_row_number: u32 = (row_offset + row_idx).try_into().unwrap();
@@ -180,15 +653,25 @@ impl Table {
 
 
     pub fn export_to_csv(&self, path: &str, delimiter: &str ) -> Result<(), DBError> {
+        self.export_to_csv_with_options(path, delimiter, &ExportOptions::default())
+    }
+
 
-        let path = &format!("{}/{}", path,  &self.file_name_for_export("csv") );
+    /// same as `Table::export_to_csv()`, but only the rows and columns chosen by `options` are exported
+    pub fn export_to_csv_with_options(&self, path: &str, delimiter: &str, options: &ExportOptions) -> Result<(), DBError> {
+        let readable = self.for_reader(options.access)?;
+        let (columns, rows) = readable.export_selection(options)?;
 
-        let number_of_cols = self.columns().len();
+        let file_extension = if options.compress { "csv.gz" } else { "csv" };
+        let path = &format!("{}/{}", path,  &self.file_name_for_export(file_extension) );
+
+        let number_of_cols = columns.len();
         
-        // create the file if it doesn't exist
+        // create the file if it doesn't exist, and clear out anything from an older export
         let mut file = OpenOptions::new()
             .create(true)
             .write(true)
+            .truncate(true)
             .open( path )
             .map_err(
                 |_| DBError::IOFailure(path.to_owned(), "unable to open file".to_string())
@@ -196,7 +679,7 @@ impl Table {
 
 
         let mut table_data_as_csv: String = String::new();
-        for (idx, col) in self.columns().iter().enumerate() {
+        for (idx, col) in columns.iter().enumerate() {
             table_data_as_csv.push_str( col.get_name() );            
             if idx + 1 != number_of_cols {
                 table_data_as_csv.push_str( delimiter );
@@ -205,7 +688,7 @@ impl Table {
             }
         }
 
-        for (idx, col) in self.columns().iter().enumerate() {
+        for (idx, col) in columns.iter().enumerate() {
             let col_data_type_as_str = format!("{}", col.get_data_type() ); 
             table_data_as_csv.push_str( col_data_type_as_str.as_str() );
         
@@ -219,10 +702,10 @@ impl Table {
 
 
 
-        for row in self.rows() {
+        for row in rows {
             let mut formatted_row_data: String = String::new();
 
-            for (idx, col) in self.columns().iter().enumerate() {
+            for (idx, col) in columns.iter().enumerate() {
                 let data = row[col.get_name()].to_string();
                 
                 formatted_row_data.push_str( &data );
@@ -237,14 +720,193 @@ impl Table {
         }
 
 
-        file.write_all( table_data_as_csv.as_bytes() ).map_err(
+        let write_result = if options.compress {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all( table_data_as_csv.as_bytes() ).and_then(|_| encoder.finish().map(|_| ()))
+        } else {
+            file.write_all( table_data_as_csv.as_bytes() )
+        };
+        write_result.map_err(
             |_| return  DBError::IOFailure(path.to_owned(), "Failed to write data to CSV".to_owned())
         )?;
 
         Ok(())
     } 
 
-    
+
+    /// the columns and rows of the table which `options` says to export
+    fn export_selection(&self, options: &ExportOptions) -> Result<ExportSelection<'_>, DBError> {
+
+        let columns: Vec<&Column> = match &options.columns {
+            Some(names) => names
+                .iter()
+                .map(|name| self.columns.iter().find(|c| c.get_name() == name).ok_or(DBError::InvalidColumn(name.to_string())))
+                .collect::<Result<Vec<&Column>, DBError>>()?,
+            None => self.columns.iter().collect(),
+        };
+
+        let rows: Vec<&HashMap<String, FieldValue>> = match &options.filter {
+            Some((column_name, condition)) => {
+                if !self.is_valid_column(column_name) { return Err(DBError::InvalidColumn(column_name.to_string())) }
+
+                let now = Utc::now();
+                let mut matching_rows = Vec::new();
+                for row in &self.rows {
+                    check_cancelled()?;
+                    if !self.is_live(row, now) { continue }
+                    if non_index_row_matches_search_critieria(row.get(column_name).unwrap_or(&FieldValue::Null), condition)? {
+                        matching_rows.push(row);
+                    }
+                }
+                matching_rows
+            },
+            None => self.rows.iter().collect(),
+        };
+
+        Ok((columns, rows))
+    }
+}
+
+
+/// the columns and rows of a table chosen by `ExportOptions`, borrowed from the table
+type ExportSelection<'a> = (Vec<&'a Column>, Vec<&'a HashMap<String, FieldValue>>);
+
+
+/// chooses what part of a table is exported. By default, everything is exported.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// the columns to export, in order. `None` exports every column
+    pub columns: Option<Vec<String>>,
+    /// only rows where the column matches the condition are exported. Deleted and expired rows are left out as well
+    pub filter: Option<(String, FilterCondition)>,
+    /// gzip the exported CSV file, which is saved with a ".csv.gz" extension. Ignored by XLSX exports
+    pub compress: bool,
+    /// sensitive columns are masked unless this is `ReadAccess::Admin`
+    pub access: ReadAccess,
+}
+
+
+/// formatting for workbooks made by `export_tables_to_xlsx()`
+#[derive(Debug, Clone)]
+pub struct XlsxOptions {
+    pub bold_header: bool,
+    /// keep the header row in view when scrolling
+    pub freeze_header: bool,
+    /// add filter dropdowns to the header row
+    pub autofilter: bool,
+    /// excel number format for number columns, i.e. "0.00"
+    pub number_format: String,
+    /// excel number format for date columns, i.e. "yyyy-mm-dd"
+    pub date_format: String,
+    pub min_col_width: f64,
+    /// sensitive columns are masked unless this is `ReadAccess::Admin`
+    pub access: ReadAccess,
+}
+
+impl Default for XlsxOptions {
+    fn default() -> Self {
+        XlsxOptions {
+            bold_header: true,
+            freeze_header: true,
+            autofilter: false,
+            number_format: "General".to_string(),
+            date_format: "yyyy-mm-dd hh:mm:ss".to_string(),
+            min_col_width: 8.0,
+            access: ReadAccess::default(),
+        }
+    }
+}
+
+
+/// the longest name excel allows for a worksheet
+const MAX_WORKSHEET_NAME_LENGTH: usize = 31;
+
+
+/// exports several tables into one workbook at `file_path`, with a worksheet per table named after the table.
+///
+/// unlike `Table::export_to_xlsx()`, cells keep their datatype, so numbers, dates and booleans
+/// can be used in formulas and sorted properly in excel.
+pub fn export_tables_to_xlsx(file_path: &str, tables: &[&Table], options: &XlsxOptions) -> Result<(), DBError> {
+
+    let xlsx_error = |e: rust_xlsxwriter::XlsxError| DBError::IOFailure(file_path.to_string(), e.to_string());
+
+    let header_format = if options.bold_header { Format::new().set_bold() } else { Format::new() };
+    let number_format = Format::new().set_num_format(&options.number_format);
+    let date_format = Format::new().set_num_format(&options.date_format);
+
+    let mut workbook = Workbook::new();
+    let mut used_sheet_names: Vec<String> = Vec::new();
+
+    for table in tables {
+        let worksheet = workbook.add_worksheet();
+        let sheet_name = worksheet_name(&table.name, &used_sheet_names);
+        worksheet.set_name(&sheet_name).map_err(xlsx_error)?;
+        used_sheet_names.push(sheet_name);
+        let table = table.for_reader(options.access)?;
+
+        for (col_idx, col) in table.columns().iter().enumerate() {
+            let col_number = col_idx as u16;
+            worksheet.write_string_with_format(0, col_number, col.get_name(), &header_format).map_err(xlsx_error)?;
+
+            let max_cell_size = table.rows()
+                .iter()
+                .map(|row| row.get(col.get_name()).map(|v| v.to_string().len()).unwrap_or(0))
+                .chain(std::iter::once(col.get_name().len()))
+                .max()
+                .unwrap_or(0);
+            worksheet.set_column_width(col_number, (max_cell_size as f64).max(options.min_col_width)).map_err(xlsx_error)?;
+
+            for (row_idx, row) in table.rows().iter().enumerate() {
+                let row_number = (row_idx + 1) as u32;
+
+                match row.get(col.get_name()).unwrap_or(&FieldValue::Null) {
+                    FieldValue::Number(n) => { worksheet.write_number_with_format(row_number, col_number, *n, &number_format).map_err(xlsx_error)?; },
+                    FieldValue::Boolean(b) => { worksheet.write_boolean(row_number, col_number, *b).map_err(xlsx_error)?; },
+                    FieldValue::Date(d) => {
+                        let date = ExcelDateTime::from_timestamp(d.timestamp()).map_err(xlsx_error)?;
+                        worksheet.write_datetime_with_format(row_number, col_number, date, &date_format).map_err(xlsx_error)?;
+                    },
+                    FieldValue::String(s) | FieldValue::Url(s) => { worksheet.write_string(row_number, col_number, s).map_err(xlsx_error)?; },
+                    // leave the cell empty
+                    FieldValue::Null => (),
+                }
+            }
+        }
+
+        let last_col = table.columns().len().saturating_sub(1) as u16;
+        if options.freeze_header {
+            worksheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+        }
+        if options.autofilter && !table.columns().is_empty() {
+            worksheet.autofilter(0, 0, table.number_of_rows() as u32, last_col).map_err(xlsx_error)?;
+        }
+    }
+
+    workbook.save(file_path).map_err(xlsx_error)
+}
+
+
+/// a valid, unique worksheet name for a table. Excel names can't be longer than 31 characters,
+/// can't contain any of `[]:*?/\`, and can't repeat (ignoring case)
+fn worksheet_name(table_name: &str, used_names: &[String]) -> String {
+    let cleaned_name: String = table_name
+        .chars()
+        .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+        .take(MAX_WORKSHEET_NAME_LENGTH)
+        .collect();
+    let cleaned_name = if cleaned_name.trim().is_empty() { "Sheet".to_string() } else { cleaned_name };
+
+    let is_used = |name: &str| used_names.iter().any(|n| n.to_lowercase() == name.to_lowercase());
+
+    let mut unique_name = cleaned_name.clone();
+    let mut suffix = 2;
+    while is_used(&unique_name) {
+        let suffix_text = format!(" ({})", suffix);
+        let base: String = cleaned_name.chars().take(MAX_WORKSHEET_NAME_LENGTH - suffix_text.len()).collect();
+        unique_name = format!("{}{}", base, suffix_text);
+        suffix += 1;
+    }
+    unique_name
 }
 
 /// -------------
@@ -277,14 +939,10 @@ pub fn load_database(file_path: &str) -> Result<Table, DBError> {
     let r = file.read_to_end(&mut buffer);
     if r.is_err() { return Err(DBError::DataBaseFileFailure(file_path.to_owned())) }
     
-    
-    let decoded_data = bincode::deserialize(&buffer);
-    
-    if decoded_data.is_err() { 
-        return Err(DBError::DataBaseFileFailure(file_path.to_owned()))
-    } else {
-        Ok(decoded_data.unwrap())
-    }
+    // files saved before the format was versioned are still read, see `decode_table()`
+    let mut table = decode_table(&buffer, file_path)?;
+    table.refresh_computed_columns()?;
+    Ok(table)
 }
 
 
@@ -318,11 +976,50 @@ pub fn index_file_name(table_name: &str, column_name: &str) -> String {
 }
 
 
-pub fn relation_file_name(name: &String) -> String {
+pub fn relation_file_name(name: &str) -> String {
     format!("db_{}.bin", format_for_file_name(name) )
 }
 
 
+/// the path of the relation's file in `directory`. Use this rather than building the path from the table name,
+/// since the file name is capitalized (see `load_database()`)
+pub fn relation_file_path(directory: &str, name: &str) -> String {
+    format!("{}/{}", directory, relation_file_name(name))
+}
+
+
+/// the name of the file saves of the relation are locked on, see `Table::save()`
+pub fn relation_lock_file_name(name: &str) -> String {
+    format!("db_{}.{}", format_for_file_name(name), TABLE_LOCK_EXTENSION)
+}
+
+
+/// makes sure a user given table name can be saved and written in queries.
+///
+/// ## Rules
+/// 1. it can't be empty, or contain whitespace. Spaces become underscores in file names, so `a b` would be saved over `a_b`
+/// 2. it can't start with a digit
+/// 3. it can't contain any of `INVALID_IDENTIFIER_CHARS`, or a path separator
+pub fn validate_table_name(name: &str) -> Result<(), DBError> {
+    let invalid = |reason: &str| Err(DBError::InvalidTableName(name.to_string(), reason.to_string()));
+
+    if name.is_empty() {
+        return invalid("table names can't be empty");
+    }
+    if name.contains(char::is_whitespace) {
+        return invalid("table names can't contain spaces, use '_' instead");
+    }
+    if name.chars().next().unwrap().is_ascii_digit() {
+        return invalid("table names can't start with a digit");
+    }
+    if let Some(c) = name.chars().find(|c| INVALID_IDENTIFIER_CHARS.contains(c) || ['/', '\\'].contains(c)) {
+        return invalid(&format!("table names can't contain '{}'", c.escape_default()));
+    }
+
+    Ok(())
+}
+
+
 /// converts a string into its file name counterpart. Used to help find a file for a possible relation
 pub fn format_for_file_name(str: &str) -> String {
     str.to_uppercase().replace(" ", "_")
@@ -341,4 +1038,34 @@ impl Table {
         name.to_uppercase().replace(" ", "_")
     }
     
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::db_err::DBError;
+
+    use super::{parse_csv_data, validate_table_name};
+
+    #[test]
+    fn table_names_with_spaces_are_refused() {
+        assert!(validate_table_name("orders_2024").is_ok());
+        for name in ["", "my orders", "orders\t", "2024_orders", "orders(1)", "a/b"] {
+            assert!(matches!(validate_table_name(name), Err(DBError::InvalidTableName(..))), "{name}");
+        }
+    }
+
+    #[test]
+    fn a_csv_without_a_datatype_line_is_an_error() {
+        assert!(matches!(parse_csv_data(b"id,name\n", ",", None), Err(DBError::MalformedRow(2, _))));
+        assert!(matches!(parse_csv_data(b"id,name", ",", None), Err(DBError::MalformedRow(2, _))));
+        assert!(parse_csv_data(b"", ",", None).is_err());
+    }
+
+    #[test]
+    fn a_csv_with_only_names_and_datatypes_has_no_rows() {
+        let result = parse_csv_data(b"id,name\nnumber,string\n", ",", None).unwrap();
+        assert_eq!(result.table.columns.len(), 2);
+        assert_eq!(result.rows_loaded, 0);
+    }
+}
@@ -1,7 +1,13 @@
 use std::{cmp::Ordering, collections::HashMap};
 
-use crate::structures::{column::{Column, FieldValue}, db_err::DBError};
-use super::table::Table;
+use crate::structures::{cancel::{check_cancelled, check_row_limit, memory_budget_or}, column::{Column, FieldValue}, db_err::DBError, join::CartesianGuard};
+use super::{lineage::{Lineage, LineageOperation}, spill::{sorted_positions, Row, DEFAULT_SORT_MEMORY_BUDGET}, table::Table};
+
+
+/// the value of a row which is joined on, rows without one are joined as `NULL`
+fn join_value<'a>(row: &'a Row, column_to_join: &str) -> &'a FieldValue {
+    row.get(column_to_join).unwrap_or(&FieldValue::Null)
+}
 
 
 impl Table {
@@ -11,8 +17,26 @@ impl Table {
     // TODO: implement Aggregation ? maybe?
     // MIN, MAX, AVG, MED(ian), Count, SUM, GROUP (by) 
     
-    /// performs a cartesian product on the two tables
+    /// performs a cartesian product on the two tables.
+    ///
+    /// refuses with `DBError::CartesianTooLarge` if the result would have more than `DEFAULT_MAX_CARTESIAN_ROWS`
+    /// rows, see `Table::cartesian_join_guarded()` to change or lift the limit.
     pub fn cartesian_join(&self, other: &Table) -> Result<Table, DBError> {
+        self.cartesian_join_guarded(other, CartesianGuard::default())
+    }
+
+
+    /// performs a cartesian product on the two tables, checking the size of the result against `guard` first.
+    ///
+    /// the result always has exactly (rows in self) * (rows in other) rows, so the check is done before any work.
+    pub fn cartesian_join_guarded(&self, other: &Table, guard: CartesianGuard) -> Result<Table, DBError> {
+
+        let estimated_rows = self.rows.len().saturating_mul(other.rows.len());
+        if let CartesianGuard::Limit(max_rows) = guard {
+            if estimated_rows > max_rows {
+                return Err(DBError::CartesianTooLarge(estimated_rows, max_rows));
+            }
+        }
 
         let mut join_table_columns: Vec<Column> = Vec::new();
 
@@ -32,10 +56,17 @@ impl Table {
             join_table_columns,
             true
         );
+        join_table.lineage = Some(Lineage::new(
+            LineageOperation::Join,
+            vec![self.as_lineage_source(), other.as_lineage_source()],
+            "cartesian".to_string()
+        ));
+        join_table.mark_temporary();
         
         // Nested loop join method
         for r_row in self.rows() {
             for s_row in other.rows() {
+                check_cancelled()?;
                 let mut joined_row = r_row.clone();
 
                 for (k, v) in s_row {
@@ -43,6 +74,7 @@ impl Table {
                     joined_row.insert( format!("{} (S)", k), v.clone());
                 }
                 join_table.insert_row(&joined_row)?;
+                check_row_limit(join_table.rows.len())?;
             }
         }
 
@@ -50,13 +82,21 @@ impl Table {
     }
 
 
+    /// joins the two tables on `column_to_join`, keeping the rows of this table which have no match in `other`
+    /// with `NULL` in the columns from `other`.
+    ///
+    /// This uses the statement's memory budget, or `DEFAULT_SORT_MEMORY_BUDGET`, see `Table::outer_join_with_budget()`.
     pub fn outer_join(&self, other: &Table, column_to_join: String) -> Result<Table, DBError> {
-        #[derive(Debug)]
-        struct JoinPair { value_to_sort_on: FieldValue, row_index: usize }
+        self.outer_join_with_budget(other, column_to_join, memory_budget_or(DEFAULT_SORT_MEMORY_BUDGET))
+    }
 
-        fn cmp_pairs(p1: &JoinPair, p2: &JoinPair) -> Ordering {
-            p1.value_to_sort_on.cmp(&p2.value_to_sort_on)
-        }
+
+    /// same as `Table::outer_join()`, but at most `max_keys_in_memory` join values are sorted in memory at once,
+    /// see `Table::sort_rows_with_budget()`.
+    pub fn outer_join_with_budget(&self, other: &Table, column_to_join: String, max_keys_in_memory: usize) -> Result<Table, DBError> {
+        let cmp_pairs = |r_position: usize, s_position: usize| {
+            join_value(&self.rows[r_position], &column_to_join).cmp( join_value(&other.rows[s_position], &column_to_join) )
+        };
         fn join_rows(r1: &HashMap<String, FieldValue>, r2: &HashMap<String, FieldValue>, join_column: &String) -> HashMap<String, FieldValue> {
             let mut result = HashMap::new();
             for (k, v) in r1 {
@@ -89,6 +129,12 @@ impl Table {
             join_table_columns,
             true
         );
+        join_table.lineage = Some(Lineage::new(
+            LineageOperation::Join,
+            vec![self.as_lineage_source(), other.as_lineage_source()],
+            format!("outer on {}", &column_to_join)
+        ));
+        join_table.mark_temporary();
 
 
         // make sure there's at least one element
@@ -96,21 +142,9 @@ impl Table {
             return Ok(join_table)
         }
 
-        let mut r_join_elements: Vec<JoinPair> = Vec::new();
-        let mut s_join_elements: Vec<JoinPair> = Vec::new();
-
-        for (idx, r) in self.rows().iter().enumerate() {
-            let field_value = r.get(&column_to_join).unwrap();
-            r_join_elements.push( JoinPair{ value_to_sort_on: field_value.clone(), row_index: idx} );
-        } 
-        for (idx, r) in other.rows().iter().enumerate() {
-            let field_value = r.get(&column_to_join).unwrap();
-            s_join_elements.push( JoinPair{ value_to_sort_on: field_value.clone(), row_index: idx} );
-        }
-
-        
-        r_join_elements.sort_by(|a, b| cmp_pairs(a, b) );
-        s_join_elements.sort_by(|a, b| cmp_pairs(a, b) );
+        // the positions of the rows in each table, sorted on the join column
+        let r_join_elements: Vec<usize> = sorted_positions(&self.rows, &column_to_join, |a, b| a.cmp(b), max_keys_in_memory)?;
+        let s_join_elements: Vec<usize> = sorted_positions(&other.rows, &column_to_join, |a, b| a.cmp(b), max_keys_in_memory)?;
         
 
 
@@ -123,6 +157,7 @@ impl Table {
         // TODO: need to rethink the whole skipped row vector thing
 
         'outer: loop {
+            check_cancelled()?;
             // stop when one list ran out of elements
             if r_pointer == r_join_elements.len() || s_pointer == s_join_elements.len() {
                 break 'outer;
@@ -131,11 +166,11 @@ impl Table {
             if marked_row.is_none() {
 
                 'until_eq: loop {
-                    let row_cmp_result = cmp_pairs(&r_join_elements[r_pointer], &s_join_elements[s_pointer]);
+                    let row_cmp_result = cmp_pairs(r_join_elements[r_pointer], s_join_elements[s_pointer]);
                     if row_cmp_result == Ordering::Equal     { break 'until_eq; }
                     else if row_cmp_result == Ordering::Less { 
                         // if the current row in r isn't in the join result, it was skipped
-                        if !r_ptr_in_result { skipped_rows.push( (&r_join_elements[r_pointer]).row_index ); }
+                        if !r_ptr_in_result { skipped_rows.push( r_join_elements[r_pointer] ); }
                         r_ptr_in_result = false; 
                         r_pointer += 1;
                     }
@@ -144,10 +179,11 @@ impl Table {
                 marked_row = Some( s_pointer );
             }
 
-            if cmp_pairs( &r_join_elements[r_pointer], &s_join_elements[s_pointer] ) == Ordering::Equal {
-                let r1 =  self.get_row(r_join_elements[r_pointer].row_index).unwrap();
-                let r2 = other.get_row(s_join_elements[s_pointer].row_index).unwrap();
+            if cmp_pairs( r_join_elements[r_pointer], s_join_elements[s_pointer] ) == Ordering::Equal {
+                let r1 =  self.get_row(r_join_elements[r_pointer]).unwrap();
+                let r2 = other.get_row(s_join_elements[s_pointer]).unwrap();
                 join_table.insert_row( &join_rows(r1, r2, &column_to_join) )?;
+                check_row_limit(join_table.rows.len())?;
                 r_ptr_in_result = true;
                 s_pointer += 1;
             } else {
@@ -166,7 +202,7 @@ impl Table {
             // make sure the last element of r wasn't used in the result
             if r_ptr_in_result { r_pointer += 1; r_ptr_in_result = false; continue; } 
 
-            skipped_rows.push( (&r_join_elements[r_pointer]).row_index );
+            skipped_rows.push( r_join_elements[r_pointer] );
             r_pointer += 1;
         }
 
@@ -178,6 +214,7 @@ impl Table {
                 r.insert( column.get_name().to_string(), FieldValue::Null );
             } 
             join_table.insert_row(&r)?;
+            check_row_limit(join_table.rows.len())?;
         }        
 
         return Ok(join_table)
@@ -186,14 +223,18 @@ impl Table {
 
 
     /// based on the algorithm from UCBerkley CS186: https://www.youtube.com/watch?v=jiWCPJtDE2c
+    ///
+    /// This uses the statement's memory budget, or `DEFAULT_SORT_MEMORY_BUDGET`, see `Table::inner_join_with_budget()`.
     pub fn inner_join(&self, other: &Table, column_to_join: String) -> Result<Table, DBError> {
-        
-        #[derive(Debug)]
-        struct JoinPair { value_to_sort_on: FieldValue, row_index: usize }
+        self.inner_join_with_budget(other, column_to_join, memory_budget_or(DEFAULT_SORT_MEMORY_BUDGET))
+    }
 
-        fn cmp_pairs(p1: &JoinPair, p2: &JoinPair) -> Ordering {
-            p1.value_to_sort_on.cmp(&p2.value_to_sort_on)
-        }
+
+    /// same as `Table::inner_join()`, but at most `max_keys_in_memory` join values are sorted in memory at once.
+    /// Both tables are sorted on the join column by position, so the join values themselves are only held
+    /// in memory while sorting, see `Table::sort_rows_with_budget()`.
+    pub fn inner_join_with_budget(&self, other: &Table, column_to_join: String, max_keys_in_memory: usize) -> Result<Table, DBError> {
+        
         fn join_rows(r1: &HashMap<String, FieldValue>, r2: &HashMap<String, FieldValue>, join_column: &String) -> HashMap<String, FieldValue> {
             let mut result = r1.clone();
             for (k, v) in r2 {
@@ -223,58 +264,141 @@ impl Table {
         let mut join_table: Table = Table::new(
             format!("Join Result of Tables {} and {} on column {}", self.name(), other.name(), &column_to_join),
             join_table_columns,
-            false
+            true
         );
+        join_table.lineage = Some(Lineage::new(
+            LineageOperation::Join,
+            vec![self.as_lineage_source(), other.as_lineage_source()],
+            format!("inner on {}", &column_to_join)
+        ));
+        join_table.mark_temporary();
+
+        self.merge_join(&column_to_join, other, &column_to_join, max_keys_in_memory, |r_position, s_position| {
+            join_table.insert_row( &join_rows(&self.rows[r_position], &other.rows[s_position], &column_to_join) )?;
+            check_row_limit(join_table.rows.len())
+        })?;
 
+        Ok(join_table)
+    }
 
-        // make sure there's at least one element
-        if self.rows().len() == 0 || other.rows().len() == 0 {
-            return Ok(join_table)
+
+    /// joins the rows where `left_column` of this table equals `right_column` of `other`, keeping every column of both tables.
+    /// Unlike `Table::inner_join()` the join columns can have different names, and `NULL` doesn't match anything.
+    ///
+    /// the tables can't share any column names, use `Table::qualified()` to give their columns distinct names first.
+    pub fn inner_join_on(&self, other: &Table, left_column: &str, right_column: &str) -> Result<Table, DBError> {
+        if !self.is_valid_column(&left_column.to_string()) {
+            return Err(DBError::InvalidColumn(left_column.to_string()));
+        }
+        if !other.is_valid_column(&right_column.to_string()) {
+            return Err(DBError::InvalidColumn(right_column.to_string()));
+        }
+        if let Some(shared) = other.columns().iter().find(|c| self.is_valid_column(&c.get_name().to_string())) {
+            return Err(DBError::AmbiguousColumn(
+                shared.get_name().to_string(),
+                vec![format!("{}.{}", self.name(), shared.get_name()), format!("{}.{}", other.name(), shared.get_name())]
+            ));
         }
 
-        let mut r_join_elements: Vec<JoinPair> = Vec::new();
-        let mut s_join_elements: Vec<JoinPair> = Vec::new();
+        let join_table_columns: Vec<Column> = self.columns().iter().chain(other.columns().iter()).cloned().collect();
+        let mut join_table: Table = Table::new(
+            format!("Join Result of Tables {} and {} on {} = {}", self.name(), other.name(), left_column, right_column),
+            join_table_columns,
+            true
+        );
+        join_table.lineage = Some(Lineage::new(
+            LineageOperation::Join,
+            vec![self.as_lineage_source(), other.as_lineage_source()],
+            format!("inner on {} = {}", left_column, right_column)
+        ));
+        join_table.mark_temporary();
+
+        self.merge_join(left_column, other, right_column, memory_budget_or(DEFAULT_SORT_MEMORY_BUDGET), |r_position, s_position| {
+            let r_row = &self.rows[r_position];
+            if join_value(r_row, left_column) == &FieldValue::Null { return Ok(()) }
+
+            let mut joined_row = r_row.clone();
+            joined_row.extend( other.rows[s_position].iter().map(|(k, v)| (k.clone(), v.clone())) );
+            join_table.insert_row(&joined_row)?;
+            check_row_limit(join_table.rows.len())
+        })?;
 
-        for (idx, r) in self.rows().iter().enumerate() {
-            let field_value = r.get(&column_to_join).unwrap();
-            r_join_elements.push( JoinPair{ value_to_sort_on: field_value.clone(), row_index: idx} );
-        } 
-        for (idx, r) in other.rows().iter().enumerate() {
-            let field_value = r.get(&column_to_join).unwrap();
-            s_join_elements.push( JoinPair{ value_to_sort_on: field_value.clone(), row_index: idx} );
-        }
+        Ok(join_table)
+    }
 
-        
-        r_join_elements.sort_by(|a, b| cmp_pairs(a, b) );
-        s_join_elements.sort_by(|a, b| cmp_pairs(a, b) );
-        
+
+    /// a copy of the table where every column is named `alias.column`, i.e. `o.id`, so it can be joined with
+    /// tables sharing its column names. The copy has no primary keys or unique columns, since it is never saved.
+    pub fn qualified(&self, alias: &str) -> Table {
+        let qualify = |column_name: &str| format!("{}.{}", alias, column_name);
+
+        let columns: Vec<Column> = self.columns().iter().map(|c| {
+            let mut column = c.clone();
+            column.new_name( qualify(c.get_name()) );
+            column.change_pk_state(false);
+            column
+        }).collect();
+
+        let mut qualified_table = Table::new(self.name.clone(), columns, true);
+        qualified_table.lineage = self.lineage.clone();
+        qualified_table.rows = self.rows.iter().map(|row| {
+            row.iter().map(|(k, v)| (qualify(k), v.clone())).collect()
+        }).collect();
+
+        qualified_table
+    }
+
+
+    /// calls `on_match` with the positions of every pair of rows where `r_column` of this table equals `s_column` of `other`,
+    /// by sorting both tables on their join column and walking through them together.
+    ///
+    /// based on the algorithm from UCBerkley CS186: https://www.youtube.com/watch?v=jiWCPJtDE2c
+    fn merge_join<F>(&self, r_column: &str, other: &Table, s_column: &str, max_keys_in_memory: usize, mut on_match: F) -> Result<(), DBError>
+    where F: FnMut(usize, usize) -> Result<(), DBError> {
+
+        let cmp_pairs = |r_position: usize, s_position: usize| {
+            join_value(&self.rows[r_position], r_column).cmp( join_value(&other.rows[s_position], s_column) )
+        };
+
+        // the positions of the rows in each table, sorted on the join column
+        let r_join_elements: Vec<usize> = sorted_positions(&self.rows, r_column, |a, b| a.cmp(b), max_keys_in_memory)?;
+        let s_join_elements: Vec<usize> = sorted_positions(&other.rows, s_column, |a, b| a.cmp(b), max_keys_in_memory)?;
 
         let mut marked_row: Option<usize> = None;
         let mut r_pointer: usize = 0;
         let mut s_pointer: usize = 0;
 
-
         'outer: loop {
-            // stop when one list ran out of elements
-            if r_pointer == r_join_elements.len() || s_pointer == s_join_elements.len() {
+            check_cancelled()?;
+            if r_pointer == r_join_elements.len() {
                 break 'outer;
             }
 
+            // when S runs out in the middle of a run of equal values, the next row in R may match the same run
+            if s_pointer == s_join_elements.len() {
+                match marked_row.take() {
+                    Some(marked) => { s_pointer = marked; r_pointer += 1; continue 'outer; },
+                    None => break 'outer,
+                }
+            }
+
             if marked_row.is_none() {
 
                 'until_eq: loop {
-                    let row_cmp_result = cmp_pairs(&r_join_elements[r_pointer], &s_join_elements[s_pointer]);
-                    if row_cmp_result == Ordering::Equal     { break 'until_eq; }
-                    else if row_cmp_result == Ordering::Less { r_pointer += 1;  }
-                    else /* if r > s */                      { s_pointer += 1;  }
+                    match cmp_pairs(r_join_elements[r_pointer], s_join_elements[s_pointer]) {
+                        Ordering::Equal   => break 'until_eq,
+                        Ordering::Less    => r_pointer += 1,
+                        Ordering::Greater => s_pointer += 1,
+                    }
+                    if r_pointer == r_join_elements.len() || s_pointer == s_join_elements.len() {
+                        break 'outer;
+                    }
                 }
                 marked_row = Some( s_pointer );
             }
 
-            if cmp_pairs( &r_join_elements[r_pointer], &s_join_elements[s_pointer] ) == Ordering::Equal {
-                let r1 =  self.get_row(r_join_elements[r_pointer].row_index).unwrap();
-                let r2 = other.get_row(s_join_elements[s_pointer].row_index).unwrap();
-                join_table.insert_row( &join_rows(r1, r2, &column_to_join) )?;
+            if cmp_pairs( r_join_elements[r_pointer], s_join_elements[s_pointer] ) == Ordering::Equal {
+                on_match(r_join_elements[r_pointer], s_join_elements[s_pointer])?;
                 s_pointer += 1;
             } else {
                 s_pointer  = marked_row.unwrap();
@@ -283,8 +407,96 @@ impl Table {
             }
         } 
 
-        return Ok(join_table)
+        Ok(())
     }
 
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::structures::{column::{Column, DataType, FieldValue}, relation::table::Table};
+
+    /// a table with a single `column`, holding one row per key. `None` leaves the cell out of the row
+    fn keyed_table(column: &str, keys: &[Option<f64>]) -> Table {
+        let mut table = Table::new(column.to_owned(), vec![Column::new(column.to_owned(), DataType::Number, false)], true);
+        for key in keys {
+            let row = match key {
+                Some(n) => HashMap::from([(column.to_owned(), FieldValue::Number(*n))]),
+                None => HashMap::from([(column.to_owned(), FieldValue::Null)]),
+            };
+            table.insert_row(&row).unwrap();
+        }
+        table
+    }
+
+    /// every pair of positions `merge_join` matches, in a fixed order
+    fn matched_pairs(r: &Table, s: &Table, max_keys_in_memory: usize) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        r.merge_join("r", s, "s", max_keys_in_memory, |r_position, s_position| {
+            pairs.push((r_position, s_position));
+            Ok(())
+        }).unwrap();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn duplicate_keys_on_both_sides_match_every_pair() {
+        let r = keyed_table("r", &[Some(2.0), Some(1.0), Some(2.0), Some(3.0)]);
+        let s = keyed_table("s", &[Some(4.0), Some(2.0), Some(3.0), Some(2.0)]);
+        assert_eq!(matched_pairs(&r, &s, 100), vec![(0, 1), (0, 3), (2, 1), (2, 3), (3, 2)]);
+    }
+
+    #[test]
+    fn either_side_can_run_out_first() {
+        // S runs out in the middle of a run of equal values in R
+        let r = keyed_table("r", &[Some(5.0), Some(1.0), Some(5.0)]);
+        let s = keyed_table("s", &[Some(5.0)]);
+        assert_eq!(matched_pairs(&r, &s, 100), vec![(0, 0), (2, 0)]);
+
+        // R runs out while S still has larger values
+        let r = keyed_table("r", &[Some(5.0)]);
+        let s = keyed_table("s", &[Some(9.0), Some(5.0), Some(1.0), Some(5.0)]);
+        assert_eq!(matched_pairs(&r, &s, 100), vec![(0, 1), (0, 3)]);
+    }
+
+    #[test]
+    fn empty_inputs_match_nothing() {
+        let empty_r = keyed_table("r", &[]);
+        let empty_s = keyed_table("s", &[]);
+        let r = keyed_table("r", &[Some(1.0)]);
+        let s = keyed_table("s", &[Some(1.0)]);
+        assert!(matched_pairs(&empty_r, &s, 100).is_empty());
+        assert!(matched_pairs(&r, &empty_s, 100).is_empty());
+        assert!(matched_pairs(&empty_r, &empty_s, 100).is_empty());
+    }
+
+    #[test]
+    fn nulls_never_match_in_inner_join_on() {
+        let r = keyed_table("r", &[None, Some(1.0), None]);
+        let s = keyed_table("s", &[None, Some(1.0)]);
+
+        // nulls sort as equal to each other, so `merge_join` pairs them up and the callers skip them
+        assert_eq!(matched_pairs(&r, &s, 100), vec![(0, 0), (1, 1), (2, 0)]);
+
+        let joined = r.inner_join_on(&s, "r", "s").unwrap();
+        assert_eq!(joined.rows().len(), 1);
+        assert_eq!(joined.rows()[0]["r"], FieldValue::Number(1.0));
+    }
+
+    #[test]
+    fn a_join_which_spills_matches_the_same_pairs() {
+        let r_keys: Vec<Option<f64>> = (0..40).map(|n| Some(f64::from(n % 7))).collect();
+        let s_keys: Vec<Option<f64>> = (0..25).map(|n| Some(f64::from(n % 5))).collect();
+        let r = keyed_table("r", &r_keys);
+        let s = keyed_table("s", &s_keys);
+
+        let in_memory = matched_pairs(&r, &s, 1000);
+        assert_eq!(in_memory.len(), 6 * 5 * 5);
+        assert_eq!(matched_pairs(&r, &s, 3), in_memory);
+    }
+}
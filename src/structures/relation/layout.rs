@@ -0,0 +1,120 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::RELATION_PATH, structures::{db_err::DBError, sort::SortCondition}};
+
+use super::table::Table;
+
+
+/// the file in the relation directory which keeps the saved layouts of every table
+pub const LAYOUTS_FILE_NAME: &str = "table_layouts.bin";
+
+
+/// a saved way of viewing a table: which of its columns are shown, in what order, and how the rows are sorted.
+/// i.e. a "compact" view of a wide imported table
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TableLayout {
+    pub columns: Vec<String>,
+    /// the column the rows are sorted by, and how
+    pub sort: Option<(String, SortCondition)>,
+}
+
+
+impl fmt::Display for TableLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.columns.join(", "))?;
+        if let Some((column, condition)) = &self.sort {
+            write!(f, " | sorted by {} ({:?})", column, condition)?;
+        }
+        Ok(())
+    }
+}
+
+
+fn layouts_file_path() -> String {
+    format!("{}/{}", RELATION_PATH, LAYOUTS_FILE_NAME)
+}
+
+
+/// the saved layouts of every table, keyed by table name and then layout name
+fn load_layouts() -> HashMap<String, BTreeMap<String, TableLayout>> {
+    fs::read(layouts_file_path())
+        .ok()
+        .and_then(|data| bincode::deserialize(&data).ok())
+        .unwrap_or_default()
+}
+
+
+fn save_layouts(layouts: &HashMap<String, BTreeMap<String, TableLayout>>) -> Result<(), DBError> {
+    let file_path = layouts_file_path();
+    let encoded_data = bincode::serialize(layouts).map_err(|_| DBError::DataBaseFileFailure(file_path.clone()))?;
+    fs::write(&file_path, encoded_data).map_err(|e| DBError::IOFailure(file_path, e.to_string()))
+}
+
+
+impl Table {
+
+    /// saves a layout for the table under `layout_name`, replacing any layout with the same name.
+    ///
+    /// layouts are kept separately from the table, so it doesn't need to be saved again.
+    pub fn save_layout(&self, layout_name: &str, layout: TableLayout) -> Result<(), DBError> {
+        if layout.columns.is_empty() {
+            return Err(DBError::InvalidColumnName(String::new(), "a layout needs at least one column".to_string()));
+        }
+        for (i, column_name) in layout.columns.iter().enumerate() {
+            if !self.is_valid_column(column_name) {
+                return Err(DBError::InvalidColumn(column_name.to_string()));
+            }
+            if layout.columns[..i].contains(column_name) {
+                return Err(DBError::InvalidColumnName(column_name.to_string(), "it is listed more than once".to_string()));
+            }
+        }
+        if let Some((sort_column, _)) = &layout.sort {
+            if !self.is_valid_column(sort_column) {
+                return Err(DBError::InvalidColumn(sort_column.to_string()));
+            }
+        }
+
+        let mut layouts = load_layouts();
+        layouts.entry(self.name.clone()).or_default().insert(layout_name.to_string(), layout);
+        save_layouts(&layouts)
+    }
+
+
+    /// removes the layout called `layout_name`. Returns false if the table had no layout with that name
+    pub fn remove_layout(&self, layout_name: &str) -> Result<bool, DBError> {
+        let mut layouts = load_layouts();
+        let table_layouts = match layouts.get_mut(&self.name) {
+            Some(table_layouts) => table_layouts,
+            None => return Ok(false),
+        };
+        if table_layouts.remove(layout_name).is_none() { return Ok(false) }
+        if table_layouts.is_empty() { layouts.remove(&self.name); }
+
+        save_layouts(&layouts)?;
+        Ok(true)
+    }
+
+
+    /// every layout saved for the table, keyed by name
+    pub fn layouts(&self) -> BTreeMap<String, TableLayout> {
+        load_layouts().remove(&self.name).unwrap_or_default()
+    }
+
+
+    /// a copy of the table viewed through the layout called `layout_name`, with only its columns, sorted its way.
+    ///
+    /// fails with `DBError::InvalidColumn` if one of the layout's columns was removed from the table since it was saved.
+    pub fn with_layout(&self, layout_name: &str) -> Result<Table, DBError> {
+        let layout = self.layouts()
+            .remove(layout_name)
+            .ok_or(DBError::UnknownLayout(self.name.clone(), layout_name.to_string()))?;
+
+        let mut table = self.clone();
+        if let Some((sort_column, condition)) = layout.sort {
+            table.sort_rows(condition, sort_column)?;
+        }
+        table.select_columns(&layout.columns)
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::RELATION_PATH, structures::db_err::DBError};
+
+use super::{io::{load_database, relation_file_path, STDIN_PATH}, table::Table};
+
+
+/// how a table was made from its sources
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum LineageOperation {
+    /// read from a file, a URL or standard input
+    Import,
+    /// only the rows matching a condition were kept
+    Filter,
+    /// only some of the columns were kept
+    Projection,
+    Join,
+}
+
+
+impl fmt::Display for LineageOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineageOperation::Import     => write!(f, "import"),
+            LineageOperation::Filter     => write!(f, "filter"),
+            LineageOperation::Projection => write!(f, "projection"),
+            LineageOperation::Join       => write!(f, "join"),
+        }
+    }
+}
+
+
+/// a table (or imported file) another table was made from
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LineageSource {
+    /// the name of the source table, or the path of an imported file
+    pub name: String,
+    /// how the source was made, if it was derived from something else too.
+    /// This is copied when the table is made, so the chain is kept even if the source was never saved.
+    pub lineage: Option<Box<Lineage>>,
+}
+
+
+/// where a table came from: what it was made from, how, and when
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lineage {
+    pub operation: LineageOperation,
+    pub sources: Vec<LineageSource>,
+    /// what was done to the sources, i.e. the condition of a filter or the column a join was on
+    pub detail: String,
+    pub created_at: DateTime<Utc>,
+}
+
+
+impl Lineage {
+
+    pub fn new(operation: LineageOperation, sources: Vec<LineageSource>, detail: String) -> Self {
+        Lineage { operation, sources, detail, created_at: Utc::now() }
+    }
+
+
+    /// writes one line for the step which made the table, then each of its sources (and their sources) indented below it
+    fn write_chain(&self, chain: &mut String, depth: usize) {
+        let created_at = DateTime::<Local>::from(self.created_at).format("%Y-%m-%d %H:%M");
+        *chain += &format!(" <- {} {} at {}\n", self.operation, self.detail, created_at);
+
+        for source in &self.sources {
+            *chain += &format!("{}{}", "  ".repeat(depth + 1), source.name);
+            match &source.lineage {
+                Some(lineage) => lineage.write_chain(chain, depth + 1),
+                None => *chain += "\n",
+            }
+        }
+    }
+}
+
+
+impl Table {
+
+    /// how the table was made, or `None` if it was made directly rather than derived from something else
+    pub fn lineage(&self) -> Option<&Lineage> { self.lineage.as_ref() }
+
+
+    /// this table as the source of a table being derived from it, carrying along its own lineage
+    pub(super) fn as_lineage_source(&self) -> LineageSource {
+        LineageSource { name: self.name.clone(), lineage: self.lineage.clone().map(Box::new) }
+    }
+
+
+    /// the chain of tables and files this table was derived from, one step per line, i.e.
+    /// ```text
+    /// big orders <- filter where total > 100 at 2024-05-01 09:30
+    ///   ORDERS <- import csv at 2024-05-01 09:12
+    ///     orders.csv
+    /// ```
+    pub fn lineage_chain(&self) -> String {
+        let mut chain = self.name.clone();
+        match &self.lineage {
+            Some(lineage) => lineage.write_chain(&mut chain, 0),
+            None => chain += " was not derived from another table\n",
+        }
+        chain
+    }
+}
+
+
+/// the lineage of a table imported from `source` (a file path or URL), where `format` says how it was read
+pub(super) fn import_lineage(source: &str, format: &str) -> Lineage {
+    let name = if source == STDIN_PATH { "standard input".to_string() } else { source.to_string() };
+    let source = LineageSource { name, lineage: None };
+    Lineage::new(LineageOperation::Import, vec![source], format.to_string())
+}
+
+
+/// the lineage chain of the saved table called `table_name`, see `Table::lineage_chain()`
+pub fn lineage_of(table_name: &str) -> Result<String, DBError> {
+    let table = load_database(&relation_file_path(RELATION_PATH, table_name))?;
+    Ok(table.lineage_chain())
+}
@@ -0,0 +1,127 @@
+use std::{borrow::Cow, fs};
+
+use crate::{config::RELATION_PATH, structures::db_err::DBError};
+
+use super::table::Table;
+
+
+/// the file holding the database's masking key, see `masking_key()`
+pub const MASKING_KEY_FILE_NAME: &str = "masking_key.bin";
+
+/// the length of the masking key in bytes
+const MASKING_KEY_LENGTH: usize = 32;
+
+
+/// who is reading a table. Sensitive columns (see `Column::set_mask()`) are masked for everyone except admins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadAccess {
+    /// sensitive columns are masked using their `MaskingStrategy`
+    #[default]
+    Masked,
+    /// every value is shown as it is saved
+    Admin,
+}
+
+
+/// the secret key used by `MaskingStrategy::Hash`. It is made the first time it is needed and kept in the
+/// relation directory, so every table in the database is masked with the same key.
+///
+/// anyone who can read the key can brute force hashed values, so it should be as private as the tables themselves.
+pub fn masking_key() -> Result<Vec<u8>, DBError> {
+    let file_path = format!("{}/{}", RELATION_PATH, MASKING_KEY_FILE_NAME);
+
+    if let Ok(key) = fs::read(&file_path) {
+        if key.len() != MASKING_KEY_LENGTH {
+            return Err(DBError::IOFailure(file_path, "the masking key is damaged".to_owned()));
+        }
+        return Ok(key);
+    }
+
+    let key: [u8; MASKING_KEY_LENGTH] = rand::random();
+    fs::write(&file_path, key).map_err(|e| DBError::IOFailure(file_path, e.to_string()))?;
+    Ok(key.to_vec())
+}
+
+
+impl Table {
+
+    /// true if any of the table's columns are masked for readers who aren't admins
+    pub fn has_sensitive_columns(&self) -> bool {
+        self.columns.iter().any(|c| c.is_sensitive())
+    }
+
+
+    /// the table as `access` is allowed to see it. Every query result and export goes through this,
+    /// so sensitive columns are only ever shown in plaintext to admins.
+    ///
+    /// the rows keep their positions, so a row of the result is the same row of this table.
+    pub fn for_reader(&self, access: ReadAccess) -> Result<Cow<'_, Table>, DBError> {
+        if access == ReadAccess::Admin || !self.has_sensitive_columns() {
+            return Ok(Cow::Borrowed(self));
+        }
+        Ok(Cow::Owned(self.redact()?))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, collections::HashMap};
+
+    use crate::structures::{column::{Column, DataType, FieldValue, MaskingStrategy}, relation::table::Table};
+
+    use super::{masking_key, ReadAccess};
+
+    /// a table with a plain `id`, and a `card` and `email` masked with `card_mask` and `email_mask`
+    fn customers(card_mask: MaskingStrategy, email_mask: MaskingStrategy) -> Table {
+        let mut card = Column::new("card".to_owned(), DataType::String, false);
+        card.set_mask(Some(card_mask));
+        let mut email = Column::new("email".to_owned(), DataType::String, false);
+        email.set_mask(Some(email_mask));
+
+        let mut table = Table::new("masking test".to_owned(), vec![Column::new("id".to_owned(), DataType::Number, false), card, email], true);
+        for (id, card, email) in [(1.0, "4111222233334321", "a@x.com"), (2.0, "5500111122223333", "a@x.com")] {
+            table.insert_row(&HashMap::from([
+                ("id".to_owned(), FieldValue::Number(id)),
+                ("card".to_owned(), FieldValue::String(card.to_owned())),
+                ("email".to_owned(), FieldValue::String(email.to_owned())),
+            ])).unwrap();
+        }
+        table
+    }
+
+    fn text(value: &str) -> FieldValue { FieldValue::String(value.to_owned()) }
+
+    #[test]
+    fn sensitive_columns_are_masked_unless_read_as_an_admin() {
+        let table = customers(MaskingStrategy::Partial(4), MaskingStrategy::Fixed("hidden".to_owned()));
+        assert!(table.has_sensitive_columns());
+
+        let masked = table.for_reader(ReadAccess::Masked).unwrap();
+        assert_eq!(masked.rows()[0]["card"], text("************4321"));
+        assert_eq!(masked.rows()[0]["email"], text("hidden"));
+        assert_eq!(masked.rows()[1]["id"], FieldValue::Number(2.0));
+
+        let admin = table.for_reader(ReadAccess::Admin).unwrap();
+        assert!(matches!(admin, Cow::Borrowed(_)));
+        assert_eq!(admin.rows()[0]["card"], text("4111222233334321"));
+    }
+
+    #[test]
+    fn hashed_values_can_still_be_matched_up() {
+        let table = customers(MaskingStrategy::Partial(4), MaskingStrategy::Hash);
+        let masked = table.for_reader(ReadAccess::Masked).unwrap();
+
+        let first = &masked.rows()[0]["email"];
+        assert_eq!(first, &masked.rows()[1]["email"]);
+        assert_ne!(first, &text("a@x.com"));
+        assert_eq!(masking_key().unwrap(), masking_key().unwrap());
+    }
+
+    #[test]
+    fn tables_without_sensitive_columns_are_not_copied() {
+        let table = Table::new("masking test plain".to_owned(), vec![Column::new("id".to_owned(), DataType::Number, false)], true);
+        assert!(!table.has_sensitive_columns());
+        assert!(matches!(table.for_reader(ReadAccess::Masked).unwrap(), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::structures::{cancel::{check_cancelled, check_row_limit}, column::FieldValue, conflict::{MatchedAction, MergeSummary, NotMatchedAction}, db_err::DBError};
+
+use super::table::Table;
+
+
+impl Table {
+
+    /// syncs this table with `source` in a single pass, matching rows on `key_column`.
+    ///
+    /// for every row in `source`:
+    /// - if a row in this table has the same key, `when_matched` decides what happens to it
+    /// - otherwise, `when_not_matched` decides if the source row is inserted
+    ///
+    /// only the columns both tables share are copied over. This is the usual way to refresh
+    /// a table from a periodic extract of the same data.
+    pub fn merge_from(
+        &mut self,
+        source: &Table,
+        key_column: &String,
+        when_matched: MatchedAction,
+        when_not_matched: NotMatchedAction
+    ) -> Result<MergeSummary, DBError> {
+
+        if !self.is_valid_column(key_column) { return Err(DBError::InvalidColumn(key_column.to_string())) }
+        if !source.is_valid_column(key_column) { return Err(DBError::InvalidColumn(key_column.to_string())) }
+
+        let shared_columns: Vec<String> = source
+            .all_column_names()
+            .into_iter()
+            .filter(|c| self.is_valid_column(c))
+            .collect();
+
+        let columns_to_update: Vec<String> = match &when_matched {
+            MatchedAction::Update => shared_columns.iter().filter(|c| *c != key_column).cloned().collect(),
+            MatchedAction::UpdateColumns(columns) => {
+                for c in columns {
+                    if !shared_columns.contains(c) { return Err(DBError::InvalidColumn(c.to_string())) }
+                }
+                columns.clone()
+            },
+            MatchedAction::Nothing => Vec::new(),
+        };
+
+        // find the row each key is in
+        let mut target_rows: HashMap<String, usize> = HashMap::new();
+        for (row_index, row) in self.rows.iter().enumerate() {
+            let key = row.get(key_column).unwrap_or(&FieldValue::Null).to_key_string();
+            target_rows.entry(key).or_insert(row_index);
+        }
+
+        let mut summary = MergeSummary::default();
+
+        for source_row in source.rows() {
+            check_cancelled()?;
+            let key_value = source_row.get(key_column).unwrap_or(&FieldValue::Null);
+
+            match target_rows.get(&key_value.to_key_string()) {
+                Some(&row_index) => {
+                    if columns_to_update.is_empty() {
+                        summary.skipped += 1;
+                        continue;
+                    }
+                    let new_values: HashMap<String, FieldValue> = columns_to_update
+                        .iter()
+                        .map(|c| (c.clone(), source_row.get(c).cloned().unwrap_or(FieldValue::Null)))
+                        .collect();
+                    self.overwrite_row(row_index, &new_values)?;
+                    summary.updated += 1;
+                    check_row_limit((summary.updated + summary.inserted) as usize)?;
+                },
+                None => {
+                    if let NotMatchedAction::Nothing = when_not_matched {
+                        summary.skipped += 1;
+                        continue;
+                    }
+                    let new_row: HashMap<String, FieldValue> = shared_columns
+                        .iter()
+                        .map(|c| (c.clone(), source_row.get(c).cloned().unwrap_or(FieldValue::Null)))
+                        .collect();
+                    self.insert_row(&new_row)?;
+                    // later source rows with the same key should update this row, not insert it again
+                    target_rows.insert(key_value.to_key_string(), self.rows.len() - 1);
+                    summary.inserted += 1;
+                    check_row_limit((summary.updated + summary.inserted) as usize)?;
+                },
+            }
+        }
+
+        Ok(summary)
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::RELATION_PATH, structures::db_err::DBError};
+
+use super::{summary::summarize_all_tables, table::Table};
+
+
+/// the file in the relation directory which keeps the descriptions and tags of every table
+pub const METADATA_FILE_NAME: &str = "table_metadata.bin";
+
+
+/// a free-form description and `key=value` tags attached to a table or a column, i.e. `source=stripe`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Metadata {
+    pub description: Option<String>,
+    /// sorted by key, so they are always listed in the same order
+    pub tags: BTreeMap<String, String>,
+}
+
+
+/// the metadata of a table, along with the metadata of each of its columns
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TableMetadata {
+    pub table: Metadata,
+    /// keyed by column name. Columns without metadata aren't included
+    pub columns: HashMap<String, Metadata>,
+}
+
+
+impl Metadata {
+    fn is_empty(&self) -> bool { self.description.is_none() && self.tags.is_empty() }
+
+    /// the tags written as `key=value`, separated by commas
+    pub fn tags_text(&self) -> String {
+        self.tags.iter().map(|(key, value)| format!("{key}={value}")).collect::<Vec<String>>().join(", ")
+    }
+}
+
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description.as_deref().unwrap_or("(no description)"))?;
+        if !self.tags.is_empty() {
+            write!(f, " [{}]", self.tags_text())?;
+        }
+        Ok(())
+    }
+}
+
+
+fn metadata_file_path() -> String {
+    format!("{}/{}", RELATION_PATH, METADATA_FILE_NAME)
+}
+
+
+/// the metadata of every table, keyed by table name
+fn load_all_metadata() -> HashMap<String, TableMetadata> {
+    fs::read(metadata_file_path())
+        .ok()
+        .and_then(|data| bincode::deserialize(&data).ok())
+        .unwrap_or_default()
+}
+
+
+fn save_all_metadata(all_metadata: &HashMap<String, TableMetadata>) -> Result<(), DBError> {
+    let file_path = metadata_file_path();
+    let encoded_data = bincode::serialize(all_metadata).map_err(|_| DBError::DataBaseFileFailure(file_path.clone()))?;
+    fs::write(&file_path, encoded_data).map_err(|e| DBError::IOFailure(file_path, e.to_string()))
+}
+
+
+/// a tag key can't be empty or contain '=', since tags are written and searched as `key=value`
+fn validate_tag_key(key: &str) -> Result<(), DBError> {
+    if key.trim().is_empty() || key.contains('=') {
+        return Err(DBError::InvalidTag(key.to_string(), "tag names can't be empty or contain '='".to_string()));
+    }
+    Ok(())
+}
+
+
+impl Table {
+
+    /// the description and tags of the table and its columns
+    pub fn metadata(&self) -> TableMetadata {
+        metadata_of(&self.name)
+    }
+
+
+    /// changes the metadata of the table (or of `column_name`, if given) with `edit`, then saves it.
+    ///
+    /// the metadata is kept separately from the table, so it doesn't need to be saved again.
+    fn edit_metadata(&self, column_name: Option<&str>, edit: impl FnOnce(&mut Metadata)) -> Result<(), DBError> {
+        if let Some(column_name) = column_name {
+            if !self.is_valid_column(&column_name.to_string()) {
+                return Err(DBError::InvalidColumn(column_name.to_string()));
+            }
+        }
+
+        let mut all_metadata = load_all_metadata();
+        let table_metadata = all_metadata.entry(self.name.clone()).or_default();
+        match column_name {
+            Some(column_name) => {
+                let column_metadata = table_metadata.columns.entry(column_name.to_string()).or_default();
+                edit(column_metadata);
+                if column_metadata.is_empty() { table_metadata.columns.remove(column_name); }
+            },
+            None => edit(&mut table_metadata.table),
+        }
+
+        if table_metadata.table.is_empty() && table_metadata.columns.is_empty() {
+            all_metadata.remove(&self.name);
+        }
+        save_all_metadata(&all_metadata)
+    }
+
+
+    /// sets the description of the table, or of one of its columns if `column_name` is given. `None` removes it
+    pub fn set_description(&self, column_name: Option<&str>, description: Option<&str>) -> Result<(), DBError> {
+        self.edit_metadata(column_name, |metadata| metadata.description = description.map(|d| d.to_string()))
+    }
+
+
+    /// tags the table, or one of its columns if `column_name` is given, replacing any tag with the same key
+    pub fn set_tag(&self, column_name: Option<&str>, key: &str, value: &str) -> Result<(), DBError> {
+        validate_tag_key(key)?;
+        self.edit_metadata(column_name, |metadata| { metadata.tags.insert(key.trim().to_string(), value.trim().to_string()); })
+    }
+
+
+    /// removes the tag with the given key from the table, or from one of its columns if `column_name` is given
+    pub fn remove_tag(&self, column_name: Option<&str>, key: &str) -> Result<(), DBError> {
+        self.edit_metadata(column_name, |metadata| { metadata.tags.remove(key.trim()); })
+    }
+
+
+    /// describes the table's schema along with its metadata, one column per line
+    pub fn describe(&self) -> String {
+        let metadata = self.metadata();
+
+        let mut description = format!("{}: {}\n", self.name, metadata.table);
+        for column in &self.columns {
+            let key = if column.is_primary_key() { ", primary key" } else { "" };
+            description += &format!("  {} <{}{}>", column.get_name(), column.get_data_type(), key);
+            if let Some(column_metadata) = metadata.columns.get(column.get_name()) {
+                description += &format!(": {}", column_metadata);
+            }
+            description += "\n";
+        }
+        description
+    }
+}
+
+
+/// lists the saved tables tagged with `key`, sorted by name. If `value` is given, the tag must also have that value.
+///
+/// `tag` is written as `key` or `key=value`, i.e. `source=stripe`. Only the tables' own tags are checked, not their columns'.
+pub fn tables_with_tag(tag: &str) -> Result<Vec<String>, DBError> {
+    let (key, value) = match tag.split_once('=') {
+        Some((key, value)) => (key.trim(), Some(value.trim())),
+        None => (tag.trim(), None),
+    };
+    validate_tag_key(key)?;
+
+    let all_metadata = load_all_metadata();
+    let tables = summarize_all_tables()?
+        .into_iter()
+        .map(|summary| summary.name)
+        .filter(|name| {
+            let tag_value = all_metadata.get(name).and_then(|m| m.table.tags.get(key));
+            match (tag_value, value) {
+                (Some(tag_value), Some(value)) => tag_value == value,
+                (Some(_), None) => true,
+                (None, _) => false,
+            }
+        })
+        .collect();
+    Ok(tables)
+}
+
+
+/// the metadata of the table called `table_name`, without loading the table
+pub fn metadata_of(table_name: &str) -> TableMetadata {
+    load_all_metadata().remove(table_name).unwrap_or_default()
+}
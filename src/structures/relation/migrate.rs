@@ -0,0 +1,162 @@
+use std::{fmt, fs, io::Cursor};
+
+use crate::{config::RELATION_PATH, structures::db_err::DBError};
+
+use super::{format::{decode_table, read_file_header}, io::relation_file_path};
+
+
+/// the extension added to a legacy table file when it is kept as a backup by `migrate_table_file()`
+pub const LEGACY_BACKUP_EXTENSION: &str = "legacy";
+
+
+/// what `migrate_table_file()` did
+#[derive(Debug)]
+pub struct MigrationReport {
+    pub table_name: String,
+    /// false if the file was already in the current format, so it was only copied and re-indexed
+    pub converted: bool,
+    /// where the original file was copied to before it was replaced, if it was replaced
+    pub backup_path: Option<String>,
+    pub rows: usize,
+    /// the columns whose indexes were rebuilt
+    pub indexed_columns: Vec<String>,
+}
+
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = if self.converted { "migrated" } else { "was already in the current format, re-saved" };
+        write!(f, "'{}' {} ({} row(s))", self.table_name, action, self.rows)?;
+        if !self.indexed_columns.is_empty() {
+            write!(f, ", rebuilt the indexes on {}", self.indexed_columns.join(", "))?;
+        }
+        if let Some(backup_path) = &self.backup_path {
+            write!(f, ". The original file was kept at {}", backup_path)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// converts a table file saved before the file format was versioned into the current format, saves it into the
+/// relation directory, and rebuilds the indexes on its primary keys and unique columns.
+///
+/// a file which is already the table's file in the relation directory is replaced, after copying it to
+/// `{file}.legacy`. A file from anywhere else is saved into the relation directory, as long as no table
+/// with the same name is saved there already. Files already in the current format are saved and re-indexed the same way.
+pub fn migrate_table_file(file_path: &str) -> Result<MigrationReport, DBError> {
+    let bytes = fs::read(file_path).map_err(|e| DBError::IOFailure(file_path.to_owned(), e.to_string()))?;
+    let is_legacy = read_file_header(&mut Cursor::new(&bytes), file_path, bytes.len() as u64)?.is_none();
+    let mut table = decode_table(&bytes, file_path)?;
+    table.refresh_computed_columns()?;
+
+    let destination = relation_file_path(RELATION_PATH, &table.to_file_name());
+    let replaces_source = match (fs::canonicalize(file_path), fs::canonicalize(&destination)) {
+        (Ok(source), Ok(destination)) => source == destination,
+        _ => false,
+    };
+
+    if !replaces_source && fs::metadata(&destination).is_ok() {
+        return Err(DBError::IOFailure(
+            destination,
+            format!("a table named '{}' is already saved, move it before migrating {}", table.name(), file_path)
+        ));
+    }
+
+    let backup_path = if replaces_source && is_legacy {
+        let backup_path = format!("{}.{}", file_path, LEGACY_BACKUP_EXTENSION);
+        fs::copy(file_path, &backup_path).map_err(|e| DBError::IOFailure(backup_path.clone(), e.to_string()))?;
+        Some(backup_path)
+    } else {
+        None
+    };
+
+    // the saved file decides the generation from here on, see `Table::save()`
+    if !replaces_source {
+        table.generation = 0;
+    }
+    table.save(RELATION_PATH.to_owned())?;
+
+    let mut indexed_columns: Vec<String> = Vec::new();
+    for column in table.constrained_columns() {
+        table.index_column(column.get_name().to_string())?;
+        indexed_columns.push(column.get_name().to_string());
+    }
+
+    Ok(MigrationReport {
+        table_name: table.name().to_string(),
+        converted: is_legacy,
+        backup_path,
+        rows: table.rows().len(),
+        indexed_columns,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use crate::{config::{INDEX_PATH, RELATION_PATH, TEMP_PATH}, structures::{column::{DataType, FieldValue}, relation::io::{index_file_name, load_database, relation_file_path, relation_lock_file_name}}};
+
+    use super::{migrate_table_file, LEGACY_BACKUP_EXTENSION};
+
+    /// writes a table keyed by `id` to `file_path` the way it was saved before the file format was versioned:
+    /// the name, columns, primary keys and rows, without a header
+    fn write_legacy_table(file_path: &str, table_name: &str, ids: &[f64]) {
+        let id_column = ("id".to_owned(), DataType::Number, true);
+        let rows: Vec<HashMap<String, FieldValue>> = ids.iter().map(|id| HashMap::from([("id".to_owned(), FieldValue::Number(*id))])).collect();
+        let encoded_data = bincode::serialize(&(table_name, vec![id_column.clone()], vec![id_column], rows)).unwrap();
+        fs::write(file_path, encoded_data).unwrap();
+    }
+
+    fn remove_table(table_name: &str) {
+        let file_path = relation_file_path(RELATION_PATH, table_name);
+        let _ = fs::remove_file(format!("{}.{}", file_path, LEGACY_BACKUP_EXTENSION));
+        let _ = fs::remove_file(file_path);
+        let _ = fs::remove_file(format!("{}/{}", RELATION_PATH, relation_lock_file_name(table_name)));
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(table_name, "id")));
+    }
+
+    #[test]
+    fn legacy_files_are_converted_into_the_relation_directory() {
+        let table_name = "migrate_test_import";
+        let source = format!("{}/{}.bin", TEMP_PATH, table_name);
+        write_legacy_table(&source, table_name, &[1.0, 2.0, 3.0]);
+
+        let report = migrate_table_file(&source).unwrap();
+        assert!(report.converted);
+        assert_eq!(report.backup_path, None);
+        assert_eq!(report.rows, 3);
+        assert_eq!(report.indexed_columns, vec!["id"]);
+
+        let migrated = load_database(&relation_file_path(RELATION_PATH, table_name)).unwrap();
+        assert_eq!(migrated.rows().len(), 3);
+        assert!(migrated.index_available("id", INDEX_PATH));
+
+        // the table is saved already, so it isn't overwritten by a second copy
+        assert!(migrate_table_file(&source).is_err());
+
+        let _ = fs::remove_file(source);
+        remove_table(table_name);
+    }
+
+    #[test]
+    fn a_legacy_file_in_the_relation_directory_is_replaced_after_a_backup() {
+        let table_name = "migrate_test_in_place";
+        let file_path = relation_file_path(RELATION_PATH, table_name);
+        write_legacy_table(&file_path, table_name, &[1.0]);
+
+        let report = migrate_table_file(&file_path).unwrap();
+        assert!(report.converted);
+        let backup_path = report.backup_path.unwrap();
+        assert!(fs::metadata(&backup_path).is_ok());
+
+        // migrating again only re-saves it, since it is in the current format now
+        let report = migrate_table_file(&file_path).unwrap();
+        assert!(!report.converted);
+        assert_eq!(report.backup_path, None);
+
+        remove_table(table_name);
+    }
+}
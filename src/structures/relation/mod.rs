@@ -1,9 +1,44 @@
 pub mod table;
 pub mod filter;
+pub mod filter_expr;
 pub mod join;
 pub mod sort;
 pub mod crud;
 pub mod io;
+pub mod format;
+pub mod migrate;
 pub mod search; // TODO: fill search file
 pub mod display;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod soft_delete;
+pub mod versioning;
+pub mod aggregate;
+pub mod spill;
+pub mod merge;
+pub mod summary;
+pub mod dump;
+pub mod update;
+pub mod computed;
+pub mod dedupe;
+pub mod profile;
+pub mod validation;
+pub mod ttl;
+pub mod timeseries;
+pub mod diff;
+pub mod index_stats;
+pub mod integrity;
+pub mod system;
+pub mod metadata;
+pub mod find;
+pub mod orphans;
+pub mod layout;
+pub mod annotations;
+pub mod lineage;
+pub mod temporary;
+pub mod unique;
+pub mod usage;
+pub mod package;
+pub mod masking;
+pub mod generate;
+pub mod benchmark;
+pub mod changes;
\ No newline at end of file
@@ -0,0 +1,195 @@
+use std::{collections::BTreeSet, fmt};
+
+use chrono::Utc;
+
+use crate::{config::RELATION_PATH, structures::{cancel::check_cancelled, column::FieldValue, db_err::DBError}};
+
+use super::{io::{load_database, relation_file_path}, table::Table};
+
+
+/// a relationship between two tables, where every value of `child_column` should be a value of `parent_column`.
+/// i.e. every `orders.customer_id` should be the `id` of a row in `customers`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    pub child_table: String,
+    pub child_column: String,
+    pub parent_table: String,
+    pub parent_column: String,
+}
+
+
+impl ForeignKey {
+
+    pub fn new(child_table: &str, child_column: &str, parent_table: &str, parent_column: &str) -> Self {
+        ForeignKey {
+            child_table: child_table.to_string(),
+            child_column: child_column.to_string(),
+            parent_table: parent_table.to_string(),
+            parent_column: parent_column.to_string(),
+        }
+    }
+
+
+    /// parses a foreign key written as `child_table.child_column -> parent_table.parent_column`
+    pub fn parse_str(str: &str) -> Option<ForeignKey> {
+        let (child, parent) = str.split_once("->")?;
+        let (child_table, child_column) = child.trim().rsplit_once('.')?;
+        let (parent_table, parent_column) = parent.trim().rsplit_once('.')?;
+
+        let parts = [child_table, child_column, parent_table, parent_column];
+        if parts.iter().any(|part| part.trim().is_empty()) { return None }
+
+        Some(ForeignKey::new(child_table.trim(), child_column.trim(), parent_table.trim(), parent_column.trim()))
+    }
+}
+
+
+impl fmt::Display for ForeignKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} -> {}.{}", self.child_table, self.child_column, self.parent_table, self.parent_column)
+    }
+}
+
+
+/// the rows of a child table which don't have a parent, found by `find_orphans()`
+#[derive(Debug, Clone)]
+pub struct OrphanCheck {
+    pub foreign_key: ForeignKey,
+    /// the number of child rows with a value in the child column
+    pub rows_checked: usize,
+    /// the number of child rows without a value, which aren't counted as orphans
+    pub null_rows: usize,
+    /// the positions of the child rows whose value isn't in the parent column
+    pub orphan_rows: Vec<usize>,
+    /// every value missing from the parent column, sorted
+    pub missing_values: Vec<FieldValue>,
+}
+
+
+impl OrphanCheck {
+    /// the percent of checked rows which have a parent
+    pub fn match_rate(&self) -> f64 {
+        if self.rows_checked == 0 { return 100.0 }
+        100.0 * (self.rows_checked - self.orphan_rows.len()) as f64 / self.rows_checked as f64
+    }
+}
+
+
+impl fmt::Display for OrphanCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{} | {} of {} row(s) orphaned ({:.1}% matched), {} null",
+            self.foreign_key, self.orphan_rows.len(), self.rows_checked, self.match_rate(), self.null_rows
+        )?;
+        if !self.missing_values.is_empty() {
+            let shown: Vec<String> = self.missing_values.iter().take(MISSING_VALUES_SHOWN).map(|v| v.to_string()).collect();
+            let more = self.missing_values.len().saturating_sub(MISSING_VALUES_SHOWN);
+            write!(f, " | missing: {}", shown.join(", "))?;
+            if more > 0 { write!(f, " and {} more", more)?; }
+        }
+        Ok(())
+    }
+}
+
+
+/// how many missing values are listed when a check is displayed
+const MISSING_VALUES_SHOWN: usize = 5;
+
+
+/// the result of `find_orphans()`, with one check per foreign key
+#[derive(Debug, Clone)]
+pub struct OrphanReport {
+    pub checks: Vec<OrphanCheck>,
+}
+
+
+impl OrphanReport {
+
+    pub fn total_orphans(&self) -> usize {
+        self.checks.iter().map(|c| c.orphan_rows.len()).sum()
+    }
+
+    /// true if every child row has a parent
+    pub fn is_clean(&self) -> bool { self.total_orphans() == 0 }
+}
+
+
+impl fmt::Display for OrphanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "checked {} relationship(s), found {} orphaned row(s)", self.checks.len(), self.total_orphans())?;
+        for check in &self.checks {
+            writeln!(f, "  {}", check)?;
+        }
+        Ok(())
+    }
+}
+
+
+fn load_saved_table(table_name: &str) -> Result<Table, DBError> {
+    load_database(&relation_file_path(RELATION_PATH, table_name))
+}
+
+
+/// checks each foreign key, finding the rows of the child table whose value isn't in the parent table.
+/// Nothing is enforced or changed, so this can be run on imported data before deciding how to clean it up.
+///
+/// null child values are counted separately, since they don't point at any parent.
+/// Deleted and expired rows are skipped in both tables.
+pub fn find_orphans(foreign_keys: &[ForeignKey]) -> Result<OrphanReport, DBError> {
+    let mut checks: Vec<OrphanCheck> = Vec::new();
+
+    for foreign_key in foreign_keys {
+        let child = load_saved_table(&foreign_key.child_table)?;
+        let parent = load_saved_table(&foreign_key.parent_table)?;
+        checks.push( child.orphans_of(&parent, foreign_key)? );
+    }
+
+    Ok(OrphanReport { checks })
+}
+
+
+impl Table {
+
+    /// the rows of this table whose value in `foreign_key.child_column` isn't in `foreign_key.parent_column` of `parent`
+    pub fn orphans_of(&self, parent: &Table, foreign_key: &ForeignKey) -> Result<OrphanCheck, DBError> {
+        if !self.is_valid_column(&foreign_key.child_column) {
+            return Err(DBError::InvalidColumn(foreign_key.child_column.clone()));
+        }
+        if !parent.is_valid_column(&foreign_key.parent_column) {
+            return Err(DBError::InvalidColumn(foreign_key.parent_column.clone()));
+        }
+
+        let now = Utc::now();
+        let mut parent_values: BTreeSet<&FieldValue> = BTreeSet::new();
+        for row in &parent.rows {
+            check_cancelled()?;
+            if !parent.is_live(row, now) { continue }
+            if let Some(value) = row.get(&foreign_key.parent_column) {
+                parent_values.insert(value);
+            }
+        }
+
+        let mut check = OrphanCheck { foreign_key: foreign_key.clone(), rows_checked: 0, null_rows: 0, orphan_rows: Vec::new(), missing_values: Vec::new() };
+        let mut missing_values: BTreeSet<FieldValue> = BTreeSet::new();
+
+        for (row_index, row) in self.rows.iter().enumerate() {
+            check_cancelled()?;
+            if !self.is_live(row, now) { continue }
+
+            let value = row.get(&foreign_key.child_column).unwrap_or(&FieldValue::Null);
+            if value.eq(&FieldValue::Null) {
+                check.null_rows += 1;
+                continue;
+            }
+
+            check.rows_checked += 1;
+            if !parent_values.contains(value) {
+                check.orphan_rows.push(row_index);
+                missing_values.insert(value.clone());
+            }
+        }
+
+        check.missing_values = missing_values.into_iter().collect();
+        Ok(check)
+    }
+}
@@ -0,0 +1,208 @@
+use std::{collections::BTreeMap, fs::File, io::{BufReader, BufWriter, Read, Write}};
+
+use bincode::Options;
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::INDEX_PATH, structures::{column::FieldValue, db_err::DBError}};
+
+use super::{masking::ReadAccess, table::Table};
+
+
+/// the extension given to package files, i.e. "orders.sqlpkg"
+pub const PACKAGE_EXTENSION: &str = "sqlpkg";
+
+/// every package file starts with these bytes, so other files are rejected before trying to decode them
+const PACKAGE_MAGIC: &[u8; 8] = b"SEQLPKG1";
+
+/// bumped whenever the layout of a package changes. Written right after `PACKAGE_MAGIC`, so packages from a
+/// newer version are rejected before anything is decoded
+pub const PACKAGE_FORMAT_VERSION: u32 = 2;
+
+/// the most bytes `Table::open_package()` decodes from a package, so a damaged or malicious package
+/// can't claim a huge length and run out of memory. See `Table::open_package_with_limit()`
+pub const DEFAULT_PACKAGE_SIZE_LIMIT: u64 = 1 << 30;
+
+
+/// an index saved in a package, keyed by column name
+type PackagedIndexes = BTreeMap<String, BTreeMap<FieldValue, Vec<usize>>>;
+
+
+#[derive(Serialize, Deserialize)]
+struct PackageContents {
+    packaged_at: DateTime<Utc>,
+    table: Table,
+    indexes: PackagedIndexes,
+}
+
+
+/// a table opened from a package made by `Table::package()`.
+///
+/// it can be read like any saved table, but only through a shared reference, so it can't be changed or saved.
+/// Use `PackagedTable::to_table()` to get a copy which can be.
+pub struct PackagedTable {
+    table: Table,
+    indexes: PackagedIndexes,
+    packaged_at: DateTime<Utc>,
+}
+
+
+impl PackagedTable {
+
+    pub fn table(&self) -> &Table { &self.table }
+
+    pub fn packaged_at(&self) -> DateTime<Utc> { self.packaged_at }
+
+    /// the columns which were indexed when the table was packaged
+    pub fn indexed_columns(&self) -> Vec<&String> { self.indexes.keys().collect() }
+
+    /// the positions of the rows holding `value` in `column_name`, found using the packaged index.
+    /// Returns `None` if the column wasn't indexed
+    pub fn rows_with_value(&self, column_name: &str, value: &FieldValue) -> Option<Vec<usize>> {
+        let index = self.indexes.get(column_name)?;
+        Some(index.get(value).cloned().unwrap_or_default())
+    }
+
+    /// a copy of the packaged table which can be changed, i.e. to save it into this database
+    pub fn to_table(&self) -> Table { self.table.clone() }
+}
+
+
+impl Table {
+
+    /// writes the table, its schema and the indexes on its columns into one compressed file at `path`,
+    /// which any Sequel installation can read with `Table::open_package()` without importing it.
+    ///
+    /// the package is a snapshot: later changes to the table don't change it. Sensitive columns are masked
+    /// unless `access` is `ReadAccess::Admin`, since whoever opens the package reads it as it was written.
+    /// The primary keys, unique columns and any other indexed columns are indexed from the packaged rows.
+    pub fn package(&self, path: &str, access: ReadAccess) -> Result<(), DBError> {
+        let readable = self.for_reader(access)?;
+
+        let constrained_columns = self.constrained_columns();
+        let mut indexes: PackagedIndexes = BTreeMap::new();
+        for column in readable.columns() {
+            let column_name = column.get_name();
+            let is_constrained = constrained_columns.iter().any(|c| c.get_name() == column_name);
+            if !is_constrained && !self.index_available(column_name, INDEX_PATH) { continue }
+            indexes.insert(column_name.to_string(), readable.index_in_memory(column_name, None)?);
+        }
+
+        // virtual columns are never saved, they are computed again when the package is opened
+        let table = readable.without_virtual_values().unwrap_or_else(|| readable.into_owned());
+        let contents = PackageContents { packaged_at: Utc::now(), table, indexes };
+
+        let file = File::create(path).map_err(|e| DBError::IOFailure(path.to_owned(), e.to_string()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(PACKAGE_MAGIC).map_err(|e| DBError::IOFailure(path.to_owned(), e.to_string()))?;
+        writer.write_all(&PACKAGE_FORMAT_VERSION.to_le_bytes()).map_err(|e| DBError::IOFailure(path.to_owned(), e.to_string()))?;
+
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        bincode::DefaultOptions::new()
+            .serialize_into(&mut encoder, &contents)
+            .map_err(|e| DBError::IOFailure(path.to_owned(), e.to_string()))?;
+        encoder
+            .finish()
+            .and_then(|mut writer| writer.flush())
+            .map_err(|e| DBError::IOFailure(path.to_owned(), e.to_string()))
+    }
+
+
+    /// opens a package made by `Table::package()`. Nothing is added to this database, see `PackagedTable`.
+    ///
+    /// at most `DEFAULT_PACKAGE_SIZE_LIMIT` bytes are decoded, use `Table::open_package_with_limit()` for larger packages.
+    pub fn open_package(path: &str) -> Result<PackagedTable, DBError> {
+        Table::open_package_with_limit(path, DEFAULT_PACKAGE_SIZE_LIMIT)
+    }
+
+
+    /// same as `Table::open_package()`, but fails if the package holds more than `max_bytes` once decompressed.
+    pub fn open_package_with_limit(path: &str, max_bytes: u64) -> Result<PackagedTable, DBError> {
+        let file = File::open(path).map_err(|e| DBError::IOFailure(path.to_owned(), e.to_string()))?;
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        if reader.read_exact(&mut magic).is_err() || &magic != PACKAGE_MAGIC {
+            return Err(DBError::IOFailure(path.to_owned(), "not a sequel package file".to_owned()));
+        }
+
+        let mut format_version = [0u8; 4];
+        reader.read_exact(&mut format_version).map_err(|_| DBError::IOFailure(path.to_owned(), "corrupted package file".to_owned()))?;
+        let format_version = u32::from_le_bytes(format_version);
+        if format_version != PACKAGE_FORMAT_VERSION {
+            return Err(DBError::IOFailure(
+                path.to_owned(),
+                format!("unsupported package format version {} (expected {})", format_version, PACKAGE_FORMAT_VERSION)
+            ));
+        }
+
+        let contents: PackageContents = bincode::DefaultOptions::new()
+            .with_limit(max_bytes)
+            .deserialize_from(GzDecoder::new(reader))
+            .map_err(|_| DBError::IOFailure(path.to_owned(), "corrupted package file, or larger than the size limit".to_owned()))?;
+
+        let mut table = contents.table;
+        table.refresh_computed_columns()?;
+        Ok(PackagedTable { table, indexes: contents.indexes, packaged_at: contents.packaged_at })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use crate::{config::{INDEX_PATH, TEMP_PATH}, structures::{column::{Column, DataType, FieldValue, MaskingStrategy}, relation::{io::index_file_name, masking::ReadAccess, table::Table}}};
+
+    /// a table keyed by `id`, with a `secret` column which is masked for everyone except admins
+    fn secrets_table(table_name: &str) -> Table {
+        let mut secret = Column::new("secret".to_owned(), DataType::String, false);
+        secret.set_mask(Some(MaskingStrategy::Fixed("***".to_owned())));
+        let mut table = Table::new(table_name.to_owned(), vec![Column::new("id".to_owned(), DataType::Number, true), secret], false);
+        for id in 0..3 {
+            table.insert_row(&HashMap::from([
+                ("id".to_owned(), FieldValue::Number(id as f64)),
+                ("secret".to_owned(), FieldValue::String(format!("secret {id}"))),
+            ])).unwrap();
+        }
+        table
+    }
+
+    fn package_path(name: &str) -> String { format!("{}/{}.{}", TEMP_PATH, name, super::PACKAGE_EXTENSION) }
+
+    #[test]
+    fn packages_open_with_their_rows_and_indexes() {
+        let table = secrets_table("package_test_open");
+        let path = package_path("package_test_open");
+        table.package(&path, ReadAccess::Masked).unwrap();
+
+        let packaged = Table::open_package(&path).unwrap();
+        assert_eq!(packaged.table().rows().len(), 3);
+        assert_eq!(packaged.table().rows()[1]["secret"], FieldValue::String("***".to_owned()));
+        assert_eq!(packaged.indexed_columns(), vec!["id"]);
+        assert_eq!(packaged.rows_with_value("id", &FieldValue::Number(2.0)), Some(vec![2]));
+        assert_eq!(packaged.rows_with_value("secret", &FieldValue::Null), None);
+
+        table.package(&path, ReadAccess::Admin).unwrap();
+        let packaged = Table::open_package(&path).unwrap();
+        assert_eq!(packaged.to_table().rows()[1]["secret"], FieldValue::String("secret 1".to_owned()));
+
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name("package_test_open", "id")));
+    }
+
+    #[test]
+    fn other_files_and_oversized_packages_are_refused() {
+        let path = package_path("package_test_refused");
+        fs::write(&path, b"not a package at all").unwrap();
+        assert!(Table::open_package(&path).is_err());
+
+        secrets_table("package_test_refused").package(&path, ReadAccess::Masked).unwrap();
+        assert!(Table::open_package_with_limit(&path, 16).is_err());
+        assert!(Table::open_package(&path).is_ok());
+
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name("package_test_refused", "id")));
+    }
+}
@@ -0,0 +1,147 @@
+use core::fmt;
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use comfy_table::presets::ASCII_MARKDOWN;
+
+use crate::structures::{cancel::check_cancelled, column::{parse_into_field_value, DataType, FieldValue}, db_err::DBError};
+
+use super::table::Table;
+
+
+/// how many suspicious values are listed for each column
+const MAX_SUSPICIOUS_VALUES: usize = 5;
+
+
+/// an overview of the data in each column of a table, used to spot mistakes after an import
+#[derive(Debug, Clone)]
+pub struct TableProfile {
+    pub table_name: String,
+    pub rows: usize,
+    pub columns: Vec<ColumnProfile>,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct ColumnProfile {
+    pub name: String,
+    pub declared_type: DataType,
+    /// the datatype most of the values look like, going by their text
+    pub inferred_type: Option<DataType>,
+    /// the fraction of non-null values which look like the declared datatype, from 0 to 1
+    pub type_confidence: f64,
+    pub null_percent: f64,
+    /// the percent of non-null values which are unique
+    pub distinct_percent: f64,
+    pub min: Option<FieldValue>,
+    pub max: Option<FieldValue>,
+    /// a few values which don't look like the rest of the column, i.e. "N/A" in a column of numbers
+    pub suspicious_values: Vec<String>,
+}
+
+
+impl Table {
+
+    /// builds a `TableProfile` of the table, with statistics about each column
+    pub fn profile(&self) -> Result<TableProfile, DBError> {
+
+        let now = Utc::now();
+        let rows: Vec<&HashMap<String, FieldValue>> = self.rows
+            .iter()
+            .filter(|r| self.is_live(r, now))
+            .collect();
+
+        let mut column_profiles: Vec<ColumnProfile> = Vec::new();
+
+        for column in &self.columns {
+            check_cancelled()?;
+            let values: Vec<&FieldValue> = rows
+                .iter()
+                .map(|r| r.get(column.get_name()).unwrap_or(&FieldValue::Null))
+                .filter(|v| !v.eq(&&FieldValue::Null))
+                .collect();
+
+            let null_count = rows.len() - values.len();
+
+            // work out what each value would have been parsed as, if its type wasn't given
+            let inferred_types: Vec<DataType> = values
+                .iter()
+                .map(|v| parse_into_field_value(&v.to_string()).data_type())
+                .collect();
+            let mut type_counts: HashMap<String, (DataType, usize)> = HashMap::new();
+            for dt in &inferred_types {
+                type_counts.entry(dt.to_string()).or_insert((dt.clone(), 0)).1 += 1;
+            }
+            let inferred_type = type_counts
+                .values()
+                .max_by_key(|(_, count)| *count)
+                .map(|(dt, _)| dt.clone());
+
+            let matching_declared_type = inferred_types.iter().filter(|dt| *dt == column.get_data_type()).count();
+
+            let mut suspicious_values: Vec<String> = Vec::new();
+            if let Some(majority_type) = &inferred_type {
+                for (value, dt) in values.iter().zip(inferred_types.iter()) {
+                    if suspicious_values.len() >= MAX_SUSPICIOUS_VALUES { break }
+                    let value = value.to_string();
+                    if dt != majority_type && !suspicious_values.contains(&value) {
+                        suspicious_values.push(value);
+                    }
+                }
+            }
+
+            let distinct_values: HashSet<String> = values.iter().map(|v| v.to_key_string()).collect();
+
+            let min = values.iter().copied().min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).cloned();
+            let max = values.iter().copied().max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).cloned();
+
+            column_profiles.push(ColumnProfile {
+                name: column.get_name().to_string(),
+                declared_type: column.get_data_type().clone(),
+                inferred_type,
+                type_confidence: fraction(matching_declared_type, values.len()),
+                null_percent: fraction(null_count, rows.len()) * 100.0,
+                distinct_percent: fraction(distinct_values.len(), values.len()) * 100.0,
+                min,
+                max,
+                suspicious_values,
+            });
+        }
+
+        Ok(TableProfile { table_name: self.name.clone(), rows: rows.len(), columns: column_profiles })
+    }
+}
+
+
+/// `part / whole`, or 0 if there is nothing to divide
+fn fraction(part: usize, whole: usize) -> f64 {
+    if whole == 0 { return 0.0 }
+    part as f64 / whole as f64
+}
+
+
+impl fmt::Display for TableProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text_table = comfy_table::Table::new();
+        text_table.set_header(vec!["column", "type", "looks like", "confidence", "null %", "distinct %", "min", "max", "suspicious values"]);
+
+        let optional = |v: &Option<FieldValue>| v.as_ref().map(|v| v.to_string()).unwrap_or("-".to_owned());
+
+        for c in &self.columns {
+            text_table.add_row(vec![
+                c.name.clone(),
+                c.declared_type.to_string(),
+                c.inferred_type.as_ref().map(|dt| dt.to_string()).unwrap_or("-".to_owned()),
+                format!("{:.0}%", c.type_confidence * 100.0),
+                format!("{:.1}", c.null_percent),
+                format!("{:.1}", c.distinct_percent),
+                optional(&c.min),
+                optional(&c.max),
+                c.suspicious_values.join(", "),
+            ]);
+        }
+        text_table.load_preset(ASCII_MARKDOWN).remove_style(comfy_table::TableComponent::HorizontalLines);
+
+        write!(f, "profile of '{}' ({} rows)\n{}", self.table_name, self.rows, text_table)
+    }
+}
@@ -1,47 +1,66 @@
 use chrono::DateTime;
 
-use crate::structures::{column::FieldValue, db_err::DBError, filter::{FilterCondition, FilterConditionValue}};
+use crate::structures::{column::{DataType, FieldValue}, db_err::DBError, filter::{FilterCondition, FilterConditionValue}};
 
 
 
 pub fn non_index_row_matches_search_critieria(row_value: &FieldValue, search_criteria: &FilterCondition) 
 -> Result<bool, DBError> {
 
+    /// compares the value in the row against the target of the condition. Null cells never match.
     fn check_against_condition(
+        row_value: &FieldValue,
         condition_value: &FilterConditionValue, 
-        op: fn(&FilterConditionValue, f64) -> bool 
+        op: fn(f64, f64) -> bool 
     ) 
     -> Result<bool, DBError> {
-        match condition_value {
-            FilterConditionValue::Number(condition_target) => { Ok( op( condition_value, *condition_target ) ) }
+        let condition_target = match condition_value {
+            FilterConditionValue::Number(condition_target) => *condition_target,
             _ => return Err(DBError::MisMatchConditionDataType(FilterConditionValue::Number(0.0), condition_value.clone()))
+        };
+        match row_value {
+            FieldValue::Number(n) => Ok( op( *n, condition_target ) ),
+            FieldValue::Null => Ok(false),
+            other => Err(DBError::MisMatchDataType(DataType::Number, other.data_type()))
         }
-
     } 
 
+    /// compares the value in the row against a number, text or date. Null cells never match.
+    fn is_equal_to_condition(row_value: &FieldValue, condition_value: &FilterConditionValue) -> Result<Option<bool>, DBError> {
+        match (row_value, condition_value) {
+            (FieldValue::Null, _) => Ok(None),
+            (_, FilterConditionValue::Number(_)) => check_against_condition(row_value, condition_value, |v1, v2| v1 == v2).map(Some),
+            (_, FilterConditionValue::String(text)) => Ok(Some( row_value.to_string() == *text )),
+            (FieldValue::Date(date), FilterConditionValue::Date(target)) => Ok(Some( date == target )),
+            (other, FilterConditionValue::Date(_)) => Err(DBError::MisMatchDataType(DataType::Date, other.data_type())),
+            _ => Err(DBError::MisMatchConditionDataType(FilterConditionValue::Number(0.0), condition_value.clone())),
+        }
+    }
+
     match &search_criteria {
         // check if the condition is a relational operator (i.e. >, >=, ==, !=, <, <=)
         FilterCondition::LessThan(condition_value) =>
-            check_against_condition(condition_value, |v1, v2| v1.number().unwrap() < v2),
+            check_against_condition(row_value, condition_value, |v1, v2| v1 < v2),
         FilterCondition::LessThanOrEqualTo(condition_value) =>
-            check_against_condition(condition_value, |v1, v2| v1.number().unwrap() <= v2),
+            check_against_condition(row_value, condition_value, |v1, v2| v1 <= v2),
         FilterCondition::GreaterThan(condition_value) =>
-            check_against_condition(condition_value, |v1, v2| v1.number().unwrap() > v2),
+            check_against_condition(row_value, condition_value, |v1, v2| v1 > v2),
         FilterCondition::GreaterThanOrEqualTo(condition_value) =>
-            check_against_condition(condition_value, |v1, v2| v1.number().unwrap() >= v2),
+            check_against_condition(row_value, condition_value, |v1, v2| v1 >= v2),
         FilterCondition::Equal(condition_value) => 
-            check_against_condition(condition_value, |v1, v2| v1.number().unwrap() == v2),
+            Ok( is_equal_to_condition(row_value, condition_value)? == Some(true) ),
         FilterCondition::NotEqual(condition_value) =>
-            check_against_condition(condition_value, |v1, v2| v1.number().unwrap() != v2),
+            Ok( is_equal_to_condition(row_value, condition_value)? == Some(false) ),
         FilterCondition::NumberBetween(condition_value) => {
             // make sure the target value is a range so we can see if the cell value is in a range
             match &condition_value { 
                 FilterConditionValue::NumberRange(lower_bound, upper_bound) => {
-                    Ok(FieldValue::Number(*lower_bound).is_less_than(row_value)? 
-                    && FieldValue::Number(*upper_bound).is_greater_than(row_value)?)
+                    // both bounds are included, the same as an index lookup
+                    Ok(!FieldValue::Number(*lower_bound).is_greater_than(row_value)? 
+                    && !FieldValue::Number(*upper_bound).is_less_than(row_value)?)
                 },
                     _ => return Err(DBError::MisMatchConditionDataType(
-                    FilterConditionValue::DateRange(DateTime::default(), DateTime::default()), condition_value.clone()
+                    FilterConditionValue::NumberRange(0.0, 0.0), condition_value.clone()
                 )) 
             }
         },
@@ -49,11 +68,12 @@ pub fn non_index_row_matches_search_critieria(row_value: &FieldValue, search_cri
             // make sure the target value is a range so we can see if the cell value is in a range
             match &condition_value { 
                 FilterConditionValue::DateRange(lower_bound, upper_bound) => {
-                    Ok(FieldValue::Date(*lower_bound).is_less_than(row_value)? 
-                    && FieldValue::Date(*upper_bound).is_greater_than(row_value)?)
+                    // both bounds are included, the same as an index lookup
+                    Ok(!FieldValue::Date(*lower_bound).is_greater_than(row_value)? 
+                    && !FieldValue::Date(*upper_bound).is_less_than(row_value)?)
                 },
                     _ => return Err(DBError::MisMatchConditionDataType(
-                    FilterConditionValue::NumberRange(0.0, 0.0), condition_value.clone()
+                    FilterConditionValue::DateRange(DateTime::default(), DateTime::default()), condition_value.clone()
                 )) 
             }
         },
@@ -61,5 +81,44 @@ pub fn non_index_row_matches_search_critieria(row_value: &FieldValue, search_cri
         FilterCondition::False                => Ok( row_value.eq( &FieldValue::Boolean(false) )),
         FilterCondition::Null                 => Ok( row_value.eq(&FieldValue::Null)),
         FilterCondition::NotNull              => Ok(!row_value.eq(&FieldValue::Null)),
+        FilterCondition::SimilarTo(text, threshold) 
+                                              => Ok( !row_value.eq(&FieldValue::Null) && similarity(&row_value.to_string(), text) >= *threshold ),
+        #[cfg(feature = "regex")]
+        FilterCondition::Matches(pattern)     => Ok( !row_value.eq(&FieldValue::Null) && pattern.is_match(&row_value.to_string()) ),
+    }
+}
+
+
+
+/// how alike two strings are, from 0 (nothing in common) to 1 (the same), ignoring case.
+///
+/// uses the levenshtein distance, scaled by the length of the longer string
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    let longest = a.len().max(b.len());
+    if longest == 0 { return 1.0 }
+
+    1.0 - (levenshtein_distance(&a, &b) as f64 / longest as f64)
+}
+
+
+/// the minimum number of single character insertions, deletions, or substitutions to turn `a` into `b`
+pub fn levenshtein_distance(a: &[char], b: &[char]) -> usize {
+    // only the previous row of the distance matrix is needed
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    let mut current_row: Vec<usize> = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.iter().enumerate() {
+        current_row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution_cost = if a_char == b_char { 0 } else { 1 };
+            current_row[j + 1] = (previous_row[j] + substitution_cost)
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1);
+        }
+        std::mem::swap(&mut previous_row, &mut current_row);
     }
+    previous_row[b.len()]
 }
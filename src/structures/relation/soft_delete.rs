@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::structures::{column::{Column, DataType, FieldValue}, db_err::DBError, filter::FilterCondition};
+
+use super::table::Table;
+
+
+/// the column which records when a row was inserted into a table with soft deletes enabled
+pub const INSERTED_AT_COLUMN: &str = "Inserted At";
+
+/// the column which records when a row was deleted from a table with soft deletes enabled.
+/// rows which haven't been deleted have a `Null` value in this column
+pub const DELETED_AT_COLUMN: &str = "Deleted At";
+
+
+/// determines if a row has been marked as deleted
+pub fn is_tombstoned(row: &HashMap<String, FieldValue>) -> bool {
+    match row.get(DELETED_AT_COLUMN) {
+        Some(deleted_at) => !deleted_at.eq(&FieldValue::Null),
+        None => false
+    }
+}
+
+
+impl Table {
+
+    pub fn soft_delete_enabled(&self) -> bool { self.soft_delete }
+
+
+    /// switches the table to soft deletes. Instead of removing rows, `Table::delete_rows()` will mark
+    /// them with a deletion timestamp, so older versions of the table can be viewed with `Table::as_of()`.
+    ///
+    /// two date columns, "Inserted At" and "Deleted At", are added to the table to keep track of this.
+    /// rows which already exist are treated as if they were inserted right now.
+    pub fn enable_soft_delete(&mut self) -> Result<(), DBError> {
+        if self.soft_delete { return Ok(()) }
+
+        for col_name in [INSERTED_AT_COLUMN, DELETED_AT_COLUMN] {
+            if self.is_valid_column(&col_name.to_string()) {
+                return Err(DBError::MandatoryColumn(col_name.to_string()));
+            }
+            self.columns.push( Column::new(col_name.to_string(), DataType::Date, false) );
+        }
+
+        let now = FieldValue::Date(Utc::now());
+        for row in &mut self.rows {
+            row.insert( INSERTED_AT_COLUMN.to_string(), now.clone() );
+            row.insert( DELETED_AT_COLUMN.to_string(), FieldValue::Null );
+        }
+
+        self.soft_delete = true;
+        Ok(())
+    }
+
+
+    /// fills in the soft delete columns for a row that is about to be inserted
+    pub(super) fn stamp_inserted_row(&self, row: &mut HashMap<String, FieldValue>) {
+        row.entry( INSERTED_AT_COLUMN.to_string() ).or_insert( FieldValue::Date(Utc::now()) );
+        row.entry( DELETED_AT_COLUMN.to_string() ).or_insert( FieldValue::Null );
+    }
+
+
+    /// marks every row matching the search criteria as deleted, returning how many rows were marked.
+    /// rows which were already deleted are not counted again.
+    pub(super) fn soft_delete_rows(&mut self, column_name: &String, search_criteria: FilterCondition) -> Result<u32, DBError> {
+
+        let filtered_table = self.select_rows(column_name, search_criteria)?;
+        let rows_to_delete = filtered_table.rows();
+
+        let now = FieldValue::Date(Utc::now());
+        let mut number_of_deleted_rows: u32 = 0;
+
+        for row in &mut self.rows {
+            if is_tombstoned(row) || !rows_to_delete.contains(row) { continue; }
+
+            row.insert( DELETED_AT_COLUMN.to_string(), now.clone() );
+            number_of_deleted_rows += 1;
+        }
+
+        Ok(number_of_deleted_rows)
+    }
+
+
+    /// rebuilds the table as it was at the given point in time:
+    /// rows inserted after `timestamp` are left out, and rows deleted after `timestamp` are brought back.
+    ///
+    /// ## Note
+    /// only insertions and deletions are tracked, so edited rows show their current values.
+    pub fn as_of(&self, timestamp: DateTime<Utc>) -> Result<Table, DBError> {
+        if !self.soft_delete {
+            return Err(DBError::ActionNotImplemented("time travel on a table without soft deletes".to_owned()));
+        }
+
+        let timestamp = FieldValue::Date(timestamp);
+        let mut past_table = Table::new( format!("{} as of {}", &self.name, &timestamp), self.columns().clone(), true );
+
+        for row in &self.rows {
+            let inserted_at = row.get(INSERTED_AT_COLUMN).unwrap();
+            let deleted_at = row.get(DELETED_AT_COLUMN).unwrap();
+
+            let existed_then = !inserted_at.is_greater_than(&timestamp)?;
+            let deleted_then = !deleted_at.eq(&FieldValue::Null) && !deleted_at.is_greater_than(&timestamp)?;
+
+            if existed_then && !deleted_then {
+                past_table.insert_row(row)?;
+            }
+        }
+
+        Ok(past_table)
+    }
+
+
+    /// permanently removes every row marked as deleted, or which has passed the table's TTL,
+    /// returning how many rows were removed.
+    ///
+    /// every saved index on the table is rebuilt afterwards since the row positions change.
+    pub fn purge(&mut self) -> Result<u32, DBError> {
+        let now = Utc::now();
+        let purged_rows: Vec<usize> = self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| is_tombstoned(r) || self.is_expired(r, now))
+            .map(|(row_index, _)| row_index)
+            .collect();
+
+        if !purged_rows.is_empty() {
+            self.remove_rows_at(&purged_rows)?;
+        }
+
+        Ok(purged_rows.len() as u32)
+    }
+}
@@ -1,42 +1,54 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::cmp::Ordering;
 
-use crate::structures::{column::FieldValue, db_err::DBError, sort::SortCondition};
+use crate::structures::{cancel::memory_budget_or, column::FieldValue, db_err::DBError, sort::SortCondition};
 
-use super::table::*;
+use super::{spill::{sorted_positions, Row, DEFAULT_SORT_MEMORY_BUDGET}, table::*};
 
 
 impl Table {
 
+    /// sorts the rows of the table on `sorting_column`. Rows with equal values keep their order.
+    ///
+    /// This uses the statement's memory budget, or `DEFAULT_SORT_MEMORY_BUDGET`, see `Table::sort_rows_with_budget()`.
     pub fn sort_rows(&mut self, sorting_by: SortCondition, sorting_column: String) -> Result<(), DBError> {
+        self.sort_rows_with_budget(sorting_by, sorting_column, memory_budget_or(DEFAULT_SORT_MEMORY_BUDGET))
+    }
+
+
+    /// same as `Table::sort_rows()`, but at most `max_keys_in_memory` values are sorted in memory at once.
+    ///
+    /// larger tables are sorted in runs which are spilled to temporary files and merged, then the rows are
+    /// moved into their new order without being copied. The table isn't changed if the sort fails.
+    pub fn sort_rows_with_budget(&mut self, sorting_by: SortCondition, sorting_column: String, max_keys_in_memory: usize) -> Result<(), DBError> {
         
         if !self.is_valid_column( &sorting_column ) {
             return Err(DBError::InvalidColumn( sorting_column.clone() ));
         }
 
-        fn compare(col: &String, a: &HashMap<String, FieldValue>, b: &HashMap<String, FieldValue> , descending_ord: bool) -> Ordering {
-            let a = a.get(col).unwrap();
-            let b = b.get(col).unwrap();
-            let comparison_result = if descending_ord { b.compare_to(a) } else {a.compare_to(b) };
+        let descending_ord = match sorting_by {
+            SortCondition::NumericAscending  | SortCondition::AlphaAscending  | SortCondition::DateAscending  => false,
+            SortCondition::NumericDescending | SortCondition::AlphaDescending | SortCondition::DateDescending => true,
+        };
+
+        let compare = |a: &FieldValue, b: &FieldValue| {
+            let comparison_result = if descending_ord { b.compare_to(a) } else { a.compare_to(b) };
             match comparison_result {
                 Ok(ordering) => ordering,
                 // temporary, unsure what to do if an error is thrown right now, if its even possible with this implementation 
                 Err(_) => Ordering::Equal   
             }
-        }
-
-        match sorting_by {
-            SortCondition::NumericAscending  => self.rows.sort_by(|a, b| compare(&sorting_column, a, b, false)),
-            SortCondition::NumericDescending => self.rows.sort_by(|a, b| compare(&sorting_column, a, b, true)),
-            SortCondition::AlphaAscending    => self.rows.sort_by(|a, b| compare(&sorting_column, a, b, false)),
-            SortCondition::AlphaDescending   => self.rows.sort_by(|a, b| compare(&sorting_column, a, b, true)),
-            SortCondition::DateAscending     => self.rows.sort_by(|a, b| compare(&sorting_column, a, b, false)),
-            SortCondition::DateDescending    => self.rows.sort_by(|a, b| compare(&sorting_column, a, b, true)),
         };
 
+        let positions = sorted_positions(&self.rows, &sorting_column, compare, max_keys_in_memory)?;
+
+        let mut unsorted_rows: Vec<Option<Row>> = std::mem::take(&mut self.rows).into_iter().map(Some).collect();
+        self.rows = positions
+            .into_iter()
+            .map(|position| unsorted_rows[position].take().unwrap())
+            .collect();
 
-    
         Ok(())
     }
 
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,250 @@
+use std::{cmp::Ordering, collections::HashMap, fs::{self, File}, io::{BufReader, BufWriter, Write}, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{config::TEMP_PATH, structures::{cancel::check_cancelled, column::FieldValue, db_err::DBError}};
+
+
+pub type Row = HashMap<String, FieldValue>;
+
+/// every spill file starts with this, followed by the id of the process which made it
+const SPILL_FILE_PREFIX: &str = "spill_";
+
+/// the default number of sort keys kept in memory at once by `Table::sort_rows()` and the joins
+pub const DEFAULT_SORT_MEMORY_BUDGET: usize = 100_000;
+
+/// a value being sorted, and the position of the row it came from
+type SortKey = (FieldValue, usize);
+
+
+/// a temporary file holding rows which didn't fit in memory.
+/// The file is deleted when this is dropped, so spilled data is cleaned up even if the operation fails.
+pub struct SpillFile {
+    path: PathBuf,
+    writer: Option<BufWriter<File>>,
+}
+
+impl SpillFile {
+    /// creates an empty spill file in the temp directory. `label` is only used to make the file name readable.
+    pub fn create(label: &str) -> Result<Self, DBError> {
+        fs::create_dir_all(TEMP_PATH).map_err(
+            |_| DBError::IOFailure(TEMP_PATH.to_owned(), "unable to create temp directory".to_owned())
+        )?;
+
+        let file_name = format!("{}{}_{}_{}.bin", SPILL_FILE_PREFIX, std::process::id(), rand::random::<u32>(), label);
+        let path = PathBuf::from(TEMP_PATH).join(file_name);
+        let file = File::create(&path).map_err(
+            |_| DBError::IOFailure(path.to_string_lossy().to_string(), "unable to create spill file".to_owned())
+        )?;
+        Ok(SpillFile { path, writer: Some(BufWriter::new(file)) })
+    }
+
+    pub fn write_row(&mut self, row: &Row) -> Result<(), DBError> {
+        self.write(row)
+    }
+
+    /// flushes the file and reads every row back
+    pub fn read_rows(&mut self) -> Result<impl Iterator<Item = Result<Row, DBError>>, DBError> {
+        self.read::<Row>()
+    }
+
+    fn write<T: Serialize>(&mut self, item: &T) -> Result<(), DBError> {
+        let writer = self.writer.as_mut().unwrap();
+        bincode::serialize_into(writer, item).map_err(
+            |_| DBError::IOFailure(self.path.to_string_lossy().to_string(), "unable to write to spill file".to_owned())
+        )
+    }
+
+    /// flushes the file and reads everything written to it back, in the order it was written
+    fn read<T: DeserializeOwned>(&mut self) -> Result<impl Iterator<Item = Result<T, DBError>>, DBError> {
+        let path = self.path.to_string_lossy().to_string();
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush().map_err(|_| DBError::IOFailure(path.clone(), "unable to flush spill file".to_owned()))?;
+        }
+
+        let file = File::open(&self.path).map_err(|_| DBError::IOFailure(path.clone(), "unable to open spill file".to_owned()))?;
+        let mut reader = BufReader::new(file);
+
+        Ok(std::iter::from_fn(move || {
+            match bincode::deserialize_from::<_, T>(&mut reader) {
+                Ok(item) => Some(Ok(item)),
+                Err(e) => match *e {
+                    // reaching the end of the file is how we know we're done
+                    bincode::ErrorKind::Io(ref io_err) if io_err.kind() == std::io::ErrorKind::UnexpectedEof => None,
+                    _ => Some(Err(DBError::IOFailure(path.clone(), "corrupted spill file".to_owned()))),
+                }
+            }
+        }))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        self.writer.take();
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+
+/// the positions of `rows`, ordered by their values in `column` using `compare`. Rows with equal values keep their order.
+///
+/// at most `max_keys_in_memory` values are sorted in memory at once. Past that, the values are sorted in runs of that size,
+/// each run is written to a spill file, and the runs are merged back together. The spill files are always deleted,
+/// even if the sort fails. Rows without a value in `column` are sorted as `NULL`.
+pub(super) fn sorted_positions<F>(rows: &[Row], column: &str, compare: F, max_keys_in_memory: usize) -> Result<Vec<usize>, DBError>
+where F: Fn(&FieldValue, &FieldValue) -> Ordering {
+
+    let run_length = max_keys_in_memory.max(1);
+    // breaking ties on the position keeps the sort stable, even across runs
+    let compare_keys = |a: &SortKey, b: &SortKey| compare(&a.0, &b.0).then(a.1.cmp(&b.1));
+
+    let mut spilled_runs: Vec<SpillFile> = Vec::new();
+    let mut run: Vec<SortKey> = Vec::with_capacity(run_length.min(rows.len()));
+
+    for (position, row) in rows.iter().enumerate() {
+        check_cancelled()?;
+        run.push( (row.get(column).cloned().unwrap_or(FieldValue::Null), position) );
+
+        let more_rows_left = position + 1 < rows.len();
+        if run.len() == run_length && more_rows_left {
+            run.sort_by(compare_keys);
+            let mut spill_file = SpillFile::create(&format!("sort_run_{}", spilled_runs.len()))?;
+            for key in &run {
+                spill_file.write(key)?;
+            }
+            spilled_runs.push(spill_file);
+            run.clear();
+        }
+    }
+    run.sort_by(compare_keys);
+
+    if spilled_runs.is_empty() {
+        return Ok(run.into_iter().map(|(_, position)| position).collect());
+    }
+
+    // the last run is still in memory, so it is merged with the spilled ones as it is
+    let mut runs: Vec<Box<dyn Iterator<Item = Result<SortKey, DBError>>>> = Vec::new();
+    for spill_file in spilled_runs.iter_mut() {
+        runs.push( Box::new(spill_file.read::<SortKey>()?) );
+    }
+    runs.push( Box::new(run.into_iter().map(Ok)) );
+
+    let mut heads: Vec<Option<SortKey>> = Vec::with_capacity(runs.len());
+    for run in runs.iter_mut() {
+        heads.push( run.next().transpose()? );
+    }
+
+    let mut positions: Vec<usize> = Vec::with_capacity(rows.len());
+    loop {
+        check_cancelled()?;
+        let smallest = heads
+            .iter()
+            .enumerate()
+            .filter_map(|(run_index, head)| head.as_ref().map(|key| (run_index, key)))
+            .min_by(|(_, a), (_, b)| compare_keys(a, b))
+            .map(|(run_index, _)| run_index);
+
+        let Some(run_index) = smallest else { break };
+        let (_, position) = heads[run_index].take().unwrap();
+        positions.push(position);
+        heads[run_index] = runs[run_index].next().transpose()?;
+    }
+
+    Ok(positions)
+}
+
+
+/// deletes spill files left behind by other processes.
+///
+/// spill files are normally deleted as soon as they are used, but if the program is killed 
+/// (i.e. with Ctrl+C) in the middle of an operation, they stay in the temp directory.
+/// This should be called on startup, before any queries are run. Returns the number of files removed.
+pub fn remove_stale_spill_files() -> Result<u32, DBError> {
+    let entries = match fs::read_dir(TEMP_PATH) {
+        Ok(e) => e,
+        // no temp directory means nothing has ever been spilled
+        Err(_) => return Ok(0),
+    };
+
+    let own_prefix = format!("{}{}_", SPILL_FILE_PREFIX, std::process::id());
+    let mut number_of_removed_files: u32 = 0;
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !file_name.starts_with(SPILL_FILE_PREFIX) || file_name.starts_with(&own_prefix) { continue; }
+
+        fs::remove_file(entry.path()).map_err(
+            |_| DBError::IOFailure(file_name.clone(), "unable to remove stale spill file".to_owned())
+        )?;
+        number_of_removed_files += 1;
+    }
+
+    Ok(number_of_removed_files)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::structures::column::FieldValue;
+
+    use super::{sorted_positions, Row, SpillFile};
+
+    fn rows_of(values: &[FieldValue]) -> Vec<Row> {
+        values.iter().map(|v| HashMap::from([("n".to_owned(), v.clone())])).collect()
+    }
+
+    #[test]
+    fn a_sort_which_spills_several_runs_matches_an_in_memory_sort() {
+        let values: Vec<FieldValue> = (0..50).map(|n| FieldValue::Number(f64::from((n * 37) % 11))).collect();
+        let rows = rows_of(&values);
+
+        let in_memory = sorted_positions(&rows, "n", |a, b| a.cmp(b), 1000).unwrap();
+        // 50 rows in runs of 4 is 12 spilled runs, plus the one left in memory
+        let spilled = sorted_positions(&rows, "n", |a, b| a.cmp(b), 4).unwrap();
+        assert_eq!(spilled, in_memory);
+
+        let sorted_values: Vec<&FieldValue> = spilled.iter().map(|&p| &values[p]).collect();
+        assert!(sorted_values.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn equal_values_keep_their_order_across_runs() {
+        let rows = rows_of(&[
+            FieldValue::Number(2.0), FieldValue::Number(1.0), FieldValue::Number(2.0),
+            FieldValue::Number(1.0), FieldValue::Number(2.0), FieldValue::Number(1.0),
+        ]);
+        assert_eq!(sorted_positions(&rows, "n", |a, b| a.cmp(b), 2).unwrap(), vec![1, 3, 5, 0, 2, 4]);
+        assert_eq!(sorted_positions(&rows, "n", |a, b| b.cmp(a), 2).unwrap(), vec![0, 2, 4, 1, 3, 5]);
+    }
+
+    #[test]
+    fn missing_values_are_sorted_as_nulls() {
+        let mut rows = rows_of(&[FieldValue::Number(3.0), FieldValue::Null, FieldValue::Number(-1.0)]);
+        rows.push(HashMap::new());
+        assert_eq!(sorted_positions(&rows, "n", |a, b| a.cmp(b), 1).unwrap(), vec![1, 3, 2, 0]);
+        assert_eq!(sorted_positions(&rows, "n", |a, b| a.cmp(b), 100).unwrap(), vec![1, 3, 2, 0]);
+    }
+
+    #[test]
+    fn empty_input_is_sorted_without_spilling() {
+        assert!(sorted_positions(&[], "n", |a, b| a.cmp(b), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn spill_files_are_deleted_when_dropped() {
+        let rows = rows_of(&[FieldValue::Number(1.0), FieldValue::String("a".to_owned())]);
+        let mut spill_file = SpillFile::create("test").unwrap();
+        for row in &rows {
+            spill_file.write_row(row).unwrap();
+        }
+        let read_back: Vec<Row> = spill_file.read_rows().unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(read_back, rows);
+
+        let path = spill_file.path.clone();
+        assert!(path.exists());
+        drop(spill_file);
+        assert!(!path.exists());
+    }
+}
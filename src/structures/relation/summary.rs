@@ -0,0 +1,136 @@
+use std::{fmt, fs::{self, File}, io::BufReader, time::SystemTime};
+
+use bincode::Options;
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::{column::Column, db_err::DBError}};
+
+use super::{format::{read_file_header, LegacyColumn}, io::index_file_name};
+
+
+/// a quick overview of a table on disk, used when listing the tables in the database
+#[derive(Debug, Clone)]
+pub struct TableSummary {
+    pub name: String,
+    pub rows: usize,
+    pub cols: usize,
+    pub columns: Vec<Column>,
+    /// the columns which have an index saved for them
+    pub indexes: Vec<String>,
+    /// size of the table's file plus all of its index files
+    pub disk_bytes: u64,
+    pub last_modified: Option<SystemTime>,
+}
+
+
+/// the start of a saved `Table` (after the file header), in the same field order. Since bincode writes the length
+/// of a `Vec` before its items, reading this only decodes the schema and the number of rows, not the rows themselves.
+#[derive(Deserialize)]
+struct TableHeader {
+    name: String,
+    columns: Vec<Column>,
+    _primary_keys: Vec<Column>,
+    number_of_rows: u64,
+}
+
+
+/// the same as `TableHeader`, for files saved before the file format was versioned
+#[derive(Deserialize)]
+struct LegacyTableHeader {
+    name: String,
+    columns: Vec<LegacyColumn>,
+    _primary_keys: Vec<LegacyColumn>,
+    number_of_rows: u64,
+}
+
+
+/// summarizes the table saved at `file_path` without loading all of its rows.
+///
+/// every change to a table is saved to its file right away, so the summary is always up to date.
+pub fn table_summary(file_path: &str) -> Result<TableSummary, DBError> {
+
+    let file = File::open(file_path).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?;
+    let metadata = file.metadata().map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?;
+
+    // the limit stops a damaged file from claiming a huge length and running out of memory
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(metadata.len());
+    let mut reader = BufReader::new(file);
+
+    let header: TableHeader = match read_file_header(&mut reader, file_path, metadata.len())? {
+        Some(_) => options.deserialize_from(reader).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?,
+        None => {
+            let legacy_header: LegacyTableHeader = options.deserialize_from(reader).map_err(|_| DBError::DataBaseFileFailure(file_path.to_owned()))?;
+            TableHeader {
+                name: legacy_header.name,
+                columns: legacy_header.columns.into_iter().map(Column::from).collect(),
+                _primary_keys: Vec::new(),
+                number_of_rows: legacy_header.number_of_rows,
+            }
+        },
+    };
+
+    let mut indexes: Vec<String> = Vec::new();
+    let mut disk_bytes = metadata.len();
+
+    for column in &header.columns {
+        let index_path = format!("{}/{}", INDEX_PATH, index_file_name(&header.name, column.get_name()));
+        if let Ok(index_metadata) = fs::metadata(index_path) {
+            indexes.push(column.get_name().to_string());
+            disk_bytes += index_metadata.len();
+        }
+    }
+
+    Ok(TableSummary {
+        name: header.name,
+        rows: header.number_of_rows as usize,
+        cols: header.columns.len(),
+        columns: header.columns,
+        indexes,
+        disk_bytes,
+        last_modified: metadata.modified().ok(),
+    })
+}
+
+
+/// summarizes every table saved in the relation directory, sorted by name.
+///
+/// files which can't be read as a table are skipped.
+pub fn summarize_all_tables() -> Result<Vec<TableSummary>, DBError> {
+
+    let entries = fs::read_dir(RELATION_PATH)
+        .map_err(|e| DBError::IOFailure(RELATION_PATH.to_owned(), e.to_string()))?;
+
+    let mut summaries: Vec<TableSummary> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            file_name.starts_with("db_") && file_name.ends_with(".bin")
+        })
+        .filter_map(|path| table_summary(path.to_str()?).ok())
+        .collect();
+
+    summaries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(summaries)
+}
+
+
+impl fmt::Display for TableSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let last_modified = match self.last_modified {
+            Some(time) => DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string(),
+            None => "unknown".to_owned(),
+        };
+        let indexes = if self.indexes.is_empty() { "none".to_owned() } else { self.indexes.join(", ") };
+
+        write!(
+            f, 
+            "{} | {} row(s), {} column(s) | indexes: {} | {} bytes | modified {}", 
+            self.name, self.rows, self.cols, indexes, self.disk_bytes, last_modified
+        )
+    }
+}
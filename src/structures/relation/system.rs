@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::structures::{column::{Column, DataType, FieldValue}, db_err::DBError};
+
+use super::{index_stats::index_usage, metadata::{metadata_of, Metadata}, spill::Row, summary::summarize_all_tables, table::Table};
+
+
+/// every system table's name starts with this, so they can't be confused with tables made by users
+pub const SYSTEM_TABLE_PREFIX: &str = "sys.";
+
+/// every table saved in the database, one row per table
+pub const SYS_TABLES: &str = "sys.tables";
+/// every column of every saved table, one row per column
+pub const SYS_COLUMNS: &str = "sys.columns";
+/// every index of every saved table, along with how often it has been used
+pub const SYS_INDEXES: &str = "sys.indexes";
+
+
+pub fn is_system_table(name: &str) -> bool {
+    name.to_lowercase().starts_with(SYSTEM_TABLE_PREFIX)
+}
+
+
+/// builds the system table called `name` from what is currently saved in the database.
+///
+/// system tables aren't saved anywhere, so they are always up to date but can't be changed.
+pub fn system_table(name: &str) -> Result<Table, DBError> {
+    match name.to_lowercase().as_str() {
+        SYS_TABLES => sys_tables(),
+        SYS_COLUMNS => sys_columns(),
+        SYS_INDEXES => sys_indexes(),
+        _ => Err(DBError::DataBaseFileFailure(name.to_string())),
+    }
+}
+
+
+fn new_system_table(name: &str, columns: &[(&str, DataType)]) -> Table {
+    let columns = columns
+        .iter()
+        .map(|(col_name, data_type)| Column::new(col_name.to_string(), data_type.clone(), false))
+        .collect();
+    Table::new(name.to_string(), columns, true)
+}
+
+
+fn system_row(values: Vec<(&str, FieldValue)>) -> Row {
+    values.into_iter().map(|(k, v)| (k.to_string(), v)).collect::<HashMap<String, FieldValue>>()
+}
+
+
+fn description_value(metadata: &Metadata) -> FieldValue {
+    metadata.description.clone().map(FieldValue::String).unwrap_or(FieldValue::Null)
+}
+
+
+/// tags are shown as `key=value`, separated by commas
+fn tags_value(metadata: &Metadata) -> FieldValue {
+    if metadata.tags.is_empty() { return FieldValue::Null }
+    FieldValue::String(metadata.tags_text())
+}
+
+
+fn sys_tables() -> Result<Table, DBError> {
+    let mut table = new_system_table(SYS_TABLES, &[
+        ("Name", DataType::String),
+        ("Rows", DataType::Number),
+        ("Columns", DataType::Number),
+        ("Indexes", DataType::Number),
+        ("Disk Bytes", DataType::Number),
+        ("Last Modified", DataType::Date),
+        ("Description", DataType::String),
+        ("Tags", DataType::String),
+    ]);
+
+    for summary in summarize_all_tables()? {
+        let last_modified = match summary.last_modified {
+            Some(time) => FieldValue::Date(DateTime::<Utc>::from(time)),
+            None => FieldValue::Null,
+        };
+        let metadata = metadata_of(&summary.name);
+        table.insert_row(&system_row(vec![
+            ("Name", FieldValue::String(summary.name)),
+            ("Rows", FieldValue::Number(summary.rows as f64)),
+            ("Columns", FieldValue::Number(summary.cols as f64)),
+            ("Indexes", FieldValue::Number(summary.indexes.len() as f64)),
+            ("Disk Bytes", FieldValue::Number(summary.disk_bytes as f64)),
+            ("Last Modified", last_modified),
+            ("Description", description_value(&metadata.table)),
+            ("Tags", tags_value(&metadata.table)),
+        ]))?;
+    }
+    Ok(table)
+}
+
+
+fn sys_columns() -> Result<Table, DBError> {
+    let mut table = new_system_table(SYS_COLUMNS, &[
+        ("Table", DataType::String),
+        ("Column", DataType::String),
+        ("Position", DataType::Number),
+        ("Data Type", DataType::String),
+        ("Primary Key", DataType::Boolean),
+        ("Allows Nulls", DataType::Boolean),
+        ("Indexed", DataType::Boolean),
+        ("Description", DataType::String),
+        ("Tags", DataType::String),
+    ]);
+
+    for summary in summarize_all_tables()? {
+        let metadata = metadata_of(&summary.name);
+        for (position, column) in summary.columns.iter().enumerate() {
+            let column_metadata = metadata.columns.get(column.get_name()).cloned().unwrap_or_default();
+            table.insert_row(&system_row(vec![
+                ("Table", FieldValue::String(summary.name.clone())),
+                ("Column", FieldValue::String(column.get_name().to_string())),
+                ("Position", FieldValue::Number(position as f64)),
+                ("Data Type", FieldValue::String(column.get_data_type().to_string())),
+                ("Primary Key", FieldValue::Boolean(column.is_primary_key())),
+                ("Allows Nulls", FieldValue::Boolean(column.allows_nulls())),
+                ("Indexed", FieldValue::Boolean(summary.indexes.iter().any(|i| i == column.get_name()))),
+                ("Description", description_value(&column_metadata)),
+                ("Tags", tags_value(&column_metadata)),
+            ]))?;
+        }
+    }
+    Ok(table)
+}
+
+
+fn sys_indexes() -> Result<Table, DBError> {
+    let mut table = new_system_table(SYS_INDEXES, &[
+        ("Table", DataType::String),
+        ("Column", DataType::String),
+        ("Lookups", DataType::Number),
+        ("Last Used", DataType::Date),
+    ]);
+
+    for usage in index_usage()? {
+        table.insert_row(&system_row(vec![
+            ("Table", FieldValue::String(usage.table)),
+            ("Column", FieldValue::String(usage.column)),
+            ("Lookups", FieldValue::Number(usage.lookups as f64)),
+            ("Last Used", usage.last_used.map(FieldValue::Date).unwrap_or(FieldValue::Null)),
+        ]))?;
+    }
+    Ok(table)
+}
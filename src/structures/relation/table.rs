@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::structures::column::{Column, FieldValue};
+use super::{changes::ChangeTracker, lineage::Lineage, ttl::RowTtl};
+use crate::structures::{column::{Column, FieldValue}, expression::ComputedColumn, validation::ValidationRule};
 
 // TODO: implement pages
 /**
@@ -9,10 +11,31 @@ use crate::structures::column::{Column, FieldValue};
 */
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Table {
     pub(super) name: String,
+    /// counts how many times the table has been saved, so a save can tell if someone else saved the table
+    /// after it was loaded. It is kept in the header of the table's file, so it can be read without loading the rows
+    #[serde(skip)]
+    pub(super) generation: u64,
     pub(super) columns: Vec<Column>,
     pub(super) primary_keys: Vec<Column>,
     pub(super) rows: Vec<HashMap<String, FieldValue>>,
+    /// if true, deleted rows are kept with a deletion timestamp instead of being removed
+    pub(super) soft_delete: bool,
+    /// if true, every row keeps a version counter which is bumped each time the row is edited
+    pub(super) row_versions: bool,
+    /// columns whose values are worked out from other columns in the row
+    pub(super) computed_columns: Vec<ComputedColumn>,
+    /// conditions every row should meet, checked by `Table::validate()`
+    pub(super) validation_rules: Vec<ValidationRule>,
+    /// if set, rows older than the TTL are hidden from searches and removed by `Table::purge()`
+    pub(super) ttl: Option<RowTtl>,
+    /// what the table was derived from, if it was made by a filter, projection, join or import
+    pub(super) lineage: Option<Lineage>,
+    /// when the table was made, if it was made by the engine (i.e. by a filter or join) and can be cleaned up
+    pub(super) temporary_since: Option<DateTime<Utc>>,
+    /// the rows changed since `Table::track_changes()` was called, if changes are being tracked
+    #[serde(skip)]
+    pub(super) change_tracker: Option<ChangeTracker>,
 }
\ No newline at end of file
@@ -0,0 +1,137 @@
+use std::{fs::{self, File}, io::BufReader};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{config::{INDEX_PATH, RELATION_PATH}, structures::db_err::DBError};
+
+use super::{format::read_file_header, integrity::file_names_in, io::{index_file_name, load_database, relation_lock_file_name}, table::Table};
+
+
+/// how old a temporary table has to be before `remove_stale_temp_tables()` deletes it, unless told otherwise
+pub const DEFAULT_TEMP_TABLE_MAX_AGE: Duration = Duration::hours(24);
+
+
+impl Table {
+
+    /// marks a table made by the engine (i.e. the result of a filter or join) as temporary, so it is
+    /// cleaned up by `remove_stale_temp_tables()` if it is ever saved
+    pub(super) fn mark_temporary(&mut self) {
+        self.temporary_since = Some(Utc::now());
+    }
+
+
+    /// true if the table was made by the engine and hasn't been kept with `Table::keep()`
+    pub fn is_temporary(&self) -> bool { self.temporary_since.is_some() }
+
+
+    /// stops the table from being temporary, so it isn't cleaned up once it is saved
+    pub fn keep(&mut self) { self.temporary_since = None; }
+}
+
+
+/// deletes every saved temporary table which was made more than `max_age` ago, along with the indexes on its columns.
+/// Returns the names of the tables which were removed.
+///
+/// only the header of each file is read, so tables which are kept cost almost nothing to check. Tables which
+/// can't be read are left alone, see `check_integrity()` for those.
+pub fn remove_stale_temp_tables(max_age: Duration) -> Result<Vec<String>, DBError> {
+    let oldest_kept = Utc::now() - max_age;
+    let mut removed_tables: Vec<String> = Vec::new();
+
+    for file_name in file_names_in(RELATION_PATH, "db_")? {
+        let file_path = format!("{}/{}", RELATION_PATH, file_name);
+        match saved_temporary_since(&file_path) {
+            Ok(Some(made_at)) if made_at < oldest_kept => (),
+            _ => continue,
+        }
+        // the table is only loaded to find the names of its lock and index files
+        let table = match load_database(&file_path) {
+            Ok(table) => table,
+            Err(_) => continue,
+        };
+
+        fs::remove_file(&file_path).map_err(|e| DBError::IOFailure(file_path, e.to_string()))?;
+        let _ = fs::remove_file(format!("{}/{}", RELATION_PATH, relation_lock_file_name(&table.name)));
+        // not every column has an index, so missing files are fine
+        for column in table.columns() {
+            let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(&table.name, column.get_name())));
+        }
+        removed_tables.push(table.name);
+    }
+
+    Ok(removed_tables)
+}
+
+
+
+/// when the table saved at `file_path` was made, or `None` if it isn't temporary. Only the header is read,
+/// except for files saved in table format 1 or 2, which don't have it in their header
+fn saved_temporary_since(file_path: &str) -> Result<Option<DateTime<Utc>>, DBError> {
+    let file = File::open(file_path).map_err(|e| DBError::IOFailure(file_path.to_owned(), e.to_string()))?;
+    let file_size = file.metadata().map_err(|e| DBError::IOFailure(file_path.to_owned(), e.to_string()))?.len();
+
+    match read_file_header(&mut BufReader::new(file), file_path, file_size)? {
+        Some(header) if header.format_version >= 3 => Ok(header.temporary_since),
+        Some(_) => Ok(load_database(file_path)?.temporary_since),
+        // files saved before the format was versioned predate temporary tables
+        None => Ok(None),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use chrono::{Duration, Utc};
+
+    use crate::{config::RELATION_PATH, structures::{column::{Column, DataType, FieldValue}, relation::{io::{relation_file_path, relation_lock_file_name}, table::Table}}};
+
+    use super::{remove_stale_temp_tables, saved_temporary_since};
+
+    /// saves a table with one row, made `age` ago if it is temporary. Returns the path of its file
+    fn save_table(table_name: &str, age: Option<Duration>) -> String {
+        let mut table = Table::new(table_name.to_owned(), vec![Column::new("n".to_owned(), DataType::Number, false)], true);
+        table.insert_row(&HashMap::from([("n".to_owned(), FieldValue::Number(1.0))])).unwrap();
+        table.temporary_since = age.map(|age| Utc::now() - age);
+        table.save(RELATION_PATH.to_owned()).unwrap();
+        relation_file_path(RELATION_PATH, table_name)
+    }
+
+    fn remove_table(table_name: &str) {
+        let _ = fs::remove_file(relation_file_path(RELATION_PATH, table_name));
+        let _ = fs::remove_file(format!("{}/{}", RELATION_PATH, relation_lock_file_name(table_name)));
+    }
+
+    #[test]
+    fn the_header_says_when_a_temporary_table_was_made() {
+        let temporary = save_table("temporary test header", Some(Duration::hours(3)));
+        let kept = save_table("temporary test header kept", None);
+
+        let made_at = saved_temporary_since(&temporary).unwrap().unwrap();
+        assert!((Utc::now() - Duration::hours(3) - made_at).num_seconds().abs() < 60);
+        assert_eq!(saved_temporary_since(&kept).unwrap(), None);
+
+        remove_table("temporary test header");
+        remove_table("temporary test header kept");
+    }
+
+    #[test]
+    fn only_stale_temporary_tables_are_removed() {
+        let stale = save_table("temporary test stale", Some(Duration::hours(30)));
+        let recent = save_table("temporary test recent", Some(Duration::hours(1)));
+        let kept = save_table("temporary test kept", None);
+
+        let removed = remove_stale_temp_tables(Duration::hours(24)).unwrap();
+        assert!(removed.contains(&"temporary test stale".to_owned()));
+        assert!(!removed.contains(&"temporary test recent".to_owned()));
+        assert!(!removed.contains(&"temporary test kept".to_owned()));
+        assert!(!fs::exists(&stale).unwrap());
+        assert!(fs::exists(&recent).unwrap());
+        assert!(fs::exists(&kept).unwrap());
+
+        remove_table("temporary test stale");
+        remove_table("temporary test recent");
+        remove_table("temporary test kept");
+    }
+}
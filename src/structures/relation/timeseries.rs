@@ -0,0 +1,58 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::structures::{aggregate::Aggregation, cancel::check_cancelled, column::{DataType, FieldValue}, db_err::DBError, sort::SortCondition};
+
+use super::table::Table;
+
+
+impl Table {
+
+    /// splits the rows into fixed time buckets of length `interval` using the dates in `time_column`,
+    /// and computes each aggregation per bucket.
+    ///
+    /// the result has the start of each bucket in `time_column`, followed by one column per aggregation,
+    /// sorted from the earliest bucket to the latest. Rows with a null date are left out.
+    ///
+    /// ## Example
+    /// hourly averages of a sensor reading:
+    /// `table.downsample(&"Timestamp".to_string(), Duration::hours(1), vec![Aggregation::Average("Reading".to_string())])`
+    pub fn downsample(&self, time_column: &String, interval: Duration, aggregations: Vec<Aggregation>) -> Result<Table, DBError> {
+
+        let column = self.column(time_column.to_string()).ok_or(DBError::InvalidColumn(time_column.to_string()))?;
+        if column.get_data_type() != &DataType::Date {
+            return Err(DBError::MisMatchDataType(DataType::Date, column.get_data_type().clone()));
+        }
+
+        let interval_seconds = interval.num_seconds();
+        if interval_seconds <= 0 {
+            // buckets have to be at least a second long
+            return Err(DBError::InvalidValue(format!("{} seconds", interval_seconds), DataType::Number));
+        }
+
+        // replace every date with the start of its bucket, then let group_by do the aggregating
+        let mut bucketed_table = Table::new(format!("{} in buckets", &self.name), self.columns.clone(), true);
+        let now = Utc::now();
+
+        for row in &self.rows {
+            check_cancelled()?;
+            if !self.is_live(row, now) { continue }
+
+            let date = match row.get(time_column) {
+                Some(FieldValue::Date(d)) => d,
+                _ => continue,
+            };
+            let bucket_start = date.timestamp().div_euclid(interval_seconds) * interval_seconds;
+            let bucket_start = DateTime::<Utc>::from_timestamp(bucket_start, 0).ok_or(DBError::InvalidValue(date.to_string(), DataType::Date))?;
+
+            let mut bucketed_row = row.clone();
+            bucketed_row.insert(time_column.to_string(), FieldValue::Date(bucket_start));
+            bucketed_table.rows.push(bucketed_row);
+        }
+
+        let mut result = bucketed_table.group_by(time_column, aggregations)?;
+        result.name = format!("{} downsampled to {} second buckets", &self.name, interval_seconds);
+        result.sort_rows(SortCondition::DateAscending, time_column.to_string())?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::structures::{column::{DataType, FieldValue}, db_err::DBError};
+
+use super::{soft_delete::is_tombstoned, table::Table};
+
+
+/// rows older than `max_age_seconds`, going by the date in `column`, are expired
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowTtl {
+    pub column: String,
+    pub max_age_seconds: i64,
+}
+
+
+impl Table {
+
+    pub fn ttl(&self) -> Option<&RowTtl> { self.ttl.as_ref() }
+
+
+    /// makes rows expire once the date in `column_name` is more than `max_age` in the past.
+    ///
+    /// expired rows are left out of every search right away, and are removed for good by `Table::purge()`.
+    /// rows with a null date never expire.
+    pub fn set_ttl(&mut self, column_name: &str, max_age: Duration) -> Result<(), DBError> {
+        let column = self.column(column_name.to_string()).ok_or(DBError::InvalidColumn(column_name.to_string()))?;
+        if column.get_data_type() != &DataType::Date {
+            return Err(DBError::MisMatchDataType(DataType::Date, column.get_data_type().clone()));
+        }
+
+        self.ttl = Some(RowTtl { column: column_name.to_string(), max_age_seconds: max_age.num_seconds() });
+        Ok(())
+    }
+
+
+    pub fn clear_ttl(&mut self) { self.ttl = None; }
+
+
+    /// determines if a row has passed the table's TTL
+    pub(super) fn is_expired(&self, row: &HashMap<String, FieldValue>, now: DateTime<Utc>) -> bool {
+        let ttl = match &self.ttl {
+            Some(t) => t,
+            None => return false,
+        };
+        match row.get(&ttl.column) {
+            Some(FieldValue::Date(date)) => *date + Duration::seconds(ttl.max_age_seconds) < now,
+            _ => false
+        }
+    }
+
+
+    /// determines if a row should show up in searches, i.e. it hasn't been soft deleted or expired
+    pub(super) fn is_live(&self, row: &HashMap<String, FieldValue>, now: DateTime<Utc>) -> bool {
+        let deleted = self.soft_delete && is_tombstoned(row);
+        !deleted && !self.is_expired(row, now)
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use crate::{config::INDEX_PATH, structures::{column::{Column, FieldValue}, db_err::DBError}};
+
+use super::{io::save_index, table::Table};
+
+
+impl Table {
+
+    /// the columns with a unique constraint which aren't primary keys
+    pub fn unique_columns(&self) -> Vec<&Column> {
+        self.columns.iter().filter(|c| c.is_unique() && !c.is_primary_key()).collect()
+    }
+
+
+    /// the columns which are always indexed: the primary keys, then the unique columns.
+    /// These indexes are how duplicates are found, so they have to be rebuilt whenever rows move.
+    pub(super) fn constrained_columns(&self) -> Vec<Column> {
+        let mut columns = self.primary_keys.clone();
+        columns.extend( self.unique_columns().into_iter().cloned() );
+        columns
+    }
+
+
+    /// stops two rows from holding the same value in `column_name`, and indexes the column so
+    /// duplicates can be found quickly when rows are inserted or changed.
+    ///
+    /// fails with `DBError::DuplicateValue` if the column already has a duplicate, pointing at the first row which has it.
+    pub fn add_unique_constraint(&mut self, column_name: &str) -> Result<(), DBError> {
+        if self.is_computed_column(column_name) {
+            return Err(DBError::ComputedColumn(column_name.to_string()));
+        }
+        let position = self.columns
+            .iter()
+            .position(|c| c.get_name() == column_name)
+            .ok_or(DBError::InvalidColumn(column_name.to_string()))?;
+
+        let mut first_rows: BTreeMap<&FieldValue, usize> = BTreeMap::new();
+        for (row_index, row) in self.rows.iter().enumerate() {
+            let value = row.get(column_name).unwrap_or(&FieldValue::Null);
+            if value.eq(&FieldValue::Null) { continue }
+            if let Some(first_row) = first_rows.get(value) {
+                return Err(DBError::DuplicateValue(self.constraint_context(column_name, value, Some(*first_row))));
+            }
+            first_rows.insert(value, row_index);
+        }
+
+        self.columns[position].set_unique(true);
+        self.index_column(column_name.to_string())
+    }
+
+
+    /// lets `column_name` hold the same value in more than one row again. Its index is kept
+    pub fn remove_unique_constraint(&mut self, column_name: &str) -> Result<(), DBError> {
+        let column = self.columns
+            .iter_mut()
+            .find(|c| c.get_name() == column_name)
+            .ok_or(DBError::InvalidColumn(column_name.to_string()))?;
+        column.set_unique(false);
+        Ok(())
+    }
+
+
+    /// fails with `DBError::DuplicateValue` if a row other than `row_index` already holds `value` in the unique
+    /// column `column_name`, found by probing the column's index
+    pub(super) fn check_unique_value(&self, column_name: &str, value: &FieldValue, row_index: Option<usize>) -> Result<(), DBError> {
+        if value.eq(&FieldValue::Null) { return Ok(()) }
+
+        let index = self.index_on(column_name)?;
+        let existing_row = index
+            .get(value)
+            .and_then(|rows| rows.iter().find(|r| Some(**r) != row_index).copied());
+
+        match existing_row {
+            Some(existing_row) => Err(DBError::DuplicateValue(self.constraint_context(column_name, value, Some(existing_row)))),
+            None => Ok(()),
+        }
+    }
+
+
+    /// moves the row at `row_index` from `old_value` to `new_value` in the index on `column_name`
+    pub(super) fn move_in_index(&self, column_name: &str, old_value: &FieldValue, new_value: &FieldValue, row_index: usize) -> Result<(), DBError> {
+        let mut index = self.index_on(column_name)?;
+
+        if let Some(rows) = index.get_mut(old_value) {
+            rows.retain(|r| *r != row_index);
+            if rows.is_empty() { index.remove(old_value); }
+        }
+        index.entry(new_value.clone()).or_default().push(row_index);
+
+        save_index(INDEX_PATH, &self.name, column_name, index);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use crate::{config::INDEX_PATH, structures::{column::{Column, DataType, FieldValue}, db_err::DBError, filter::{FilterCondition, FilterConditionValue}, relation::{io::index_file_name, table::Table}}};
+
+    /// a table with a unique `email` column, holding one row per email. Its index is saved under `table_name`
+    fn emails_table(table_name: &str, emails: &[&str]) -> Table {
+        let columns = vec![
+            Column::new("id".to_owned(), DataType::Number, false),
+            Column::new("email".to_owned(), DataType::String, false),
+        ];
+        let mut table = Table::new(table_name.to_owned(), columns, true);
+        table.add_unique_constraint("email").unwrap();
+        for (id, email) in emails.iter().enumerate() {
+            table.insert_row(&HashMap::from([
+                ("id".to_owned(), FieldValue::Number(id as f64)),
+                ("email".to_owned(), FieldValue::String(email.to_string())),
+            ])).unwrap();
+        }
+        table
+    }
+
+    fn id_equals(id: f64) -> FilterCondition {
+        FilterCondition::Equal(FilterConditionValue::Number(id))
+    }
+
+    fn email(text: &str) -> FieldValue { FieldValue::String(text.to_owned()) }
+
+    #[test]
+    fn edits_can_not_duplicate_a_unique_value() {
+        let table_name = "unique test edits";
+        let mut table = emails_table(table_name, &["a@x.com", "b@x.com", "c@x.com"]);
+
+        let result = table.edit_rows("id".to_owned(), "email".to_owned(), id_equals(0.0), email("b@x.com"));
+        assert!(matches!(result, Err(DBError::DuplicateValue(context)) if context.row == Some(1)));
+
+        // a row can be set to the value it already has, or to a new one
+        assert_eq!(table.edit_rows("id".to_owned(), "email".to_owned(), id_equals(1.0), email("b@x.com")).unwrap(), 1);
+        assert_eq!(table.edit_rows("id".to_owned(), "email".to_owned(), id_equals(1.0), email("d@x.com")).unwrap(), 1);
+
+        // the index follows the edit, so the old value is free again and the new one is taken
+        assert_eq!(table.edit_rows("id".to_owned(), "email".to_owned(), id_equals(0.0), email("b@x.com")).unwrap(), 1);
+        let result = table.edit_rows("id".to_owned(), "email".to_owned(), id_equals(2.0), email("d@x.com"));
+        assert!(matches!(result, Err(DBError::DuplicateValue(_))));
+
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(table_name, "email")));
+    }
+
+    #[test]
+    fn only_nulls_can_be_written_to_several_rows() {
+        let table_name = "unique test many rows";
+        let mut table = emails_table(table_name, &["a@x.com", "b@x.com", "c@x.com"]);
+        let everyone = FilterCondition::GreaterThanOrEqualTo(FilterConditionValue::Number(0.0));
+
+        let result = table.edit_rows("id".to_owned(), "email".to_owned(), everyone.clone(), email("z@x.com"));
+        assert!(matches!(result, Err(DBError::DuplicateValue(_))));
+        assert_eq!(table.rows()[0]["email"], email("a@x.com"));
+
+        assert_eq!(table.edit_rows("id".to_owned(), "email".to_owned(), everyone, FieldValue::Null).unwrap(), 3);
+
+        let _ = fs::remove_file(format!("{}/{}", INDEX_PATH, index_file_name(table_name, "email")));
+    }
+}
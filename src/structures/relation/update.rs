@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::structures::{cancel::check_cancelled, column::FieldValue, db_err::DBError, filter::FilterCondition};
+
+use super::{search::non_index_row_matches_search_critieria, table::Table, versioning::ROW_VERSION_COLUMN};
+
+
+/// a row being edited by `Table::update_where()`.
+///
+/// reads see the values set so far, and only the values which are set are written back to the table.
+pub struct RowView<'a> {
+    row: &'a HashMap<String, FieldValue>,
+    changes: HashMap<String, FieldValue>,
+}
+
+
+impl<'a> RowView<'a> {
+
+    fn new(row: &'a HashMap<String, FieldValue>) -> Self {
+        RowView { row, changes: HashMap::new() }
+    }
+
+    pub fn get(&self, column_name: &str) -> Option<&FieldValue> {
+        self.changes.get(column_name).or_else(|| self.row.get(column_name))
+    }
+
+    /// gives the value of a number column, or `None` if it is null or not a number
+    pub fn number(&self, column_name: &str) -> Option<f64> {
+        match self.get(column_name) {
+            Some(FieldValue::Number(n)) => Some(*n),
+            _ => None
+        }
+    }
+
+    pub fn set(&mut self, column_name: &str, value: FieldValue) {
+        self.changes.insert(column_name.to_string(), value);
+    }
+}
+
+
+impl Table {
+
+    /// calls `update` on every row where the value in `column_name` matches `search_criteria`,
+    /// so new values can be computed from the existing ones.
+    ///
+    /// returns the number of rows which were changed.
+    ///
+    /// ## Example
+    /// ```ignore
+    /// // raise every price under 100 by 5%
+    /// table.update_where(&"Price".to_string(), FilterCondition::LessThan(FilterConditionValue::Number(100.0)), |row| {
+    ///     if let Some(price) = row.number("Price") {
+    ///         row.set("Price", FieldValue::Number(price * 1.05));
+    ///     }
+    /// })?;
+    /// ```
+    pub fn update_where<F>(&mut self, column_name: &String, search_criteria: FilterCondition, mut update: F) -> Result<u32, DBError> 
+    where F: FnMut(&mut RowView) {
+
+        if !self.is_valid_column(column_name) {
+            return Err(DBError::InvalidColumn(column_name.to_string()))
+        }
+
+        // work out every change before writing any of them, so a bad value doesn't leave the table half updated
+        let mut pending_changes: Vec<(usize, HashMap<String, FieldValue>)> = Vec::new();
+        let now = Utc::now();
+
+        for (row_index, row) in self.rows.iter().enumerate() {
+            check_cancelled()?;
+            if !self.is_live(row, now) { continue }
+
+            let row_value = row.get(column_name).unwrap_or(&FieldValue::Null);
+            if !non_index_row_matches_search_critieria(row_value, &search_criteria)? { continue }
+
+            let mut view = RowView::new(row);
+            update(&mut view);
+            if view.changes.is_empty() { continue }
+
+            if view.changes.contains_key(ROW_VERSION_COLUMN) {
+                return Err(DBError::MandatoryColumn(ROW_VERSION_COLUMN.to_string()));
+            }
+            for (col_name, new_value) in &view.changes {
+                let col = self.column(col_name.to_string()).ok_or(DBError::InvalidColumn(col_name.to_string()))?;
+                if !new_value.eq(&FieldValue::Null) && !col.get_data_type().eq(&new_value.data_type()) {
+                    return Err(DBError::MisMatchDataType(col.get_data_type().clone(), new_value.data_type()));
+                }
+            }
+            pending_changes.push((row_index, view.changes));
+        }
+
+        for (row_index, changes) in &pending_changes {
+            self.overwrite_row(*row_index, changes)?;
+        }
+
+        Ok(pending_changes.len() as u32)
+    }
+}
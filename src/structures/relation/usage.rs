@@ -0,0 +1,164 @@
+use std::{fmt, fs};
+
+use crate::{
+    config::{EXPORT_PATH, HISTORY_PATH, INDEX_PATH, RELATION_PATH, TEMP_PATH},
+    structures::{db_err::DBError, display_settings::DISPLAY_SETTINGS_FILE_NAME}
+};
+
+use super::{
+    annotations::ANNOTATIONS_FILE_NAME, display::DISPLAY_ORDER_FILE_NAME, index_stats::INDEX_STATS_FILE_NAME,
+    io::{index_file_name, TABLE_LOCK_EXTENSION}, layout::LAYOUTS_FILE_NAME, masking::MASKING_KEY_FILE_NAME, metadata::METADATA_FILE_NAME, summary::table_summary
+};
+
+
+/// files in the relation and index directories which hold settings for every table, rather than one table's data
+const SHARED_FILE_NAMES: [&str; 7] = [
+    METADATA_FILE_NAME, ANNOTATIONS_FILE_NAME, LAYOUTS_FILE_NAME, DISPLAY_ORDER_FILE_NAME, DISPLAY_SETTINGS_FILE_NAME, INDEX_STATS_FILE_NAME,
+    MASKING_KEY_FILE_NAME
+];
+
+
+/// the space one table takes up on disk
+#[derive(Debug, Clone)]
+pub struct TableUsage {
+    pub table: String,
+    /// the size of the table's file
+    pub data_bytes: u64,
+    /// the size of all of the table's index files
+    pub index_bytes: u64,
+}
+
+
+impl TableUsage {
+    pub fn total_bytes(&self) -> u64 { self.data_bytes + self.index_bytes }
+}
+
+
+/// where the space used by the database goes, found by `disk_usage()`
+#[derive(Debug, Clone)]
+pub struct UsageReport {
+    /// every saved table, largest first
+    pub tables: Vec<TableUsage>,
+    /// settings kept for every table, i.e. metadata, layouts and row notes
+    pub shared_bytes: u64,
+    /// the query history and the copy of the last changed table kept for undo
+    pub history_bytes: u64,
+    /// files left in the temp directory, i.e. spill files from an operation which was stopped
+    pub temp_bytes: u64,
+    /// tables exported to the export directory
+    pub export_bytes: u64,
+    /// files which don't belong to any saved table, with their paths and sizes. These are usually orphaned
+    /// indexes or table files which can't be read, see `check_integrity()`
+    pub unknown_files: Vec<(String, u64)>,
+}
+
+
+impl UsageReport {
+
+    pub fn unknown_bytes(&self) -> u64 { self.unknown_files.iter().map(|(_, bytes)| bytes).sum() }
+
+
+    pub fn total_bytes(&self) -> u64 {
+        let table_bytes: u64 = self.tables.iter().map(|t| t.total_bytes()).sum();
+        table_bytes + self.shared_bytes + self.history_bytes + self.temp_bytes + self.export_bytes + self.unknown_bytes()
+    }
+}
+
+
+impl fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for table in &self.tables {
+            writeln!(f, "{:>12}  {} ({} bytes data, {} bytes indexes)", table.total_bytes(), table.table, table.data_bytes, table.index_bytes)?;
+        }
+        writeln!(f, "{:>12}  shared settings", self.shared_bytes)?;
+        writeln!(f, "{:>12}  history", self.history_bytes)?;
+        writeln!(f, "{:>12}  temp files", self.temp_bytes)?;
+        writeln!(f, "{:>12}  exports", self.export_bytes)?;
+        writeln!(f, "{:>12}  unknown files", self.unknown_bytes())?;
+        for (path, bytes) in &self.unknown_files {
+            writeln!(f, "{:>12}    {}", bytes, path)?;
+        }
+        write!(f, "{:>12}  total", self.total_bytes())
+    }
+}
+
+
+/// the path and size of every file directly inside `directory`, sorted by path
+fn files_in(directory: &str) -> Result<Vec<(String, u64)>, DBError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(e) => e,
+        // nothing has been saved there yet
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut files: Vec<(String, u64)> = Vec::new();
+    for entry in entries.flatten() {
+        let metadata = entry.metadata().map_err(|e| DBError::IOFailure(entry.path().to_string_lossy().to_string(), e.to_string()))?;
+        if !metadata.is_file() { continue }
+        files.push( (entry.file_name().to_string_lossy().to_string(), metadata.len()) );
+    }
+    files.sort();
+    Ok(files)
+}
+
+
+fn total_size(directory: &str) -> Result<u64, DBError> {
+    Ok(files_in(directory)?.iter().map(|(_, bytes)| bytes).sum())
+}
+
+
+/// walks the database directories and adds up the space used by each table, along with everything which isn't a table.
+/// Only the start of each table file is read, so this is quick even for large tables.
+pub fn disk_usage() -> Result<UsageReport, DBError> {
+    let mut report = UsageReport {
+        tables: Vec::new(),
+        shared_bytes: 0,
+        history_bytes: total_size(HISTORY_PATH)?,
+        temp_bytes: total_size(TEMP_PATH)?,
+        export_bytes: total_size(EXPORT_PATH)?,
+        unknown_files: Vec::new(),
+    };
+
+    // the index files of each table, so anything left over in the index directory is known to be an orphan
+    let mut index_owners: Vec<(String, usize)> = Vec::new();
+
+    for (file_name, bytes) in files_in(RELATION_PATH)? {
+        if SHARED_FILE_NAMES.contains(&file_name.as_str()) {
+            report.shared_bytes += bytes;
+            continue;
+        }
+        // saves are locked on these, they are always empty
+        if file_name.starts_with("db_") && file_name.ends_with(&format!(".{}", TABLE_LOCK_EXTENSION)) { continue }
+
+        let summary = match file_name.starts_with("db_") && file_name.ends_with(".bin") {
+            true => table_summary(&format!("{}/{}", RELATION_PATH, file_name)).ok(),
+            false => None,
+        };
+        let summary = match summary {
+            Some(summary) => summary,
+            None => {
+                report.unknown_files.push( (format!("{}/{}", RELATION_PATH, file_name), bytes) );
+                continue;
+            }
+        };
+
+        for column in &summary.indexes {
+            index_owners.push( (index_file_name(&summary.name, column), report.tables.len()) );
+        }
+        report.tables.push( TableUsage { table: summary.name, data_bytes: bytes, index_bytes: 0 } );
+    }
+
+    for (file_name, bytes) in files_in(INDEX_PATH)? {
+        if SHARED_FILE_NAMES.contains(&file_name.as_str()) {
+            report.shared_bytes += bytes;
+            continue;
+        }
+        match index_owners.iter().find(|(index_file, _)| *index_file == file_name) {
+            Some((_, table)) => report.tables[*table].index_bytes += bytes,
+            None => report.unknown_files.push( (format!("{}/{}", INDEX_PATH, file_name), bytes) ),
+        }
+    }
+
+    report.tables.sort_by(|a, b| b.total_bytes().cmp(&a.total_bytes()).then_with(|| a.table.cmp(&b.table)));
+    Ok(report)
+}
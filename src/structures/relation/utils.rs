@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashMap};
 
 use crate::{config::INDEX_PATH, structures::{column::{Column, FieldValue}, db_err::DBError}};
 
-use super::{io::{index_file_name, load_index}, table::Table};
+use super::{io::{index_file_name, load_index}, soft_delete::{DELETED_AT_COLUMN, INSERTED_AT_COLUMN}, table::Table, versioning::ROW_VERSION_COLUMN};
 
 
 
@@ -27,6 +27,17 @@ impl Table {
     } 
     
     pub fn primary_keys(&self) -> &Vec<Column> { &self.primary_keys }
+
+
+    /// the columns a value is given for when a row is inserted, in order. Computed columns and the columns
+    /// kept up to date by the table itself (soft deletes and row versions) are left out.
+    pub fn writable_columns(&self) -> Vec<&Column> {
+        self.columns
+            .iter()
+            .filter(|c| !self.is_computed_column(c.get_name()))
+            .filter(|c| ![INSERTED_AT_COLUMN, DELETED_AT_COLUMN, ROW_VERSION_COLUMN].contains(&c.get_name()))
+            .collect()
+    }
     
     
     /// determines if a column with the given name exists in the database.
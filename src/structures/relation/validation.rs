@@ -0,0 +1,79 @@
+use chrono::Utc;
+
+use crate::structures::{cancel::check_cancelled, column::FieldValue, db_err::DBError, filter::FilterCondition, validation::{RuleViolations, ValidationRule}};
+
+use super::{search::non_index_row_matches_search_critieria, table::Table};
+
+
+impl Table {
+
+    pub fn validation_rules(&self) -> &Vec<ValidationRule> { &self.validation_rules }
+
+
+    /// saves a named rule which every row should meet. The rule is only checked when `Table::validate()` is called,
+    /// so rows which break it can still be inserted.
+    ///
+    /// ## Example
+    /// `table.add_validation_rule("valid age", "Age", "between numbers 0 120")`
+    pub fn add_validation_rule(&mut self, name: &str, column_name: &str, condition: &str) -> Result<(), DBError> {
+        if !self.is_valid_column(&column_name.to_string()) {
+            return Err(DBError::InvalidColumn(column_name.to_string()));
+        }
+        if FilterCondition::parse_str(condition).is_none() {
+            return Err(DBError::InvalidQuery(condition.to_string()));
+        }
+
+        // adding a rule with the same name replaces the old one
+        self.validation_rules.retain(|r| r.name != name);
+        self.validation_rules.push(ValidationRule {
+            name: name.to_string(),
+            column: column_name.to_string(),
+            condition: condition.to_string(),
+        });
+        Ok(())
+    }
+
+
+    /// removes the rule called `name`, returning false if there was no rule with that name
+    pub fn remove_validation_rule(&mut self, name: &str) -> bool {
+        let number_of_rules = self.validation_rules.len();
+        self.validation_rules.retain(|r| r.name != name);
+        number_of_rules != self.validation_rules.len()
+    }
+
+
+    /// checks every row against every validation rule, returning the rows which break each rule.
+    /// rules which every row meets are left out.
+    ///
+    /// like SQL check constraints, a null cell only breaks a rule if the rule is about nulls.
+    pub fn validate(&self) -> Result<Vec<RuleViolations>, DBError> {
+
+        let mut all_violations: Vec<RuleViolations> = Vec::new();
+        let now = Utc::now();
+
+        for rule in &self.validation_rules {
+            let condition = FilterCondition::parse_str(&rule.condition)
+                .ok_or(DBError::InvalidQuery(rule.condition.clone()))?;
+            let checks_for_nulls = matches!(condition, FilterCondition::Null | FilterCondition::NotNull);
+
+            let mut row_indices: Vec<usize> = Vec::new();
+            for (row_index, row) in self.rows.iter().enumerate() {
+                check_cancelled()?;
+                if !self.is_live(row, now) { continue }
+
+                let value = row.get(&rule.column).ok_or(DBError::InvalidColumn(rule.column.clone()))?;
+                if value.eq(&FieldValue::Null) && !checks_for_nulls { continue }
+
+                if !non_index_row_matches_search_critieria(value, &condition)? {
+                    row_indices.push(row_index);
+                }
+            }
+
+            if !row_indices.is_empty() {
+                all_violations.push(RuleViolations { rule: rule.name.clone(), row_indices });
+            }
+        }
+
+        Ok(all_violations)
+    }
+}
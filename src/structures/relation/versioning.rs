@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use crate::structures::{column::{Column, DataType, FieldValue}, db_err::DBError};
+
+use super::table::Table;
+
+
+/// the column which holds the version counter of each row, when row versions are enabled
+pub const ROW_VERSION_COLUMN: &str = "Row Version";
+
+
+/// increments the version counter of a row. Does nothing if the row doesn't have a version.
+pub fn bump_row_version(row: &mut HashMap<String, FieldValue>) {
+    if let Some(FieldValue::Number(version)) = row.get_mut(ROW_VERSION_COLUMN) {
+        *version += 1.0;
+    }
+}
+
+
+impl Table {
+
+    pub fn row_versions_enabled(&self) -> bool { self.row_versions }
+
+
+    /// adds a version counter to every row in the table, stored in the "Row Version" column.
+    ///
+    /// the counter starts at 1, and goes up by one every time the row is edited. This allows
+    /// compare-and-swap style updates using `Table::update_if_version()`.
+    pub fn enable_row_versions(&mut self) -> Result<(), DBError> {
+        if self.row_versions { return Ok(()) }
+
+        if self.is_valid_column(&ROW_VERSION_COLUMN.to_string()) {
+            return Err(DBError::MandatoryColumn(ROW_VERSION_COLUMN.to_string()));
+        }
+        self.columns.push( Column::new(ROW_VERSION_COLUMN.to_string(), DataType::Number, false) );
+
+        for row in &mut self.rows {
+            row.insert( ROW_VERSION_COLUMN.to_string(), FieldValue::Number(1.0) );
+        }
+
+        self.row_versions = true;
+        Ok(())
+    }
+
+
+    /// fills in the version of a row that is about to be inserted
+    pub(super) fn stamp_row_version(&self, row: &mut HashMap<String, FieldValue>) {
+        row.insert( ROW_VERSION_COLUMN.to_string(), FieldValue::Number(1.0) );
+    }
+
+
+    /// gives the version counter of the row at `row_index`
+    pub fn row_version(&self, row_index: usize) -> Result<u64, DBError> {
+        if !self.row_versions {
+            return Err(DBError::ActionNotImplemented("row versions on a table without row versions".to_owned()));
+        }
+
+        let row = self.get_row(row_index).ok_or(DBError::RowNotFound(row_index))?;
+        match row.get(ROW_VERSION_COLUMN) {
+            Some(FieldValue::Number(version)) => Ok(*version as u64),
+            Some(other) => Err(DBError::MisMatchDataType(DataType::Number, other.data_type())),
+            None => Err(DBError::InvalidColumn(ROW_VERSION_COLUMN.to_string())),
+        }
+    }
+
+
+    /// updates the row at `row_index` with `new_values`, but only if the row is still at `expected_version`.
+    ///
+    /// if the row was changed since the caller last read it, a `DBError::VersionConflict` is returned
+    /// and nothing is changed. Returns the new version of the row if the update succeeds.
+    pub fn update_if_version(
+        &mut self,
+        row_index: usize,
+        expected_version: u64,
+        new_values: &HashMap<String, FieldValue>
+    ) -> Result<u64, DBError> {
+
+        let current_version = self.row_version(row_index)?;
+        if current_version != expected_version {
+            return Err(DBError::VersionConflict(expected_version, current_version));
+        }
+
+        if new_values.contains_key(ROW_VERSION_COLUMN) {
+            return Err(DBError::MandatoryColumn(ROW_VERSION_COLUMN.to_string()));
+        }
+
+        // bumps the version as well
+        self.overwrite_row(row_index, new_values)?;
+
+        Ok(current_version + 1)
+    }
+}
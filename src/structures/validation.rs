@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+
+/// a named condition that every row of a table should meet, i.e. "valid age" is `Age BETWEEN NUMBERS 0 120`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationRule {
+    pub name: String,
+    pub column: String,
+    /// the condition in its text form, see `FilterCondition::parse_str()`
+    pub condition: String,
+}
+
+
+/// the rows which broke a validation rule, found by `Table::validate()`
+#[derive(Debug, Clone)]
+pub struct RuleViolations {
+    pub rule: String,
+    pub row_indices: Vec<usize>,
+}
+
+impl RuleViolations {
+    pub fn count(&self) -> usize { self.row_indices.len() }
+}